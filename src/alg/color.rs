@@ -1,4 +1,60 @@
 use image::{DynamicImage, GenericImageView, Rgb, RgbImage, Rgba};
+use std::fmt;
+use std::str::FromStr;
+
+use super::planes::Planes;
+use crate::Error;
+
+/// A color model whose pixels can be converted from and to RGB.
+///
+/// Channel values keep their natural ranges (hue in degrees, signed chroma), they are only mapped
+/// to 8-bit samples by `encode_u8` when an image is shown or saved.
+pub trait ColorModel: Sized {
+    /// Parameters of the conversion, such as the reference white.
+    type Params: Copy + Default + 'static;
+    /// Lowercase names of the three channels, e.g. `['h', 's', 'v']`.
+    const CHANNELS: [char; 3];
+    /// Index of the channel holding the brightness (V, L, I or Y).
+    const INTENSITY: usize;
+    /// Value range of each channel.
+    const RANGES: [(f32, f32); 3];
+
+    fn from_rgba_with(pixel: Rgba<u8>, params: Self::Params) -> Self;
+    fn to_rgb8_with(&self, params: Self::Params) -> Rgb<u8>;
+    fn from_values(values: [f32; 3]) -> Self;
+    fn values(&self) -> [f32; 3];
+
+    fn from_rgba(pixel: Rgba<u8>) -> Self {
+        Self::from_rgba_with(pixel, Self::Params::default())
+    }
+
+    fn to_rgb8(&self) -> Rgb<u8> {
+        self.to_rgb8_with(Self::Params::default())
+    }
+
+    /// Maps the channel ranges to 0~255 so the pixel can be shown as an RGB pixel.
+    fn encode_u8(&self) -> Rgb<u8> {
+        let values = self.values();
+        Rgb(std::array::from_fn(|i| {
+            encode_value(values[i], Self::RANGES[i])
+        }))
+    }
+}
+
+/// Converts a value in 0~1 to an 8-bit sample. Values out of range are clamped and NaN gives 0, so
+/// a conversion never wraps around.
+pub fn unit_to_u8(value: f32) -> u8 {
+    if value.is_nan() {
+        0
+    } else {
+        (value.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+}
+
+/// Maps `value` from `range` to an 8-bit sample.
+pub fn encode_value(value: f32, range: (f32, f32)) -> u8 {
+    unit_to_u8((value - range.0) / (range.1 - range.0))
+}
 
 pub struct Hsv {
     pub h: f32,
@@ -6,20 +62,54 @@ pub struct Hsv {
     pub v: f32,
 }
 
-impl Hsv {
-    pub fn as_rgb8(&self) -> Rgb<u8> {
-        Rgb([
-            (self.h * 255.0).round() as u8,
-            (self.s * 255.0).round() as u8,
-            (self.v * 255.0).round() as u8,
-        ])
+impl ColorModel for Hsv {
+    type Params = ();
+    const CHANNELS: [char; 3] = ['h', 's', 'v'];
+    const INTENSITY: usize = 2;
+    const RANGES: [(f32, f32); 3] = [(0.0, 360.0), (0.0, 1.0), (0.0, 1.0)];
+
+    fn from_rgba_with(pixel: Rgba<u8>, _: ()) -> Self {
+        let r = pixel[0] as f32 / 255.0;
+        let g = pixel[1] as f32 / 255.0;
+        let b = pixel[2] as f32 / 255.0;
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let mut h = 0.0;
+        let mut s = 0.0;
+        let v = max;
+        let d = max - min;
+        if max != 0.0 {
+            s = d / max;
+        }
+        if max != min {
+            h = match max {
+                x if x == r => (g - b) / d + (if g < b { 6.0 } else { 0.0 }),
+                x if x == g => (b - r) / d + 2.0,
+                x if x == b => (r - g) / d + 4.0,
+                _ => 0.0,
+            };
+            h *= 60.0;
+        }
+        Hsv { h, s, v }
+    }
+
+    fn from_values(values: [f32; 3]) -> Self {
+        Hsv {
+            h: values[0],
+            s: values[1],
+            v: values[2],
+        }
     }
 
-    pub fn to_rgb8(&self) -> Rgb<u8> {
+    fn values(&self) -> [f32; 3] {
+        [self.h, self.s, self.v]
+    }
+
+    fn to_rgb8_with(&self, _: ()) -> Rgb<u8> {
         let (r, g, b) = if self.s == 0.0 {
             (self.v, self.v, self.v)
         } else {
-            let h = self.h * 6.0;
+            let h = self.h.rem_euclid(360.0) / 60.0;
             let s = self.s;
             let v = self.v;
             let c = v * s;
@@ -36,16 +126,23 @@ impl Hsv {
             let m = v - c;
             (r + m, g + m, b + m)
         };
-        Rgb([
-            (r * 255.0).round() as u8,
-            (g * 255.0).round() as u8,
-            (b * 255.0).round() as u8,
-        ])
+        Rgb([unit_to_u8(r), unit_to_u8(g), unit_to_u8(b)])
     }
 }
 
-impl From<Rgba<u8>> for Hsv {
-    fn from(pixel: Rgba<u8>) -> Self {
+pub struct Hsl {
+    pub h: f32,
+    pub s: f32,
+    pub l: f32,
+}
+
+impl ColorModel for Hsl {
+    type Params = ();
+    const CHANNELS: [char; 3] = ['h', 's', 'l'];
+    const INTENSITY: usize = 2;
+    const RANGES: [(f32, f32); 3] = [(0.0, 360.0), (0.0, 1.0), (0.0, 1.0)];
+
+    fn from_rgba_with(pixel: Rgba<u8>, _: ()) -> Self {
         let r = pixel[0] as f32 / 255.0;
         let g = pixel[1] as f32 / 255.0;
         let b = pixel[2] as f32 / 255.0;
@@ -53,10 +150,10 @@ impl From<Rgba<u8>> for Hsv {
         let min = r.min(g).min(b);
         let mut h = 0.0;
         let mut s = 0.0;
-        let v = max;
+        let l = (max + min) / 2.0;
         let d = max - min;
-        if max != 0.0 {
-            s = d / max;
+        if l > 0.0 && l < 1.0 {
+            s = d / (1.0 - (2.0 * l - 1.0).abs());
         }
         if max != min {
             h = match max {
@@ -65,32 +162,28 @@ impl From<Rgba<u8>> for Hsv {
                 x if x == b => (r - g) / d + 4.0,
                 _ => 0.0,
             };
-            h /= 6.0;
+            h *= 60.0;
         }
-        Hsv { h, s, v }
+        Hsl { h, s, l }
     }
-}
 
-struct Hsl {
-    h: f32,
-    s: f32,
-    l: f32,
-}
+    fn from_values(values: [f32; 3]) -> Self {
+        Hsl {
+            h: values[0],
+            s: values[1],
+            l: values[2],
+        }
+    }
 
-impl Hsl {
-    fn as_rgb8(&self) -> Rgb<u8> {
-        Rgb([
-            (self.h * 255.0).round() as u8,
-            (self.s * 255.0).round() as u8,
-            (self.l * 255.0).round() as u8,
-        ])
+    fn values(&self) -> [f32; 3] {
+        [self.h, self.s, self.l]
     }
 
-    fn to_rgb8(&self) -> Rgb<u8> {
+    fn to_rgb8_with(&self, _: ()) -> Rgb<u8> {
         let (r, g, b) = if self.s == 0.0 {
             (self.l, self.l, self.l)
         } else {
-            let h = self.h * 6.0;
+            let h = self.h.rem_euclid(360.0) / 60.0;
             let s = self.s;
             let l = self.l;
             let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
@@ -107,68 +200,70 @@ impl Hsl {
             let m = l - 0.5 * c;
             (r + m, g + m, b + m)
         };
-        Rgb([
-            (r * 255.0).round() as u8,
-            (g * 255.0).round() as u8,
-            (b * 255.0).round() as u8,
-        ])
+        Rgb([unit_to_u8(r), unit_to_u8(g), unit_to_u8(b)])
     }
 }
 
-impl From<Rgba<u8>> for Hsl {
-    fn from(pixel: Rgba<u8>) -> Self {
+pub struct Hsi {
+    pub h: f32,
+    pub s: f32,
+    pub i: f32,
+}
+
+impl ColorModel for Hsi {
+    type Params = ();
+    const CHANNELS: [char; 3] = ['h', 's', 'i'];
+    const INTENSITY: usize = 2;
+    const RANGES: [(f32, f32); 3] = [(0.0, 360.0), (0.0, 1.0), (0.0, 1.0)];
+
+    fn from_rgba_with(pixel: Rgba<u8>, _: ()) -> Self {
         let r = pixel[0] as f32 / 255.0;
         let g = pixel[1] as f32 / 255.0;
         let b = pixel[2] as f32 / 255.0;
-        let max = r.max(g).max(b);
-        let min = r.min(g).min(b);
         let mut h = 0.0;
         let mut s = 0.0;
-        let l = (max + min) / 2.0;
-        let d = max - min;
-        if l > 0.0 && l < 1.0 {
-            s = d / (1.0 - (2.0 * l - 1.0).abs());
-        }
+        let i = (r + g + b) / 3.0;
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        // Greys have no hue; testing the channels rather than `s` avoids a 0/0 hue when rounding
+        // leaves `s` slightly off zero.
         if max != min {
-            h = match max {
-                x if x == r => (g - b) / d + (if g < b { 6.0 } else { 0.0 }),
-                x if x == g => (b - r) / d + 2.0,
-                x if x == b => (r - g) / d + 4.0,
-                _ => 0.0,
-            };
-            h /= 6.0;
+            s = 1.0 - min / i;
+            h = 0.5 * ((r - g) + (r - b)) / ((r - g).powi(2) + (r - b) * (g - b)).sqrt();
+            h = h.clamp(-1.0, 1.0).acos();
+            if b > g {
+                h = 2.0 * std::f32::consts::PI - h;
+            }
+            h = h.to_degrees();
         }
-        Hsl { h, s, l }
+        Hsi { h, s, i }
     }
-}
 
-pub struct Hsi {
-    h: f32,
-    s: f32,
-    i: f32,
-}
+    fn from_values(values: [f32; 3]) -> Self {
+        Hsi {
+            h: values[0],
+            s: values[1],
+            i: values[2],
+        }
+    }
 
-impl Hsi {
-    fn as_rgb8(&self) -> Rgb<u8> {
-        Rgb([
-            (self.h * 255.0).round() as u8,
-            (self.s * 255.0).round() as u8,
-            (self.i * 255.0).round() as u8,
-        ])
+    fn values(&self) -> [f32; 3] {
+        [self.h, self.s, self.i]
     }
 
-    fn to_rgb8(&self) -> Rgb<u8> {
+    fn to_rgb8_with(&self, _: ()) -> Rgb<u8> {
         let r;
         let g;
         let b;
-        let h = self.h * 2.0 * std::f32::consts::PI;
+        let h = self.h.rem_euclid(360.0).to_radians();
         let s = self.s;
         let i = self.i;
-        if h >= 0.0 && h < 2.0 * std::f32::consts::PI / 3.0 {
+        if (0.0..2.0 * std::f32::consts::PI / 3.0).contains(&h) {
             b = i * (1.0 - s);
             r = i * (1.0 + s * (h.cos() / ((60.0f32).to_radians() - h).cos()));
             g = 3.0 * i - (r + b);
-        } else if h >= 2.0 * std::f32::consts::PI / 3.0 && h < 4.0 * std::f32::consts::PI / 3.0 {
+        } else if (2.0 * std::f32::consts::PI / 3.0..4.0 * std::f32::consts::PI / 3.0).contains(&h)
+        {
             let h = h - 2.0 * std::f32::consts::PI / 3.0;
             r = i * (1.0 - s);
             g = i * (1.0 + s * (h.cos() / ((60.0f32).to_radians() - h).cos()));
@@ -179,169 +274,943 @@ impl Hsi {
             b = i * (1.0 + s * (h.cos() / ((60.0f32).to_radians() - h).cos()));
             r = 3.0 * i - (g + b);
         }
-        Rgb([
-            (r * 255.0).round() as u8,
-            (g * 255.0).round() as u8,
-            (b * 255.0).round() as u8,
-        ])
+        Rgb([unit_to_u8(r), unit_to_u8(g), unit_to_u8(b)])
+    }
+}
+
+/// Matrix coefficients of the YCbCr standards.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum YCbCrMatrix {
+    #[default]
+    Bt601,
+    Bt709,
+    Bt2020,
+}
+
+impl YCbCrMatrix {
+    /// Luma weights `(Kr, Kb)` of red and blue.
+    pub fn coefficients(self) -> (f32, f32) {
+        match self {
+            YCbCrMatrix::Bt601 => (0.299, 0.114),
+            YCbCrMatrix::Bt709 => (0.2126, 0.0722),
+            YCbCrMatrix::Bt2020 => (0.2627, 0.0593),
+        }
     }
 }
 
-impl From<Rgba<u8>> for Hsi {
-    fn from(pixel: Rgba<u8>) -> Self {
+/// Quantization range of YCbCr: full (0~255) or studio (16~235 for luma, 16~240 for chroma).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum YCbCrRange {
+    #[default]
+    Full,
+    #[cfg_attr(feature = "cli", value(alias = "limited"))]
+    Studio,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct YCbCrStandard {
+    pub matrix: YCbCrMatrix,
+    pub range: YCbCrRange,
+}
+
+/// Digital YCbCr. The values are the 8-bit code values divided by 255, with the chroma centered on
+/// zero, so `encode_u8` gives the code values of the standard.
+pub struct YCbCr {
+    pub y: f32,
+    pub cb: f32,
+    pub cr: f32,
+}
+
+impl ColorModel for YCbCr {
+    type Params = YCbCrStandard;
+    const CHANNELS: [char; 3] = ['y', 'b', 'r'];
+    const INTENSITY: usize = 0;
+    const RANGES: [(f32, f32); 3] = [
+        (0.0, 1.0),
+        (-128.0 / 255.0, 127.0 / 255.0),
+        (-128.0 / 255.0, 127.0 / 255.0),
+    ];
+
+    fn from_rgba_with(pixel: Rgba<u8>, standard: YCbCrStandard) -> Self {
         let r = pixel[0] as f32 / 255.0;
         let g = pixel[1] as f32 / 255.0;
         let b = pixel[2] as f32 / 255.0;
-        let mut h = 0.0;
-        let mut s = 0.0;
-        let i = (r + g + b) / 3.0;
-        let min = r.min(g).min(b);
-        if i != 0.0 {
-            s = 1.0 - min / i;
+        let (kr, kb) = standard.matrix.coefficients();
+        let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+        let pb = (b - y) / (2.0 * (1.0 - kb));
+        let pr = (r - y) / (2.0 * (1.0 - kr));
+        match standard.range {
+            YCbCrRange::Full => YCbCr { y, cb: pb, cr: pr },
+            YCbCrRange::Studio => YCbCr {
+                y: (16.0 + 219.0 * y) / 255.0,
+                cb: 224.0 * pb / 255.0,
+                cr: 224.0 * pr / 255.0,
+            },
         }
-        if s != 0.0 {
-            h = 0.5 * ((r - g) + (r - b)) / ((r - g).powi(2) + (r - b) * (g - b)).sqrt();
-            h = h.acos();
-            if b > g {
-                h = 2.0 * std::f32::consts::PI - h;
-            }
-            h = h / (2.0 * std::f32::consts::PI);
+    }
+
+    fn from_values(values: [f32; 3]) -> Self {
+        YCbCr {
+            y: values[0],
+            cb: values[1],
+            cr: values[2],
         }
-        Hsi { h, s, i }
+    }
+
+    fn values(&self) -> [f32; 3] {
+        [self.y, self.cb, self.cr]
+    }
+
+    fn to_rgb8_with(&self, standard: YCbCrStandard) -> Rgb<u8> {
+        let (y, pb, pr) = match standard.range {
+            YCbCrRange::Full => (self.y, self.cb, self.cr),
+            YCbCrRange::Studio => (
+                (self.y * 255.0 - 16.0) / 219.0,
+                self.cb * 255.0 / 224.0,
+                self.cr * 255.0 / 224.0,
+            ),
+        };
+        let (kr, kb) = standard.matrix.coefficients();
+        let r = y + 2.0 * (1.0 - kr) * pr;
+        let b = y + 2.0 * (1.0 - kb) * pb;
+        let g = (y - kr * r - kb * b) / (1.0 - kr - kb);
+        Rgb([unit_to_u8(r), unit_to_u8(g), unit_to_u8(b)])
     }
 }
 
-pub struct Yuv {
-    y: f32,
-    u: f32,
-    v: f32,
+/// Reference white of the CIE color spaces.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum WhitePoint {
+    #[default]
+    D65,
+    D50,
 }
 
-#[allow(dead_code)]
-impl Yuv {
-    fn as_rgb8(&self) -> Rgb<u8> {
-        Rgb([
-            (self.y * 255.0).round() as u8,
-            ((self.u + 0.5) * 255.0).round() as u8,
-            ((self.v + 0.5) * 255.0).round() as u8,
-        ])
+impl WhitePoint {
+    /// XYZ coordinates of the white, normalized to Y = 1.
+    pub fn xyz(self) -> [f32; 3] {
+        match self {
+            WhitePoint::D65 => [0.95047, 1.0, 1.08883],
+            WhitePoint::D50 => [0.96422, 1.0, 0.82521],
+        }
     }
+}
 
-    fn to_rgb8(&self) -> Rgb<u8> {
-        let y = self.y;
-        let u = self.u;
-        let v = self.v;
-        let r = y + 1.4075 * v;
-        let g = y - 0.3455 * u - 0.7169 * v;
-        let b = y + 1.7790 * u;
-        Rgb([
-            (r * 255.0).round() as u8,
-            (g * 255.0).round() as u8,
-            (b * 255.0).round() as u8,
-        ])
-    }
-}
-
-impl From<Rgba<u8>> for Yuv {
-    fn from(pixel: Rgba<u8>) -> Self {
-        let r = pixel[0] as f32 / 255.0;
-        let g = pixel[1] as f32 / 255.0;
-        let b = pixel[2] as f32 / 255.0;
-        let y = 0.299 * r + 0.587 * g + 0.114 * b;
-        let u = -0.169 * r - 0.331 * g + 0.5 * b;
-        let v = 0.5 * r - 0.419 * g - 0.081 * b;
-        Yuv { y, u, v }
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
     }
 }
 
-pub fn rgb_to_hsv(image: &DynamicImage) -> DynamicImage {
-    let mut out = RgbImage::new(image.width(), image.height());
-    for (x, y, pixel) in image.pixels() {
-        let hsv = Hsv::from(pixel).as_rgb8();
-        out.put_pixel(x, y, hsv);
+fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
     }
-    DynamicImage::from(out)
 }
 
-pub fn hsv_to_rgb(image: &DynamicImage) -> DynamicImage {
-    let mut out = RgbImage::new(image.width(), image.height());
-    for (x, y, pixel) in image.pixels() {
-        let rgb = Hsv {
-            h: pixel[0] as f32 / 255.0,
-            s: pixel[1] as f32 / 255.0,
-            v: pixel[2] as f32 / 255.0,
+fn mul3(m: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|i| m[i][0] * v[0] + m[i][1] * v[1] + m[i][2] * v[2])
+}
+
+// Linear sRGB to XYZ under D65, and the Bradford adaptation between D65 and D50.
+const RGB_TO_XYZ: [[f32; 3]; 3] = [
+    [0.4124564, 0.3575761, 0.1804375],
+    [0.2126729, 0.7151522, 0.0721750],
+    [0.0193339, 0.119192, 0.9503041],
+];
+const XYZ_TO_RGB: [[f32; 3]; 3] = [
+    [3.2404542, -1.5371385, -0.4985314],
+    [-0.969266, 1.8760108, 0.0415560],
+    [0.0556434, -0.2040259, 1.0572252],
+];
+const D65_TO_D50: [[f32; 3]; 3] = [
+    [1.0478112, 0.0228866, -0.0501270],
+    [0.0295424, 0.9904844, -0.0170491],
+    [-0.0092345, 0.0150436, 0.7521316],
+];
+const D50_TO_D65: [[f32; 3]; 3] = [
+    [0.9555766, -0.0230393, 0.0631636],
+    [-0.0282895, 1.0099416, 0.0210077],
+    [0.0122982, -0.0204830, 1.3299098],
+];
+
+/// CIE 1931 XYZ relative to the chosen white point, with Y = 1 for the white.
+pub struct Xyz {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl ColorModel for Xyz {
+    type Params = WhitePoint;
+    const CHANNELS: [char; 3] = ['x', 'y', 'z'];
+    const INTENSITY: usize = 1;
+    const RANGES: [(f32, f32); 3] = [(0.0, 1.0), (0.0, 1.0), (0.0, 1.1)];
+
+    fn from_rgba_with(pixel: Rgba<u8>, white: WhitePoint) -> Self {
+        let rgb = [0, 1, 2].map(|i| srgb_to_linear(pixel[i] as f32 / 255.0));
+        let mut xyz = mul3(&RGB_TO_XYZ, rgb);
+        if white == WhitePoint::D50 {
+            xyz = mul3(&D65_TO_D50, xyz);
         }
-        .to_rgb8();
-        out.put_pixel(x, y, rgb);
+        Xyz::from_values(xyz)
+    }
+
+    fn from_values(values: [f32; 3]) -> Self {
+        Xyz {
+            x: values[0],
+            y: values[1],
+            z: values[2],
+        }
+    }
+
+    fn values(&self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    fn to_rgb8_with(&self, white: WhitePoint) -> Rgb<u8> {
+        let mut xyz = self.values();
+        if white == WhitePoint::D50 {
+            xyz = mul3(&D50_TO_D65, xyz);
+        }
+        let rgb = mul3(&XYZ_TO_RGB, xyz);
+        Rgb(rgb.map(|c| unit_to_u8(linear_to_srgb(c))))
     }
-    DynamicImage::from(out)
 }
 
-pub fn rgb_to_hsl(image: &DynamicImage) -> DynamicImage {
-    let mut out = RgbImage::new(image.width(), image.height());
-    for (x, y, pixel) in image.pixels() {
-        let hsl = Hsl::from(pixel).as_rgb8();
-        out.put_pixel(x, y, hsl);
+const CIE_EPSILON: f32 = 216.0 / 24389.0;
+const CIE_KAPPA: f32 = 24389.0 / 27.0;
+
+fn lab_f(t: f32) -> f32 {
+    if t > CIE_EPSILON {
+        t.cbrt()
+    } else {
+        (CIE_KAPPA * t + 16.0) / 116.0
     }
-    DynamicImage::from(out)
 }
 
-pub fn hsl_to_rgb(image: &DynamicImage) -> DynamicImage {
-    let mut out = RgbImage::new(image.width(), image.height());
-    for (x, y, pixel) in image.pixels() {
-        let rgb = Hsl {
-            h: pixel[0] as f32 / 255.0,
-            s: pixel[1] as f32 / 255.0,
-            l: pixel[2] as f32 / 255.0,
+fn lab_f_inv(t: f32) -> f32 {
+    let t3 = t * t * t;
+    if t3 > CIE_EPSILON {
+        t3
+    } else {
+        (116.0 * t - 16.0) / CIE_KAPPA
+    }
+}
+
+/// CIE 1976 L*a*b*, with L* in 0~100.
+pub struct Lab {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+impl ColorModel for Lab {
+    type Params = WhitePoint;
+    const CHANNELS: [char; 3] = ['l', 'a', 'b'];
+    const INTENSITY: usize = 0;
+    const RANGES: [(f32, f32); 3] = [(0.0, 100.0), (-128.0, 128.0), (-128.0, 128.0)];
+
+    fn from_rgba_with(pixel: Rgba<u8>, white: WhitePoint) -> Self {
+        let xyz = Xyz::from_rgba_with(pixel, white).values();
+        let n = white.xyz();
+        let [fx, fy, fz] = [0, 1, 2].map(|i| lab_f(xyz[i] / n[i]));
+        Lab {
+            l: 116.0 * fy - 16.0,
+            a: 500.0 * (fx - fy),
+            b: 200.0 * (fy - fz),
         }
-        .to_rgb8();
-        out.put_pixel(x, y, rgb);
     }
-    DynamicImage::from(out)
+
+    fn from_values(values: [f32; 3]) -> Self {
+        Lab {
+            l: values[0],
+            a: values[1],
+            b: values[2],
+        }
+    }
+
+    fn values(&self) -> [f32; 3] {
+        [self.l, self.a, self.b]
+    }
+
+    fn to_rgb8_with(&self, white: WhitePoint) -> Rgb<u8> {
+        let fy = (self.l + 16.0) / 116.0;
+        let f = [fy + self.a / 500.0, fy, fy - self.b / 200.0];
+        let n = white.xyz();
+        let xyz: [f32; 3] = std::array::from_fn(|i| lab_f_inv(f[i]) * n[i]);
+        Xyz::from_values(xyz).to_rgb8_with(white)
+    }
 }
 
-pub fn rgb_to_hsi(image: &DynamicImage) -> DynamicImage {
-    let mut out = RgbImage::new(image.width(), image.height());
-    for (x, y, pixel) in image.pixels() {
-        let hsi = Hsi::from(pixel).as_rgb8();
-        out.put_pixel(x, y, hsi);
+fn uv_prime(xyz: [f32; 3]) -> (f32, f32) {
+    let d = xyz[0] + 15.0 * xyz[1] + 3.0 * xyz[2];
+    if d == 0.0 {
+        (0.0, 0.0)
+    } else {
+        (4.0 * xyz[0] / d, 9.0 * xyz[1] / d)
     }
-    DynamicImage::from(out)
 }
 
-pub fn hsi_to_rgb(image: &DynamicImage) -> DynamicImage {
-    let mut out = RgbImage::new(image.width(), image.height());
-    for (x, y, pixel) in image.pixels() {
-        let rgb = Hsi {
-            h: pixel[0] as f32 / 255.0,
-            s: pixel[1] as f32 / 255.0,
-            i: pixel[2] as f32 / 255.0,
+/// CIE 1976 L*u*v*, with L* in 0~100.
+pub struct Luv {
+    pub l: f32,
+    pub u: f32,
+    pub v: f32,
+}
+
+impl ColorModel for Luv {
+    type Params = WhitePoint;
+    const CHANNELS: [char; 3] = ['l', 'u', 'v'];
+    const INTENSITY: usize = 0;
+    const RANGES: [(f32, f32); 3] = [(0.0, 100.0), (-100.0, 200.0), (-150.0, 150.0)];
+
+    fn from_rgba_with(pixel: Rgba<u8>, white: WhitePoint) -> Self {
+        let xyz = Xyz::from_rgba_with(pixel, white).values();
+        let (un, vn) = uv_prime(white.xyz());
+        let (u, v) = uv_prime(xyz);
+        let l = 116.0 * lab_f(xyz[1]) - 16.0;
+        Luv {
+            l,
+            u: 13.0 * l * (u - un),
+            v: 13.0 * l * (v - vn),
         }
-        .to_rgb8();
-        out.put_pixel(x, y, rgb);
     }
-    DynamicImage::from(out)
+
+    fn from_values(values: [f32; 3]) -> Self {
+        Luv {
+            l: values[0],
+            u: values[1],
+            v: values[2],
+        }
+    }
+
+    fn values(&self) -> [f32; 3] {
+        [self.l, self.u, self.v]
+    }
+
+    fn to_rgb8_with(&self, white: WhitePoint) -> Rgb<u8> {
+        if self.l <= 0.0 {
+            return Rgb([0, 0, 0]);
+        }
+        let (un, vn) = uv_prime(white.xyz());
+        let u = self.u / (13.0 * self.l) + un;
+        let v = self.v / (13.0 * self.l) + vn;
+        let y = lab_f_inv((self.l + 16.0) / 116.0);
+        let x = y * 9.0 * u / (4.0 * v);
+        let z = y * (12.0 - 3.0 * u - 20.0 * v) / (4.0 * v);
+        Xyz { x, y, z }.to_rgb8_with(white)
+    }
 }
 
-pub fn rgb_to_yuv(image: &DynamicImage) -> DynamicImage {
-    let mut out = RgbImage::new(image.width(), image.height());
+/// Cylindrical form of L*a*b*: lightness, chroma and hue in degrees.
+pub struct LCh {
+    pub l: f32,
+    pub c: f32,
+    pub h: f32,
+}
+
+impl ColorModel for LCh {
+    type Params = WhitePoint;
+    const CHANNELS: [char; 3] = ['l', 'c', 'h'];
+    const INTENSITY: usize = 0;
+    const RANGES: [(f32, f32); 3] = [(0.0, 100.0), (0.0, 150.0), (0.0, 360.0)];
+
+    fn from_rgba_with(pixel: Rgba<u8>, white: WhitePoint) -> Self {
+        let lab = Lab::from_rgba_with(pixel, white);
+        let h = lab.b.atan2(lab.a).to_degrees();
+        LCh {
+            l: lab.l,
+            c: lab.a.hypot(lab.b),
+            h: if h < 0.0 { h + 360.0 } else { h },
+        }
+    }
+
+    fn from_values(values: [f32; 3]) -> Self {
+        LCh {
+            l: values[0],
+            c: values[1],
+            h: values[2],
+        }
+    }
+
+    fn values(&self) -> [f32; 3] {
+        [self.l, self.c, self.h]
+    }
+
+    fn to_rgb8_with(&self, white: WhitePoint) -> Rgb<u8> {
+        let h = self.h.to_radians();
+        Lab {
+            l: self.l,
+            a: self.c * h.cos(),
+            b: self.c * h.sin(),
+        }
+        .to_rgb8_with(white)
+    }
+}
+
+/// Converts an RGB image to the color model `M`.
+pub fn rgb_to_model<M: ColorModel>(image: &DynamicImage) -> Planes<f32, 3> {
+    rgb_to_model_with::<M>(image, M::Params::default())
+}
+
+/// Converts an image in the color model `M` back to RGB.
+pub fn model_to_rgb<M: ColorModel>(planes: &Planes<f32, 3>) -> DynamicImage {
+    model_to_rgb_with::<M>(planes, M::Params::default())
+}
+
+pub fn rgb_to_model_with<M: ColorModel>(image: &DynamicImage, params: M::Params) -> Planes<f32, 3> {
+    let mut out = Planes::new(image.width(), image.height());
     for (x, y, pixel) in image.pixels() {
-        let yuv = Yuv::from(pixel).as_rgb8();
-        out.put_pixel(x, y, yuv);
+        out.put_pixel(x, y, M::from_rgba_with(pixel, params).values());
+    }
+    out
+}
+
+pub fn model_to_rgb_with<M: ColorModel>(
+    planes: &Planes<f32, 3>,
+    params: M::Params,
+) -> DynamicImage {
+    let mut out = RgbImage::new(planes.width(), planes.height());
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        *pixel = M::from_values(planes.get_pixel(x, y)).to_rgb8_with(params);
     }
     DynamicImage::from(out)
 }
 
-pub fn yuv_to_rgb(image: &DynamicImage) -> DynamicImage {
-    let mut out = RgbImage::new(image.width(), image.height());
-    for (x, y, pixel) in image.pixels() {
-        let rgb = Yuv {
-            y: pixel[0] as f32 / 255.0,
-            u: (pixel[1] as f32 - 128.0) / 255.0,
-            v: (pixel[2] as f32 - 128.0) / 255.0,
-        }
-        .to_rgb8();
-        out.put_pixel(x, y, rgb);
+/// Encodes an image in the color model `M` as an 8-bit RGB image for display.
+pub fn encode_model<M: ColorModel>(planes: &Planes<f32, 3>) -> DynamicImage {
+    let mut out = RgbImage::new(planes.width(), planes.height());
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        *pixel = M::from_values(planes.get_pixel(x, y)).encode_u8();
     }
     DynamicImage::from(out)
 }
+
+pub fn rgb_to_hsv(image: &DynamicImage) -> Planes<f32, 3> {
+    rgb_to_model::<Hsv>(image)
+}
+
+pub fn hsv_to_rgb(planes: &Planes<f32, 3>) -> DynamicImage {
+    model_to_rgb::<Hsv>(planes)
+}
+
+pub fn rgb_to_hsl(image: &DynamicImage) -> Planes<f32, 3> {
+    rgb_to_model::<Hsl>(image)
+}
+
+pub fn hsl_to_rgb(planes: &Planes<f32, 3>) -> DynamicImage {
+    model_to_rgb::<Hsl>(planes)
+}
+
+pub fn rgb_to_hsi(image: &DynamicImage) -> Planes<f32, 3> {
+    rgb_to_model::<Hsi>(image)
+}
+
+pub fn hsi_to_rgb(planes: &Planes<f32, 3>) -> DynamicImage {
+    model_to_rgb::<Hsi>(planes)
+}
+
+/// Converts to full range BT.601 YCbCr, which is what the analog YUV formulas amount to.
+pub fn rgb_to_yuv(image: &DynamicImage) -> Planes<f32, 3> {
+    rgb_to_model::<YCbCr>(image)
+}
+
+pub fn yuv_to_rgb(planes: &Planes<f32, 3>) -> DynamicImage {
+    model_to_rgb::<YCbCr>(planes)
+}
+
+pub fn rgb_to_ycbcr(image: &DynamicImage, standard: YCbCrStandard) -> Planes<f32, 3> {
+    rgb_to_model_with::<YCbCr>(image, standard)
+}
+
+pub fn ycbcr_to_rgb(planes: &Planes<f32, 3>, standard: YCbCrStandard) -> DynamicImage {
+    model_to_rgb_with::<YCbCr>(planes, standard)
+}
+
+pub fn rgb_to_xyz(image: &DynamicImage, white: WhitePoint) -> Planes<f32, 3> {
+    rgb_to_model_with::<Xyz>(image, white)
+}
+
+pub fn xyz_to_rgb(planes: &Planes<f32, 3>, white: WhitePoint) -> DynamicImage {
+    model_to_rgb_with::<Xyz>(planes, white)
+}
+
+pub fn rgb_to_lab(image: &DynamicImage, white: WhitePoint) -> Planes<f32, 3> {
+    rgb_to_model_with::<Lab>(image, white)
+}
+
+pub fn lab_to_rgb(planes: &Planes<f32, 3>, white: WhitePoint) -> DynamicImage {
+    model_to_rgb_with::<Lab>(planes, white)
+}
+
+pub fn rgb_to_luv(image: &DynamicImage, white: WhitePoint) -> Planes<f32, 3> {
+    rgb_to_model_with::<Luv>(image, white)
+}
+
+pub fn luv_to_rgb(planes: &Planes<f32, 3>, white: WhitePoint) -> DynamicImage {
+    model_to_rgb_with::<Luv>(planes, white)
+}
+
+pub fn rgb_to_lch(image: &DynamicImage, white: WhitePoint) -> Planes<f32, 3> {
+    rgb_to_model_with::<LCh>(image, white)
+}
+
+pub fn lch_to_rgb(planes: &Planes<f32, 3>, white: WhitePoint) -> DynamicImage {
+    model_to_rgb_with::<LCh>(planes, white)
+}
+
+/// Image conversion between RGB and a color model chosen at runtime.
+pub trait ColorConversion {
+    fn channels(&self) -> [char; 3];
+    fn intensity(&self) -> usize;
+    fn ranges(&self) -> [(f32, f32); 3];
+    fn rgb_to_model(&self, image: &DynamicImage) -> Planes<f32, 3>;
+    fn model_to_rgb(&self, planes: &Planes<f32, 3>) -> DynamicImage;
+    fn encode(&self, planes: &Planes<f32, 3>) -> DynamicImage;
+}
+
+struct ModelConversion<M: ColorModel> {
+    params: M::Params,
+}
+
+impl<M: ColorModel> ColorConversion for ModelConversion<M> {
+    fn channels(&self) -> [char; 3] {
+        M::CHANNELS
+    }
+
+    fn intensity(&self) -> usize {
+        M::INTENSITY
+    }
+
+    fn ranges(&self) -> [(f32, f32); 3] {
+        M::RANGES
+    }
+
+    fn rgb_to_model(&self, image: &DynamicImage) -> Planes<f32, 3> {
+        rgb_to_model_with::<M>(image, self.params)
+    }
+
+    fn model_to_rgb(&self, planes: &Planes<f32, 3>) -> DynamicImage {
+        model_to_rgb_with::<M>(planes, self.params)
+    }
+
+    fn encode(&self, planes: &Planes<f32, 3>) -> DynamicImage {
+        encode_model::<M>(planes)
+    }
+}
+
+fn conversion<M: ColorModel + 'static>(params: M::Params) -> Box<dyn ColorConversion> {
+    Box::new(ModelConversion::<M> { params })
+}
+
+/// Parameters of the color spaces selected through `ColorSpace`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ColorOptions {
+    pub white_point: WhitePoint,
+    pub ycbcr: YCbCrStandard,
+}
+
+/// Color spaces that can be selected on the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ColorSpace {
+    Rgb,
+    Hsv,
+    Hsl,
+    Hsi,
+    #[cfg_attr(feature = "cli", value(name = "ycbcr", alias = "yuv"))]
+    YCbCr,
+    Xyz,
+    Lab,
+    Luv,
+    Lch,
+}
+
+impl ColorSpace {
+    pub const ALL: [ColorSpace; 9] = [
+        ColorSpace::Rgb,
+        ColorSpace::Hsv,
+        ColorSpace::Hsl,
+        ColorSpace::Hsi,
+        ColorSpace::YCbCr,
+        ColorSpace::Xyz,
+        ColorSpace::Lab,
+        ColorSpace::Luv,
+        ColorSpace::Lch,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorSpace::Rgb => "rgb",
+            ColorSpace::Hsv => "hsv",
+            ColorSpace::Hsl => "hsl",
+            ColorSpace::Hsi => "hsi",
+            ColorSpace::YCbCr => "ycbcr",
+            ColorSpace::Xyz => "xyz",
+            ColorSpace::Lab => "lab",
+            ColorSpace::Luv => "luv",
+            ColorSpace::Lch => "lch",
+        }
+    }
+
+    pub fn channels(self) -> [char; 3] {
+        match self.conversion() {
+            Some(conversion) => conversion.channels(),
+            None => ['r', 'g', 'b'],
+        }
+    }
+
+    /// Conversion from and to RGB with the default options, or `None` for RGB itself.
+    pub fn conversion(self) -> Option<Box<dyn ColorConversion>> {
+        self.conversion_with(&ColorOptions::default())
+    }
+
+    pub fn conversion_with(self, options: &ColorOptions) -> Option<Box<dyn ColorConversion>> {
+        match self {
+            ColorSpace::Rgb => None,
+            ColorSpace::Hsv => Some(conversion::<Hsv>(())),
+            ColorSpace::Hsl => Some(conversion::<Hsl>(())),
+            ColorSpace::Hsi => Some(conversion::<Hsi>(())),
+            ColorSpace::YCbCr => Some(conversion::<YCbCr>(options.ycbcr)),
+            ColorSpace::Xyz => Some(conversion::<Xyz>(options.white_point)),
+            ColorSpace::Lab => Some(conversion::<Lab>(options.white_point)),
+            ColorSpace::Luv => Some(conversion::<Luv>(options.white_point)),
+            ColorSpace::Lch => Some(conversion::<LCh>(options.white_point)),
+        }
+    }
+}
+
+impl fmt::Display for ColorSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ColorSpace {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("yuv") {
+            return Ok(ColorSpace::YCbCr);
+        }
+        ColorSpace::ALL
+            .into_iter()
+            .find(|c| c.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| Error::UnknownColorSpace(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(r: u8, g: u8, b: u8) -> Rgba<u8> {
+        Rgba([r, g, b, 255])
+    }
+
+    fn assert_values(actual: [f32; 3], expected: [f32; 3], tolerance: f32) {
+        for c in 0..3 {
+            assert!(
+                (actual[c] - expected[c]).abs() <= tolerance,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    /// Every 5th level of each channel (including 255) plus pseudo-random colors from a fixed seed.
+    fn sample_colors() -> Vec<Rgba<u8>> {
+        let levels: Vec<u8> = (0..=255).step_by(5).collect();
+        let mut colors = Vec::new();
+        for &r in &levels {
+            for &g in &levels {
+                for &b in &levels {
+                    colors.push(rgba(r, g, b));
+                }
+            }
+        }
+        let mut state = 0x2545_f491_u32;
+        for _ in 0..100_000 {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            colors.push(rgba(state as u8, (state >> 8) as u8, (state >> 16) as u8));
+        }
+        colors
+    }
+
+    fn max_roundtrip_error<M: ColorModel>(params: M::Params) -> u8 {
+        sample_colors()
+            .into_iter()
+            .map(|pixel| {
+                let recovered = M::from_rgba_with(pixel, params).to_rgb8_with(params);
+                (0..3)
+                    .map(|c| pixel[c].abs_diff(recovered[c]))
+                    .max()
+                    .unwrap()
+            })
+            .max()
+            .unwrap()
+    }
+
+    const PRIMARIES: [((u8, u8, u8), f32); 6] = [
+        ((255, 0, 0), 0.0),
+        ((255, 255, 0), 60.0),
+        ((0, 255, 0), 120.0),
+        ((0, 255, 255), 180.0),
+        ((0, 0, 255), 240.0),
+        ((255, 0, 255), 300.0),
+    ];
+
+    #[test]
+    fn unit_to_u8_clamps() {
+        assert_eq!(unit_to_u8(-0.5), 0);
+        assert_eq!(unit_to_u8(0.5), 128);
+        assert_eq!(unit_to_u8(1.5), 255);
+        assert_eq!(unit_to_u8(f32::NAN), 0);
+        assert_eq!(unit_to_u8(f32::INFINITY), 255);
+        assert_eq!(encode_value(400.0, (0.0, 360.0)), 255);
+    }
+
+    #[test]
+    fn hsv_reference_colors() {
+        for ((r, g, b), h) in PRIMARIES {
+            assert_values(Hsv::from_rgba(rgba(r, g, b)).values(), [h, 1.0, 1.0], 1e-4);
+        }
+        assert_values(
+            Hsv::from_rgba(rgba(0, 0, 0)).values(),
+            [0.0, 0.0, 0.0],
+            1e-6,
+        );
+        assert_values(
+            Hsv::from_rgba(rgba(255, 255, 255)).values(),
+            [0.0, 0.0, 1.0],
+            1e-6,
+        );
+        assert_values(
+            Hsv::from_rgba(rgba(51, 51, 51)).values(),
+            [0.0, 0.0, 0.2],
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn hsl_reference_colors() {
+        for ((r, g, b), h) in PRIMARIES {
+            assert_values(Hsl::from_rgba(rgba(r, g, b)).values(), [h, 1.0, 0.5], 1e-4);
+        }
+        assert_values(
+            Hsl::from_rgba(rgba(0, 0, 0)).values(),
+            [0.0, 0.0, 0.0],
+            1e-6,
+        );
+        assert_values(
+            Hsl::from_rgba(rgba(255, 255, 255)).values(),
+            [0.0, 0.0, 1.0],
+            1e-6,
+        );
+        assert_values(
+            Hsl::from_rgba(rgba(51, 51, 51)).values(),
+            [0.0, 0.0, 0.2],
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn hsi_reference_colors() {
+        for ((r, g, b), h) in PRIMARIES {
+            let hsi = Hsi::from_rgba(rgba(r, g, b));
+            let i = (r as u32 + g as u32 + b as u32) as f32 / 765.0;
+            assert_values(hsi.values(), [h, 1.0, i], 1e-3);
+        }
+        assert_values(
+            Hsi::from_rgba(rgba(0, 0, 0)).values(),
+            [0.0, 0.0, 0.0],
+            1e-6,
+        );
+        assert_values(
+            Hsi::from_rgba(rgba(255, 255, 255)).values(),
+            [0.0, 0.0, 1.0],
+            1e-6,
+        );
+        assert_values(
+            Hsi::from_rgba(rgba(51, 51, 51)).values(),
+            [0.0, 0.0, 0.2],
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn ycbcr_reference_colors() {
+        let full = YCbCrStandard::default();
+        let gray = YCbCr::from_rgba_with(rgba(128, 128, 128), full);
+        assert_values(gray.values(), [128.0 / 255.0, 0.0, 0.0], 1e-6);
+        assert_eq!(
+            YCbCr::from_rgba_with(rgba(255, 0, 0), full).encode_u8(),
+            Rgb([76, 85, 255])
+        );
+
+        let studio = YCbCrStandard {
+            matrix: YCbCrMatrix::Bt709,
+            range: YCbCrRange::Studio,
+        };
+        assert_eq!(
+            YCbCr::from_rgba_with(rgba(0, 0, 0), studio).encode_u8(),
+            Rgb([16, 128, 128])
+        );
+        assert_eq!(
+            YCbCr::from_rgba_with(rgba(255, 255, 255), studio).encode_u8(),
+            Rgb([235, 128, 128])
+        );
+        assert_eq!(
+            YCbCr::from_rgba_with(rgba(255, 0, 0), studio).encode_u8(),
+            Rgb([63, 102, 240])
+        );
+    }
+
+    #[test]
+    fn lab_reference_colors() {
+        let white = Lab::from_rgba(rgba(255, 255, 255));
+        assert_values(white.values(), [100.0, 0.0, 0.0], 1e-2);
+        let red = Lab::from_rgba(rgba(255, 0, 0));
+        assert_values(red.values(), [53.24, 80.09, 67.20], 5e-2);
+        let gray = LCh::from_rgba_with(rgba(119, 119, 119), WhitePoint::D50);
+        assert!(gray.c < 0.05);
+    }
+
+    #[test]
+    fn hue_wraps_around() {
+        let red = Rgb([255, 0, 0]);
+        for h in [0.0, 360.0, 720.0, -360.0] {
+            assert_eq!(Hsv { h, s: 1.0, v: 1.0 }.to_rgb8(), red);
+            assert_eq!(Hsl { h, s: 1.0, l: 0.5 }.to_rgb8(), red);
+            assert_eq!(
+                Hsi {
+                    h,
+                    s: 1.0,
+                    i: 1.0 / 3.0
+                }
+                .to_rgb8(),
+                red
+            );
+        }
+        let magenta = Rgb([255, 0, 255]);
+        assert_eq!(
+            Hsv {
+                h: -60.0,
+                s: 1.0,
+                v: 1.0
+            }
+            .to_rgb8(),
+            magenta
+        );
+        assert_eq!(
+            Hsl {
+                h: 660.0,
+                s: 1.0,
+                l: 0.5
+            }
+            .to_rgb8(),
+            magenta
+        );
+
+        // A hue just below 360 stays in range and converts back to the same color.
+        let pixel = rgba(255, 0, 1);
+        let hsv = Hsv::from_rgba(pixel);
+        assert!((359.0..360.0).contains(&hsv.h));
+        assert_eq!(hsv.to_rgb8(), Rgb([255, 0, 1]));
+        let hsi = Hsi::from_rgba(pixel);
+        assert!((359.0..360.0).contains(&hsi.h));
+        assert_eq!(hsi.to_rgb8(), Rgb([255, 0, 1]));
+    }
+
+    #[test]
+    fn out_of_range_values_saturate() {
+        assert_eq!(
+            Hsv {
+                h: 0.0,
+                s: 0.0,
+                v: 2.0
+            }
+            .to_rgb8(),
+            Rgb([255, 255, 255])
+        );
+        assert_eq!(
+            Hsi {
+                h: 0.0,
+                s: 1.0,
+                i: 1.0
+            }
+            .to_rgb8(),
+            Rgb([255, 0, 0])
+        );
+        assert_eq!(
+            YCbCr {
+                y: -1.0,
+                cb: 0.0,
+                cr: 0.0
+            }
+            .to_rgb8(),
+            Rgb([0, 0, 0])
+        );
+        assert_eq!(
+            Lab {
+                l: 150.0,
+                a: 0.0,
+                b: 0.0
+            }
+            .to_rgb8(),
+            Rgb([255, 255, 255])
+        );
+    }
+
+    #[test]
+    fn hsv_hsl_hsi_roundtrip() {
+        assert_eq!(max_roundtrip_error::<Hsv>(()), 0);
+        assert_eq!(max_roundtrip_error::<Hsl>(()), 0);
+        assert_eq!(max_roundtrip_error::<Hsi>(()), 0);
+    }
+
+    #[test]
+    fn ycbcr_roundtrip() {
+        for matrix in [YCbCrMatrix::Bt601, YCbCrMatrix::Bt709, YCbCrMatrix::Bt2020] {
+            for range in [YCbCrRange::Full, YCbCrRange::Studio] {
+                let standard = YCbCrStandard { matrix, range };
+                assert_eq!(max_roundtrip_error::<YCbCr>(standard), 0, "{:?}", standard);
+            }
+        }
+    }
+
+    #[test]
+    fn cie_roundtrip() {
+        for white in [WhitePoint::D65, WhitePoint::D50] {
+            assert_eq!(max_roundtrip_error::<Xyz>(white), 0);
+            assert_eq!(max_roundtrip_error::<Lab>(white), 0);
+            assert_eq!(max_roundtrip_error::<Luv>(white), 0);
+            assert_eq!(max_roundtrip_error::<LCh>(white), 0);
+        }
+    }
+
+    #[test]
+    fn color_space_from_str() {
+        assert_eq!("HSV".parse::<ColorSpace>().unwrap(), ColorSpace::Hsv);
+        assert_eq!("yuv".parse::<ColorSpace>().unwrap(), ColorSpace::YCbCr);
+        assert!(matches!(
+            "cmyk".parse::<ColorSpace>(),
+            Err(crate::Error::UnknownColorSpace(_))
+        ));
+        for space in ColorSpace::ALL {
+            assert_eq!(space.name().parse::<ColorSpace>().unwrap(), space);
+        }
+    }
+}
@@ -0,0 +1,385 @@
+use image::{GrayImage, Luma};
+use std::fmt;
+
+use super::filter::{convolve, convolve_separable, gaussian_blur, Border, Kernel};
+use super::gray::otsu_threshold;
+use super::planes::Planes;
+
+/// Edge detectors of the `edges` subcommand.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum EdgeOperator {
+    /// Gradient of 3×3 central differences smoothed across by 1, 2, 1.
+    Sobel,
+    /// Gradient smoothed across by 3, 10, 3, closer to rotation invariant than Sobel.
+    Scharr,
+    /// Gradient smoothed across by 1, 1, 1.
+    Prewitt,
+    /// Zero crossings of the Laplacian of Gaussian (Marr & Hildreth 1980).
+    Log,
+    /// Thin edges from the Sobel gradient by non-maximum suppression and hysteresis thresholds
+    /// (Canny 1986).
+    #[default]
+    Canny,
+}
+
+impl EdgeOperator {
+    pub fn name(self) -> &'static str {
+        match self {
+            EdgeOperator::Sobel => "sobel",
+            EdgeOperator::Scharr => "scharr",
+            EdgeOperator::Prewitt => "prewitt",
+            EdgeOperator::Log => "log",
+            EdgeOperator::Canny => "canny",
+        }
+    }
+
+    /// The gradient operator, for the operators working on the gradient.
+    pub fn gradient(self) -> Option<GradientOperator> {
+        match self {
+            EdgeOperator::Sobel | EdgeOperator::Canny => Some(GradientOperator::Sobel),
+            EdgeOperator::Scharr => Some(GradientOperator::Scharr),
+            EdgeOperator::Prewitt => Some(GradientOperator::Prewitt),
+            EdgeOperator::Log => None,
+        }
+    }
+}
+
+impl fmt::Display for EdgeOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 3×3 derivative kernels: a central difference along the derivative times a smoothing across it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GradientOperator {
+    #[default]
+    Sobel,
+    Scharr,
+    Prewitt,
+}
+
+impl GradientOperator {
+    /// Smoothing weights across the derivative, summing to 1.
+    fn smoothing(self) -> [f32; 3] {
+        let weights: [f32; 3] = match self {
+            GradientOperator::Sobel => [1.0, 2.0, 1.0],
+            GradientOperator::Scharr => [3.0, 10.0, 3.0],
+            GradientOperator::Prewitt => [1.0, 1.0, 1.0],
+        };
+        let sum: f32 = weights.iter().sum();
+        weights.map(|w| w / sum)
+    }
+}
+
+/// Central difference, flipped for the convolution.
+const DIFFERENCE: [f32; 3] = [0.5, 0.0, -0.5];
+
+/// Derivatives of `plane` to the right (plane 0) and downwards (plane 1), in levels per pixel.
+pub fn gradient(
+    plane: &Planes<f32, 1>,
+    operator: GradientOperator,
+    border: Border,
+) -> Planes<f32, 2> {
+    let smoothing = operator.smoothing();
+    let dx = convolve_separable(plane, &smoothing, &DIFFERENCE, border);
+    let dy = convolve_separable(plane, &DIFFERENCE, &smoothing, border);
+    let mut out = Planes::new(plane.width(), plane.height());
+    out.plane_mut(0).copy_from_slice(dx.plane(0));
+    out.plane_mut(1).copy_from_slice(dy.plane(0));
+    out
+}
+
+/// Length of every gradient vector.
+pub fn magnitude(gradient: &Planes<f32, 2>) -> Vec<f32> {
+    gradient
+        .plane(0)
+        .iter()
+        .zip(gradient.plane(1))
+        .map(|(dx, dy)| dx.hypot(*dy))
+        .collect()
+}
+
+/// Direction of every gradient vector in degrees, 0~360 counterclockwise from the right as the
+/// image is seen.
+pub fn direction(gradient: &Planes<f32, 2>) -> Vec<f32> {
+    gradient
+        .plane(0)
+        .iter()
+        .zip(gradient.plane(1))
+        .map(|(dx, dy)| (-dy).atan2(*dx).to_degrees().rem_euclid(360.0))
+        .collect()
+}
+
+/// Smallest value of the upper class when Otsu's method splits the magnitudes of `values`, which
+/// are quantized to 256 levels up to the largest, and at most the largest. Zero when all are zero.
+pub fn otsu_level(values: impl Iterator<Item = f32> + Clone) -> f32 {
+    let peak = values.clone().fold(0.0f32, f32::max);
+    if peak <= 0.0 {
+        return 0.0;
+    }
+    let mut hist = [0u64; 256];
+    for v in values {
+        hist[(v / peak * 255.0).round() as usize] += 1;
+    }
+    ((otsu_threshold(&hist) as f32 + 1.0) / 255.0 * peak).min(peak)
+}
+
+/// Zero-sum Laplacian of Gaussian kernel of deviation `sigma`, reaching `3 * sigma` from the
+/// center, scaled by `sigma²` so that the responses are comparable across scales. Negative at the
+/// center, so the response is positive on the dark side of an edge.
+pub fn laplacian_of_gaussian(sigma: f32) -> Kernel {
+    assert!(sigma > 0.0, "sigma must be positive");
+    let r = (3.0 * sigma).ceil() as i32;
+    let size = (2 * r + 1) as usize;
+    let s2 = sigma * sigma;
+    let mut weights: Vec<f32> = (-r..=r)
+        .flat_map(|y| (-r..=r).map(move |x| (x * x + y * y) as f32 / (2.0 * s2)))
+        .map(|t| -(1.0 - t) * (-t).exp() / std::f32::consts::PI)
+        .collect();
+    let mean = weights.iter().sum::<f32>() / weights.len() as f32;
+    weights.iter_mut().for_each(|w| *w -= mean);
+    Kernel::new(size, size, weights)
+}
+
+/// Laplacian of Gaussian response of `plane`.
+pub fn log_response(plane: &Planes<f32, 1>, sigma: f32, border: Border) -> Vec<f32> {
+    convolve(plane, &laplacian_of_gaussian(sigma), border)
+        .plane(0)
+        .to_vec()
+}
+
+/// Marks the zero crossings of `response`, of `width` by `height` samples: where it changes sign
+/// towards the right or downwards by at least `threshold`, the pixel closer to zero is white. With
+/// no threshold, the threshold is `otsu_level` of the steps of all crossings. Returns the edges
+/// and the threshold.
+pub fn zero_crossings(
+    response: &[f32],
+    width: u32,
+    height: u32,
+    threshold: Option<f32>,
+) -> (GrayImage, f32) {
+    let (w, h) = (width as usize, height as usize);
+    let mut crossings = Vec::new();
+    for y in 0..h {
+        for x in 0..w {
+            let i = y * w + x;
+            let neighbors = [(x + 1 < w).then(|| i + 1), (y + 1 < h).then(|| i + w)];
+            for j in neighbors.into_iter().flatten() {
+                if response[i] * response[j] < 0.0 {
+                    let at = if response[i].abs() <= response[j].abs() {
+                        i
+                    } else {
+                        j
+                    };
+                    crossings.push((at, (response[i] - response[j]).abs()));
+                }
+            }
+        }
+    }
+    let threshold =
+        threshold.unwrap_or_else(|| otsu_level(crossings.iter().map(|&(_, step)| step)));
+    let mut edges = GrayImage::new(width, height);
+    for (at, step) in crossings {
+        if step >= threshold {
+            edges.put_pixel((at % w) as u32, (at / w) as u32, Luma([255]));
+        }
+    }
+    (edges, threshold)
+}
+
+/// Result of `canny`.
+#[derive(Clone, Debug)]
+pub struct CannyEdges {
+    /// Edge pixels in white.
+    pub edges: GrayImage,
+    /// Sobel gradient of the smoothed image.
+    pub gradient: Planes<f32, 2>,
+    /// Hysteresis thresholds on the gradient magnitude, in levels per pixel.
+    pub low: f32,
+    pub high: f32,
+}
+
+/// Canny edges of `image` smoothed by a gaussian of deviation `sigma` (none if 0): the local
+/// maxima of the gradient magnitude along the gradient at least `high`, extended along maxima at
+/// least `low`. A missing `high` is the `otsu_level` of the magnitudes of the maxima, a missing
+/// `low` half of `high`. `low` is capped at `high`.
+pub fn canny(
+    image: &GrayImage,
+    sigma: f32,
+    low: Option<f32>,
+    high: Option<f32>,
+    border: Border,
+) -> CannyEdges {
+    let (width, height) = image.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let mut plane = Planes::new(width, height);
+    for (sample, &p) in plane.plane_mut(0).iter_mut().zip(image.iter()) {
+        *sample = p as f32;
+    }
+    if sigma > 0.0 {
+        plane = gaussian_blur(&plane, (3.0 * sigma).ceil() as u32, sigma, border);
+    }
+    let gradient = gradient(&plane, GradientOperator::Sobel, border);
+    let magnitude = magnitude(&gradient);
+
+    // Non-maximum suppression across the gradient, quantized to 45 degrees.
+    let at = |x: isize, y: isize| {
+        if x < 0 || y < 0 || x >= w as isize || y >= h as isize {
+            0.0
+        } else {
+            magnitude[y as usize * w + x as usize]
+        }
+    };
+    let mut maxima = vec![0.0f32; w * h];
+    for y in 0..h {
+        for x in 0..w {
+            let i = y * w + x;
+            let m = magnitude[i];
+            if m == 0.0 {
+                continue;
+            }
+            let angle = gradient.plane(1)[i]
+                .atan2(gradient.plane(0)[i])
+                .to_degrees();
+            let (dx, dy) = match ((angle / 45.0).round() as i32).rem_euclid(4) {
+                0 => (1, 0),
+                1 => (1, 1),
+                2 => (0, 1),
+                _ => (-1, 1),
+            };
+            let (x, y) = (x as isize, y as isize);
+            if m > at(x - dx, y - dy) && m >= at(x + dx, y + dy) {
+                maxima[i] = m;
+            }
+        }
+    }
+
+    let high = high.unwrap_or_else(|| otsu_level(maxima.iter().copied().filter(|&m| m > 0.0)));
+    let low = low.unwrap_or(high / 2.0).min(high);
+
+    // Hysteresis: flood the 8-connected maxima above `low` from those above `high`.
+    let mut edges = GrayImage::new(width, height);
+    let mut stack: Vec<usize> = (0..w * h)
+        .filter(|&i| maxima[i] > 0.0 && maxima[i] >= high)
+        .collect();
+    for &i in &stack {
+        edges.put_pixel((i % w) as u32, (i / w) as u32, Luma([255]));
+    }
+    while let Some(i) = stack.pop() {
+        let (x, y) = ((i % w) as isize, (i / w) as isize);
+        for (dx, dy) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy))) {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 0 || ny < 0 || nx >= w as isize || ny >= h as isize {
+                continue;
+            }
+            let j = ny as usize * w + nx as usize;
+            let pixel = edges.get_pixel_mut(nx as u32, ny as u32);
+            if pixel.0[0] == 0 && maxima[j] > 0.0 && maxima[j] >= low {
+                pixel.0[0] = 255;
+                stack.push(j);
+            }
+        }
+    }
+
+    CannyEdges {
+        edges,
+        gradient,
+        low,
+        high,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alg::filter::BorderMode;
+
+    /// Image dark left of `edge` and bright from it.
+    fn step(width: u32, height: u32, edge: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, _| {
+            Luma([if x < edge { 20 } else { 220 }])
+        })
+    }
+
+    fn plane(image: &GrayImage) -> Planes<f32, 1> {
+        let mut plane = Planes::new(image.width(), image.height());
+        for (sample, &p) in plane.plane_mut(0).iter_mut().zip(image.iter()) {
+            *sample = p as f32;
+        }
+        plane
+    }
+
+    #[test]
+    fn gradient_points_to_brighter_side() {
+        let border = Border::new(BorderMode::Clamp);
+        for operator in [
+            GradientOperator::Sobel,
+            GradientOperator::Scharr,
+            GradientOperator::Prewitt,
+        ] {
+            let g = gradient(&plane(&step(8, 5, 4)), operator, border);
+            let [dx, dy] = g.get_pixel(3, 2);
+            assert!((dx - 100.0).abs() < 1e-3 && dy == 0.0);
+            assert_eq!(g.get_pixel(1, 2), [0.0, 0.0]);
+            assert_eq!(direction(&g)[2 * 8 + 4], 0.0);
+        }
+        // Brighter upwards: the gradient points up, at 90 degrees.
+        let up = GrayImage::from_fn(4, 6, |_, y| Luma([if y < 3 { 200 } else { 0 }]));
+        let g = gradient(&plane(&up), GradientOperator::Sobel, border);
+        assert_eq!(magnitude(&g)[2 * 4 + 1], 100.0);
+        assert_eq!(direction(&g)[2 * 4 + 1], 90.0);
+    }
+
+    #[test]
+    fn log_kernel_sums_to_zero() {
+        let kernel = laplacian_of_gaussian(1.0);
+        assert_eq!((kernel.width(), kernel.height()), (7, 7));
+        assert!(kernel.sum().abs() < 1e-5);
+        assert!(kernel.weight(3, 3) < 0.0);
+    }
+
+    #[test]
+    fn log_crosses_zero_at_step() {
+        let image = step(16, 3, 8);
+        let response = log_response(&plane(&image), 1.0, Border::new(BorderMode::Clamp));
+        assert!(response[7] > 0.0 && response[8] < 0.0);
+        let (edges, threshold) = zero_crossings(&response, 16, 3, None);
+        assert!(threshold > 0.0);
+        let marked: Vec<u32> = (0..16)
+            .filter(|&x| edges.get_pixel(x, 1).0[0] == 255)
+            .collect();
+        assert_eq!(marked.len(), 1);
+        assert!(marked[0] == 7 || marked[0] == 8);
+        let (none, _) = zero_crossings(&response, 16, 3, Some(1e9));
+        assert!(none.iter().all(|&p| p == 0));
+    }
+
+    #[test]
+    fn canny_finds_one_pixel_wide_edges() {
+        let image = step(20, 10, 10);
+        let result = canny(&image, 1.0, None, None, Border::new(BorderMode::Reflect));
+        assert!(result.low <= result.high && result.high > 0.0);
+        for y in 0..10 {
+            let row: Vec<u32> = (0..20)
+                .filter(|&x| result.edges.get_pixel(x, y).0[0] == 255)
+                .collect();
+            assert_eq!(row.len(), 1, "row {}: {:?}", y, row);
+            assert!(row[0] == 9 || row[0] == 10);
+        }
+        let flat = GrayImage::from_pixel(6, 6, Luma([90]));
+        let result = canny(&flat, 1.4, None, None, Border::default());
+        assert!(result.edges.iter().all(|&p| p == 0));
+        let strict = canny(&image, 1.0, None, Some(1e6), Border::default());
+        assert!(strict.edges.iter().all(|&p| p == 0));
+    }
+
+    #[test]
+    fn otsu_level_splits_magnitudes() {
+        let values = [1.0, 1.0, 1.0, 10.0, 10.0];
+        let level = otsu_level(values.iter().copied());
+        assert!(level > 1.0 && level <= 10.0);
+        assert_eq!(otsu_level([0.0; 3].iter().copied()), 0.0);
+    }
+}
@@ -0,0 +1,723 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use std::fmt;
+
+use super::planes::Planes;
+use crate::{Error, Result};
+
+/// How samples outside of the image are made up for the neighborhoods of the pixels near its
+/// edges.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum BorderMode {
+    /// Repeat the edge sample: `aaa|abcd|ddd`.
+    #[default]
+    Clamp,
+    /// Mirror about the edge sample: `dcb|abcd|cba`.
+    Reflect,
+    /// Continue from the opposite edge: `bcd|abcd|abc`.
+    Wrap,
+    /// A constant value: `vvv|abcd|vvv`.
+    Constant,
+}
+
+impl BorderMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            BorderMode::Clamp => "clamp",
+            BorderMode::Reflect => "reflect",
+            BorderMode::Wrap => "wrap",
+            BorderMode::Constant => "constant",
+        }
+    }
+}
+
+impl fmt::Display for BorderMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Border handling of the filters.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Border {
+    pub mode: BorderMode,
+    /// Value of the samples outside of the image with `BorderMode::Constant`.
+    pub value: f32,
+}
+
+impl Border {
+    pub fn new(mode: BorderMode) -> Self {
+        Border { mode, value: 0.0 }
+    }
+
+    pub fn constant(value: f32) -> Self {
+        Border {
+            mode: BorderMode::Constant,
+            value,
+        }
+    }
+
+    /// Index of the sample standing for position `i` of a line of `len` samples, `None` for the
+    /// constant value.
+    fn resolve(self, i: isize, len: usize) -> Option<usize> {
+        let n = len as isize;
+        if (0..n).contains(&i) {
+            return Some(i as usize);
+        }
+        let index = match self.mode {
+            BorderMode::Clamp => i.clamp(0, n - 1),
+            BorderMode::Reflect if n == 1 => 0,
+            BorderMode::Reflect => {
+                let period = 2 * (n - 1);
+                let m = i.rem_euclid(period);
+                if m < n {
+                    m
+                } else {
+                    period - m
+                }
+            }
+            BorderMode::Wrap => i.rem_euclid(n),
+            BorderMode::Constant => return None,
+        };
+        Some(index as usize)
+    }
+
+    /// Sample of `plane`, `width` samples wide, at `(x, y)`, inside or outside of the image.
+    fn sample(self, plane: &[f32], width: usize, height: usize, x: isize, y: isize) -> f32 {
+        match (self.resolve(x, width), self.resolve(y, height)) {
+            (Some(x), Some(y)) => plane[y * width + x],
+            _ => self.value,
+        }
+    }
+}
+
+/// Rectangular convolution kernel of odd width and height, centered on the middle weight.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Kernel {
+    width: usize,
+    height: usize,
+    weights: Vec<f32>,
+}
+
+impl Kernel {
+    /// Kernel of `weights` in row-major order.
+    ///
+    /// Panics unless the sizes are odd and match the number of weights.
+    pub fn new(width: usize, height: usize, weights: Vec<f32>) -> Self {
+        assert!(
+            width % 2 == 1 && height % 2 == 1,
+            "kernel sizes must be odd"
+        );
+        assert_eq!(
+            weights.len(),
+            width * height,
+            "wrong number of kernel weights"
+        );
+        Kernel {
+            width,
+            height,
+            weights,
+        }
+    }
+
+    /// Product of a `column` and a `row` of weights, the kernel of a separable filter.
+    pub fn outer(column: &[f32], row: &[f32]) -> Self {
+        let weights = column
+            .iter()
+            .flat_map(|c| row.iter().map(move |r| c * r))
+            .collect();
+        Kernel::new(row.len(), column.len(), weights)
+    }
+
+    /// Parses a kernel given either as a JSON array of rows, such as `[[0, -1, 0], [-1, 4, -1],
+    /// [0, -1, 0]]`, or as text with one row per line and the weights separated by whitespace.
+    /// Text after `#` is a comment. A single JSON array is a kernel of one row.
+    pub fn parse(text: &str) -> Result<Self> {
+        let invalid = |msg: &str| Error::InvalidData(msg.to_string());
+        let number = |s: &str| {
+            s.trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| invalid(&format!("invalid kernel weight '{}'", s.trim())))
+        };
+        let trimmed = text.trim();
+        let rows: Vec<Vec<f32>> = if let Some(inner) = trimmed.strip_prefix('[') {
+            let inner = inner
+                .strip_suffix(']')
+                .ok_or_else(|| invalid("unterminated JSON array"))?
+                .trim();
+            if !inner.starts_with('[') {
+                vec![inner.split(',').map(number).collect::<Result<_>>()?]
+            } else {
+                let mut rows = Vec::new();
+                let mut rest = inner;
+                while let Some(body) = rest.strip_prefix('[') {
+                    let end = body
+                        .find(']')
+                        .ok_or_else(|| invalid("unterminated JSON row"))?;
+                    rows.push(body[..end].split(',').map(number).collect::<Result<_>>()?);
+                    rest = body[end + 1..].trim_start();
+                    if let Some(next) = rest.strip_prefix(',') {
+                        rest = next.trim_start();
+                        if rest.is_empty() {
+                            return Err(invalid("trailing comma after the last JSON row"));
+                        }
+                    } else if !rest.is_empty() {
+                        return Err(invalid("JSON rows must be separated by commas"));
+                    }
+                }
+                if !rest.is_empty() {
+                    return Err(invalid("expected a JSON array of rows"));
+                }
+                rows
+            }
+        } else {
+            text.lines()
+                .map(|line| line.split('#').next().unwrap())
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| line.split_whitespace().map(number).collect())
+                .collect::<Result<_>>()?
+        };
+
+        let width = rows.first().map_or(0, Vec::len);
+        if width == 0 || rows.iter().any(|row| row.len() != width) {
+            return Err(invalid(
+                "kernel rows must all have the same number of weights",
+            ));
+        }
+        if width % 2 != 1 || rows.len() % 2 != 1 {
+            return Err(invalid(&format!(
+                "kernel sizes must be odd, got {}x{}",
+                width,
+                rows.len()
+            )));
+        }
+        Ok(Kernel::new(width, rows.len(), rows.concat()))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn weight(&self, x: usize, y: usize) -> f32 {
+        self.weights[y * self.width + x]
+    }
+
+    pub fn sum(&self) -> f32 {
+        self.weights.iter().sum()
+    }
+}
+
+/// `2 * radius + 1` equal weights summing to 1.
+pub fn box_weights(radius: u32) -> Vec<f32> {
+    let len = 2 * radius as usize + 1;
+    vec![1.0 / len as f32; len]
+}
+
+/// `2 * radius + 1` samples of the gaussian of deviation `sigma`, normalized to sum to 1.
+pub fn gaussian_weights(radius: u32, sigma: f32) -> Vec<f32> {
+    assert!(sigma > 0.0, "sigma must be positive");
+    let r = radius as i32;
+    let weights: Vec<f32> = (-r..=r)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = weights.iter().sum();
+    weights.into_iter().map(|w| w / sum).collect()
+}
+
+/// Deviation of the gaussian fitting a kernel of `radius`, as OpenCV derives it from the kernel
+/// size.
+pub fn default_sigma(radius: u32) -> f32 {
+    0.3 * (radius as f32 - 1.0) + 0.8
+}
+
+/// Convolution of every plane with `kernel`: each output sample is the sum of the weights times
+/// the samples under the kernel flipped both ways.
+pub fn convolve<const N: usize>(
+    planes: &Planes<f32, N>,
+    kernel: &Kernel,
+    border: Border,
+) -> Planes<f32, N> {
+    let (width, height) = (planes.width() as usize, planes.height() as usize);
+    let (rx, ry) = ((kernel.width / 2) as isize, (kernel.height / 2) as isize);
+    let mut out = Planes::new(planes.width(), planes.height());
+    for p in 0..N {
+        let plane = planes.plane(p);
+        let target = out.plane_mut(p);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = 0.0;
+                for ky in 0..kernel.height {
+                    for kx in 0..kernel.width {
+                        let sx = x as isize + rx - kx as isize;
+                        let sy = y as isize + ry - ky as isize;
+                        sum += kernel.weight(kx, ky) * border.sample(plane, width, height, sx, sy);
+                    }
+                }
+                target[y * width + x] = sum;
+            }
+        }
+    }
+    out
+}
+
+/// Convolution with the kernel `Kernel::outer(column, row)`, in a horizontal then a vertical pass.
+pub fn convolve_separable<const N: usize>(
+    planes: &Planes<f32, N>,
+    column: &[f32],
+    row: &[f32],
+    border: Border,
+) -> Planes<f32, N> {
+    assert!(
+        row.len() % 2 == 1 && column.len() % 2 == 1,
+        "kernel sizes must be odd"
+    );
+    let (width, height) = (planes.width() as usize, planes.height() as usize);
+    let pass = |plane: &[f32], taps: &[f32], horizontal: bool| -> Vec<f32> {
+        let r = (taps.len() / 2) as isize;
+        let mut out = vec![0.0; plane.len()];
+        for y in 0..height {
+            for x in 0..width {
+                out[y * width + x] = taps
+                    .iter()
+                    .enumerate()
+                    .map(|(k, w)| {
+                        let offset = r - k as isize;
+                        let (sx, sy) = if horizontal {
+                            (x as isize + offset, y as isize)
+                        } else {
+                            (x as isize, y as isize + offset)
+                        };
+                        w * border.sample(plane, width, height, sx, sy)
+                    })
+                    .sum();
+            }
+        }
+        out
+    };
+    let mut out = Planes::new(planes.width(), planes.height());
+    for p in 0..N {
+        let rows = pass(planes.plane(p), row, true);
+        out.plane_mut(p)
+            .copy_from_slice(&pass(&rows, column, false));
+    }
+    out
+}
+
+/// Mean of every `(2 * radius + 1)` square window.
+pub fn box_blur<const N: usize>(
+    planes: &Planes<f32, N>,
+    radius: u32,
+    border: Border,
+) -> Planes<f32, N> {
+    let weights = box_weights(radius);
+    convolve_separable(planes, &weights, &weights, border)
+}
+
+/// Gaussian weighted mean of deviation `sigma` over every `(2 * radius + 1)` square window.
+pub fn gaussian_blur<const N: usize>(
+    planes: &Planes<f32, N>,
+    radius: u32,
+    sigma: f32,
+    border: Border,
+) -> Planes<f32, N> {
+    let weights = gaussian_weights(radius, sigma);
+    convolve_separable(planes, &weights, &weights, border)
+}
+
+/// Median of every `(2 * radius + 1)` square window, plane by plane.
+pub fn median<const N: usize>(
+    planes: &Planes<f32, N>,
+    radius: u32,
+    border: Border,
+) -> Planes<f32, N> {
+    let (width, height) = (planes.width() as usize, planes.height() as usize);
+    let r = radius as isize;
+    let mut window = Vec::with_capacity((2 * radius as usize + 1).pow(2));
+    let mut out = Planes::new(planes.width(), planes.height());
+    for p in 0..N {
+        let plane = planes.plane(p);
+        let target = out.plane_mut(p);
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                window.clear();
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        window.push(border.sample(plane, width, height, x + dx, y + dy));
+                    }
+                }
+                let middle = window.len() / 2;
+                let (_, median, _) = window.select_nth_unstable_by(middle, f32::total_cmp);
+                target[y as usize * width + x as usize] = *median;
+            }
+        }
+    }
+    out
+}
+
+/// Edge-preserving smoothing (Tomasi & Manduchi 1998): the mean of every `(2 * radius + 1)`
+/// square window weighted by a gaussian of deviation `sigma_space` of the distance to the center
+/// pixel and one of deviation `sigma_range` of the difference from its value, taken over all
+/// planes.
+pub fn bilateral<const N: usize>(
+    planes: &Planes<f32, N>,
+    radius: u32,
+    sigma_space: f32,
+    sigma_range: f32,
+    border: Border,
+) -> Planes<f32, N> {
+    assert!(
+        sigma_space > 0.0 && sigma_range > 0.0,
+        "sigmas must be positive"
+    );
+    let (width, height) = (planes.width() as usize, planes.height() as usize);
+    let r = radius as isize;
+    let spatial: Vec<f32> = (-r..=r)
+        .flat_map(|dy| (-r..=r).map(move |dx| (dx * dx + dy * dy) as f32))
+        .map(|d2| (-d2 / (2.0 * sigma_space * sigma_space)).exp())
+        .collect();
+    let range_scale = -1.0 / (2.0 * sigma_range * sigma_range);
+    let mut out = Planes::new(planes.width(), planes.height());
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let center = planes.get_pixel(x as u32, y as u32);
+            let mut sums = [0.0; N];
+            let mut total = 0.0;
+            for (k, (dy, dx)) in (-r..=r)
+                .flat_map(|dy| (-r..=r).map(move |dx| (dy, dx)))
+                .enumerate()
+            {
+                let pixel: [f32; N] = std::array::from_fn(|p| {
+                    border.sample(planes.plane(p), width, height, x + dx, y + dy)
+                });
+                let d2: f32 = (0..N).map(|p| (pixel[p] - center[p]).powi(2)).sum();
+                let weight = spatial[k] * (d2 * range_scale).exp();
+                for (sum, value) in sums.iter_mut().zip(pixel) {
+                    *sum += weight * value;
+                }
+                total += weight;
+            }
+            out.put_pixel(x as u32, y as u32, sums.map(|s| s / total));
+        }
+    }
+    out
+}
+
+/// Non-local means (Buades, Coll & Morel 2005): the mean of every `(2 * search_radius + 1)` square
+/// window weighted by how much the `(2 * patch_radius + 1)` square patch around each pixel looks
+/// like the one around the center pixel, over all planes. Patches whose mean squared difference
+/// is within the `2 * sigma²` expected from noise of deviation `sigma` alone get full weight,
+/// larger differences a weight falling as a gaussian of deviation `h`.
+pub fn non_local_means<const N: usize>(
+    planes: &Planes<f32, N>,
+    patch_radius: u32,
+    search_radius: u32,
+    sigma: f32,
+    h: f32,
+    border: Border,
+) -> Planes<f32, N> {
+    assert!(h > 0.0, "h must be positive");
+    let (width, height) = (planes.width() as usize, planes.height() as usize);
+    let r = search_radius as isize;
+    let noise = 2.0 * sigma * sigma;
+    let mut sums = Planes::new(planes.width(), planes.height());
+    let mut totals = vec![0.0; width * height];
+    let mut distances: Planes<f32, 1> = Planes::new(planes.width(), planes.height());
+    let shifted = |p: usize, x: usize, y: usize, dx: isize, dy: isize| {
+        border.sample(
+            planes.plane(p),
+            width,
+            height,
+            x as isize + dx,
+            y as isize + dy,
+        )
+    };
+    for dy in -r..=r {
+        for dx in -r..=r {
+            for (i, d) in distances.plane_mut(0).iter_mut().enumerate() {
+                let (x, y) = (i % width, i / width);
+                *d = (0..N)
+                    .map(|p| (planes.plane(p)[i] - shifted(p, x, y, dx, dy)).powi(2))
+                    .sum::<f32>()
+                    / N as f32;
+            }
+            let patches = box_blur(&distances, patch_radius, border);
+            for (i, (&d, total)) in patches.plane(0).iter().zip(&mut totals).enumerate() {
+                let weight = (-(d - noise).max(0.0) / (h * h)).exp();
+                let (x, y) = (i % width, i / width);
+                for p in 0..N {
+                    sums.plane_mut(p)[i] += weight * shifted(p, x, y, dx, dy);
+                }
+                *total += weight;
+            }
+        }
+    }
+    for p in 0..N {
+        for (s, total) in sums.plane_mut(p).iter_mut().zip(&totals) {
+            *s /= total;
+        }
+    }
+    sums
+}
+
+/// Adaptive Wiener filter (Lee 1980, as MATLAB's `wiener2`): every sample is pulled towards the
+/// mean of its `(2 * radius + 1)` square window, the more so the closer the variance of the window
+/// is to the variance `noise` of the noise, plane by plane.
+pub fn wiener<const N: usize>(
+    planes: &Planes<f32, N>,
+    radius: u32,
+    noise: f32,
+    border: Border,
+) -> Planes<f32, N> {
+    let means = box_blur(planes, radius, border);
+    let mut squares = planes.clone();
+    for p in 0..N {
+        squares.plane_mut(p).iter_mut().for_each(|v| *v *= *v);
+    }
+    let square_means = box_blur(&squares, radius, border);
+    let mut out = means.clone();
+    for p in 0..N {
+        let samples = planes.plane(p).iter().zip(square_means.plane(p));
+        for (o, (v, square_mean)) in out.plane_mut(p).iter_mut().zip(samples) {
+            let variance = (square_mean - *o * *o).max(0.0);
+            if variance > noise {
+                *o += (variance - noise) / variance * (v - *o);
+            }
+        }
+    }
+    out
+}
+
+/// Neighborhood filters applied by `Filter::apply`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    Box {
+        radius: u32,
+    },
+    Gaussian {
+        radius: u32,
+        sigma: f32,
+    },
+    Median {
+        radius: u32,
+    },
+    Bilateral {
+        radius: u32,
+        sigma_space: f32,
+        sigma_range: f32,
+    },
+}
+
+/// Kinds of `Filter`, without their parameters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum FilterKind {
+    Box,
+    #[default]
+    Gaussian,
+    Median,
+    Bilateral,
+}
+
+impl FilterKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            FilterKind::Box => "box",
+            FilterKind::Gaussian => "gaussian",
+            FilterKind::Median => "median",
+            FilterKind::Bilateral => "bilateral",
+        }
+    }
+}
+
+impl fmt::Display for FilterKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Filter {
+    pub fn kind(&self) -> FilterKind {
+        match self {
+            Filter::Box { .. } => FilterKind::Box,
+            Filter::Gaussian { .. } => FilterKind::Gaussian,
+            Filter::Median { .. } => FilterKind::Median,
+            Filter::Bilateral { .. } => FilterKind::Bilateral,
+        }
+    }
+
+    pub fn apply<const N: usize>(&self, planes: &Planes<f32, N>, border: Border) -> Planes<f32, N> {
+        match *self {
+            Filter::Box { radius } => box_blur(planes, radius, border),
+            Filter::Gaussian { radius, sigma } => gaussian_blur(planes, radius, sigma, border),
+            Filter::Median { radius } => median(planes, radius, border),
+            Filter::Bilateral {
+                radius,
+                sigma_space,
+                sigma_range,
+            } => bilateral(planes, radius, sigma_space, sigma_range, border),
+        }
+    }
+}
+
+/// Red, green and blue planes of `image`, with levels in 0~255.
+pub fn rgb_planes(image: &DynamicImage) -> Planes<f32, 3> {
+    let rgb = image.to_rgb8();
+    let mut planes = Planes::new(rgb.width(), rgb.height());
+    for (x, y, pixel) in rgb.enumerate_pixels() {
+        planes.put_pixel(x, y, pixel.0.map(|v| v as f32));
+    }
+    planes
+}
+
+/// Image of the red, green and blue `planes`, rounded and clipped to 0~255, with the alpha
+/// channel of `alpha`, which must have the same size.
+pub fn rgb_image(planes: &Planes<f32, 3>, alpha: &DynamicImage) -> DynamicImage {
+    let alpha = alpha.to_rgba8();
+    let image = RgbaImage::from_fn(planes.width(), planes.height(), |x, y| {
+        let [r, g, b] = planes
+            .get_pixel(x, y)
+            .map(|v| v.round().clamp(0.0, 255.0) as u8);
+        Rgba([r, g, b, alpha.get_pixel(x, y).0[3]])
+    });
+    DynamicImage::from(image)
+}
+
+/// Applies `filter` to the color channels of `image`. Alpha is left untouched.
+pub fn filter_image(image: &DynamicImage, filter: &Filter, border: Border) -> DynamicImage {
+    rgb_image(&filter.apply(&rgb_planes(image), border), image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(values: &[f32]) -> Planes<f32, 1> {
+        let mut planes = Planes::new(values.len() as u32, 1);
+        planes.plane_mut(0).copy_from_slice(values);
+        planes
+    }
+
+    #[test]
+    fn border_modes() {
+        let resolve = |mode, i| Border::new(mode).resolve(i, 4);
+        assert_eq!(
+            (-3..7)
+                .map(|i| resolve(BorderMode::Clamp, i))
+                .collect::<Vec<_>>(),
+            [0, 0, 0, 0, 1, 2, 3, 3, 3, 3].map(Some)
+        );
+        assert_eq!(
+            (-3..7)
+                .map(|i| resolve(BorderMode::Reflect, i))
+                .collect::<Vec<_>>(),
+            [3, 2, 1, 0, 1, 2, 3, 2, 1, 0].map(Some)
+        );
+        assert_eq!(
+            (-3..7)
+                .map(|i| resolve(BorderMode::Wrap, i))
+                .collect::<Vec<_>>(),
+            [1, 2, 3, 0, 1, 2, 3, 0, 1, 2].map(Some)
+        );
+        assert_eq!(resolve(BorderMode::Constant, -1), None);
+        assert_eq!(Border::new(BorderMode::Reflect).resolve(-5, 1), Some(0));
+        let plane = [1.0, 2.0];
+        assert_eq!(Border::constant(9.0).sample(&plane, 2, 1, 2, 0), 9.0);
+    }
+
+    #[test]
+    fn convolution_flips_kernel() {
+        let planes = line(&[0.0, 0.0, 1.0, 0.0, 0.0]);
+        let kernel = Kernel::new(3, 1, vec![1.0, 2.0, 3.0]);
+        let out = convolve(&planes, &kernel, Border::default());
+        assert_eq!(out.plane(0), [0.0, 1.0, 2.0, 3.0, 0.0]);
+        let separable = convolve_separable(&planes, &[1.0], &[1.0, 2.0, 3.0], Border::default());
+        assert_eq!(separable.plane(0), out.plane(0));
+    }
+
+    #[test]
+    fn blurs_keep_constant_images() {
+        let mut planes: Planes<f32, 3> = Planes::new(7, 5);
+        for p in 0..3 {
+            planes.plane_mut(p).fill(100.0);
+        }
+        let filters = [
+            Filter::Box { radius: 2 },
+            Filter::Gaussian {
+                radius: 3,
+                sigma: 1.5,
+            },
+            Filter::Median { radius: 1 },
+            Filter::Bilateral {
+                radius: 2,
+                sigma_space: 2.0,
+                sigma_range: 10.0,
+            },
+        ];
+        for filter in filters {
+            let out = filter.apply(&planes, Border::new(BorderMode::Reflect));
+            assert!(out.plane(1).iter().all(|v| (v - 100.0).abs() < 1e-3));
+        }
+        let dark = box_blur(&planes, 1, Border::constant(0.0));
+        assert!((dark.get_pixel(0, 0)[0] - 400.0 / 9.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn median_removes_impulses() {
+        let planes = line(&[10.0, 10.0, 255.0, 10.0, 0.0, 10.0]);
+        let out = median(&planes, 1, Border::default());
+        assert_eq!(out.plane(0), [10.0, 10.0, 10.0, 10.0, 10.0, 10.0]);
+    }
+
+    #[test]
+    fn bilateral_keeps_edges() {
+        let planes = line(&[0.0, 0.0, 0.0, 200.0, 200.0, 200.0]);
+        let out = bilateral(&planes, 2, 2.0, 10.0, Border::default());
+        assert!(out.plane(0)[2] < 0.01 && out.plane(0)[3] > 199.99);
+        let blurred = box_blur(&planes, 1, Border::default());
+        assert!((blurred.plane(0)[2] - 200.0 / 3.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn kernel_parsing() {
+        let laplacian = Kernel::new(3, 3, vec![0.0, -1.0, 0.0, -1.0, 4.0, -1.0, 0.0, -1.0, 0.0]);
+        let json = "[[0, -1, 0],\n [-1, 4, -1], [0,-1,0]]";
+        assert_eq!(Kernel::parse(json).unwrap(), laplacian);
+        let text = "# laplacian\n0 -1 0\n-1 4 -1  # center\n\n0 -1 0\n";
+        assert_eq!(Kernel::parse(text).unwrap(), laplacian);
+        let row = Kernel::parse("[0.25, 0.5, 0.25]").unwrap();
+        assert_eq!((row.width(), row.height(), row.sum()), (3, 1, 1.0));
+
+        for bad in [
+            "",
+            "1 2",
+            "1 2 3\n4 5",
+            "[[1, 2, 3] [4, 5, 6], [7, 8, 9]]",
+            "[[1]",
+            "1 x 3",
+        ] {
+            assert!(
+                matches!(Kernel::parse(bad), Err(Error::InvalidData(_))),
+                "{:?}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn gaussian_weights_are_normalized() {
+        let weights = gaussian_weights(3, 1.0);
+        assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        assert_eq!(weights[0], weights[6]);
+        assert!(weights[3] > weights[2]);
+        assert_eq!(
+            Kernel::outer(&[1.0, 2.0, 1.0], &[1.0, 0.0, -1.0]).weight(0, 1),
+            2.0
+        );
+    }
+}
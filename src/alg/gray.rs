@@ -1,4 +1,9 @@
 use image::{DynamicImage, GenericImageView, GrayImage};
+use std::fmt;
+
+use super::color::unit_to_u8;
+use super::integral::IntegralImage;
+use super::lut::Lut;
 
 pub fn threshold(image: &GrayImage, level: u8) -> GrayImage {
     let mut out = image.clone();
@@ -8,34 +13,435 @@ pub fn threshold(image: &GrayImage, level: u8) -> GrayImage {
     out
 }
 
+/// Mean gray level of `image`, or 0 for an empty image.
 pub fn average_gray_level(image: &GrayImage) -> u8 {
     let pixels = image.pixels();
     let len = pixels.len() as u64;
+    if len == 0 {
+        return 0;
+    }
     let sum: u64 = pixels.into_iter().map(|x| x.0[0] as u64).sum();
     (sum / len) as u8
 }
 
-pub fn histogram_equalize(image: &GrayImage) -> GrayImage {
-    let mut gray_count = [0u64; 256];
+/// Methods of choosing a binarization threshold from the gray level histogram.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ThresholdMethod {
+    /// Mean gray level.
+    #[default]
+    Mean,
+    /// Maximum between-class variance (Otsu 1979).
+    Otsu,
+    /// Largest distance from the line between the histogram peak and its far end (Zack 1977).
+    Triangle,
+    /// Iterative mean of the two class means (Ridler & Calvard 1978).
+    Isodata,
+    /// Maximum sum of the class entropies (Kapur, Sahoo & Wong 1985).
+    Kapur,
+    /// Minimum cross entropy (Li & Lee 1993).
+    Li,
+}
+
+impl ThresholdMethod {
+    pub fn name(self) -> &'static str {
+        match self {
+            ThresholdMethod::Mean => "mean",
+            ThresholdMethod::Otsu => "otsu",
+            ThresholdMethod::Triangle => "triangle",
+            ThresholdMethod::Isodata => "isodata",
+            ThresholdMethod::Kapur => "kapur",
+            ThresholdMethod::Li => "li",
+        }
+    }
+}
+
+impl fmt::Display for ThresholdMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Number of pixels of every gray level.
+pub fn gray_histogram(image: &GrayImage) -> [u64; 256] {
+    let mut count = [0u64; 256];
     for luma in image.pixels() {
-        gray_count[luma.0[0] as usize] += 1;
+        count[luma.0[0] as usize] += 1;
+    }
+    count
+}
+
+/// Chooses a threshold for `threshold` with `method`: levels up to and including the result
+/// become black.
+pub fn auto_threshold(image: &GrayImage, method: ThresholdMethod) -> u8 {
+    let hist = gray_histogram(image);
+    match method {
+        ThresholdMethod::Mean => average_gray_level(image),
+        ThresholdMethod::Otsu => otsu_threshold(&hist),
+        ThresholdMethod::Triangle => triangle_threshold(&hist),
+        ThresholdMethod::Isodata => isodata_threshold(&hist),
+        ThresholdMethod::Kapur => kapur_threshold(&hist),
+        ThresholdMethod::Li => li_threshold(&hist),
     }
+}
 
-    let mut gray_map = [0u8; 256];
-    let len = image.width() as u64 * image.height() as u64;
-    let mut sum = 0u64;
+/// First and last non-empty level of `hist`, or `None` if it is empty.
+fn occupied_range(hist: &[u64; 256]) -> Option<(usize, usize)> {
+    let first = hist.iter().position(|&n| n > 0)?;
+    let last = hist.iter().rposition(|&n| n > 0)?;
+    Some((first, last))
+}
+
+/// Pixel count and level sum of the bins `levels`.
+fn class_moments(hist: &[u64; 256], levels: std::ops::Range<usize>) -> (f64, f64) {
+    levels.fold((0.0, 0.0), |(n, sum), i| {
+        (n + hist[i] as f64, sum + (i as u64 * hist[i]) as f64)
+    })
+}
+
+pub fn otsu_threshold(hist: &[u64; 256]) -> u8 {
+    let Some((first, last)) = occupied_range(hist) else {
+        return 0;
+    };
+    let (total, total_sum) = class_moments(hist, 0..256);
+
+    let mut best = (first, -1.0);
+    let mut n0 = 0.0;
+    let mut sum0 = 0.0;
+    for (t, &n) in hist.iter().enumerate().take(last).skip(first) {
+        n0 += n as f64;
+        sum0 += (t as u64 * n) as f64;
+        let n1 = total - n0;
+        let mean0 = sum0 / n0;
+        let mean1 = (total_sum - sum0) / n1;
+        let variance = n0 * n1 * (mean0 - mean1).powi(2);
+        if variance > best.1 {
+            best = (t, variance);
+        }
+    }
+    best.0 as u8
+}
+
+pub fn triangle_threshold(hist: &[u64; 256]) -> u8 {
+    let Some((first, last)) = occupied_range(hist) else {
+        return 0;
+    };
+    let peak = (first..=last)
+        .max_by_key(|&i| (hist[i], std::cmp::Reverse(i)))
+        .unwrap();
+
+    // The line runs from the peak to the empty bin just past the farther end of the histogram; the
+    // threshold is the level lying farthest below it.
+    let (end, levels) = if peak - first > last - peak {
+        (first as f64 - 1.0, first..peak)
+    } else {
+        (last as f64 + 1.0, peak + 1..last + 1)
+    };
+    let height = hist[peak] as f64;
+    let distance = |i: usize| height * (i as f64 - end) / (peak as f64 - end) - hist[i] as f64;
+
+    let mut best = (peak, f64::MIN);
+    for i in levels {
+        let d = distance(i);
+        if d > best.1 {
+            best = (i, d);
+        }
+    }
+    best.0 as u8
+}
+
+pub fn isodata_threshold(hist: &[u64; 256]) -> u8 {
+    let Some((first, last)) = occupied_range(hist) else {
+        return 0;
+    };
+    let (total, total_sum) = class_moments(hist, 0..256);
+    let mut t = (total_sum / total) as usize;
+    for _ in 0..256 {
+        let (n0, sum0) = class_moments(hist, 0..t + 1);
+        let n1 = total - n0;
+        if n0 == 0.0 || n1 == 0.0 {
+            break;
+        }
+        let mean0 = sum0 / n0;
+        let mean1 = (total_sum - sum0) / n1;
+        let next = (((mean0 + mean1) / 2.0) as usize).clamp(first, last);
+        if next == t {
+            break;
+        }
+        t = next;
+    }
+    t as u8
+}
+
+pub fn kapur_threshold(hist: &[u64; 256]) -> u8 {
+    let Some((first, last)) = occupied_range(hist) else {
+        return 0;
+    };
+    let (total, _) = class_moments(hist, 0..256);
+    let p: Vec<f64> = hist.iter().map(|&n| n as f64 / total).collect();
+    // Entropy of a class with probability `w` whose bins have the plogp sum `plogp`.
+    let entropy = |w: f64, plogp: f64| if w > 0.0 { w.ln() - plogp / w } else { 0.0 };
+    let plogp = |p: f64| if p > 0.0 { p * p.ln() } else { 0.0 };
+    let total_plogp: f64 = p.iter().map(|&p| plogp(p)).sum();
+
+    let mut best = (first, f64::MIN);
+    let mut w0 = 0.0;
+    let mut plogp0 = 0.0;
+    for (t, &p_t) in p.iter().enumerate().take(last).skip(first) {
+        w0 += p_t;
+        plogp0 += plogp(p_t);
+        let h = entropy(w0, plogp0) + entropy(1.0 - w0, total_plogp - plogp0);
+        if h > best.1 {
+            best = (t, h);
+        }
+    }
+    best.0 as u8
+}
+
+pub fn li_threshold(hist: &[u64; 256]) -> u8 {
+    let Some((first, last)) = occupied_range(hist) else {
+        return 0;
+    };
+    if first == last {
+        return first as u8;
+    }
+    // Levels are shifted by one so that the class means are never zero under the logarithm.
+    let (total, total_sum) = class_moments(hist, 0..256);
+    let mut t = total_sum / total;
+    for _ in 0..256 {
+        let split = (t as usize).clamp(first, last - 1);
+        let (n0, sum0) = class_moments(hist, 0..split + 1);
+        let mean0 = sum0 / n0 + 1.0;
+        let mean1 = (total_sum - sum0) / (total - n0) + 1.0;
+        let next = (mean1 - mean0) / (mean1.ln() - mean0.ln()) - 1.0;
+        if (next - t).abs() < 0.5 {
+            t = next;
+            break;
+        }
+        t = next;
+    }
+    (t as usize).clamp(first, last - 1) as u8
+}
+
+/// Cuts splitting `hist` into `levels` classes with the largest between-class variance (multi-level
+/// Otsu), found exactly by dynamic programming. Class `i` holds the levels above cut `i - 1` up to
+/// and including cut `i`, as with `threshold`.
+pub fn multi_otsu_thresholds(hist: &[u64; 256], levels: usize) -> Vec<u8> {
+    assert!((2..=256).contains(&levels), "levels must be in 2~256");
+    let mut count = [0.0f64; 257];
+    let mut sum = [0.0f64; 257];
     for i in 0..256 {
-        sum += gray_count[i];
-        gray_map[i] = ((sum * 255 + len / 2) / len) as u8;
+        count[i + 1] = count[i] + hist[i] as f64;
+        sum[i + 1] = sum[i] + (i as u64 * hist[i]) as f64;
+    }
+    // Maximizing the between-class variance is maximizing the sum of `sum^2 / count` of the
+    // classes; `score(a, b)` is that term for the class of bins `a..b`.
+    let score = |a: usize, b: usize| {
+        let n = count[b] - count[a];
+        if n > 0.0 {
+            (sum[b] - sum[a]).powi(2) / n
+        } else {
+            0.0
+        }
+    };
+
+    // best[k][j]: best score of splitting bins 0..j into k + 1 classes, with from[k][j] the start
+    // of the last class.
+    let mut best = vec![[f64::MIN; 257]; levels];
+    let mut from = vec![[0usize; 257]; levels];
+    for (j, b) in best[0].iter_mut().enumerate().skip(1) {
+        *b = score(0, j);
+    }
+    for k in 1..levels {
+        for j in k + 1..=256 {
+            for i in k..j {
+                let v = best[k - 1][i] + score(i, j);
+                if v > best[k][j] {
+                    best[k][j] = v;
+                    from[k][j] = i;
+                }
+            }
+        }
+    }
+
+    let mut cuts = vec![0u8; levels - 1];
+    let mut end = 256;
+    for k in (1..levels).rev() {
+        end = from[k][end];
+        cuts[k - 1] = (end - 1) as u8;
+    }
+    cuts
+}
+
+/// Labels every pixel with the number of `cuts` below it, so pixels up to and including `cuts[0]`
+/// get 0 and pixels above the last cut get `cuts.len()`. The cuts must be increasing.
+pub fn quantize(image: &GrayImage, cuts: &[u8]) -> GrayImage {
+    let mut label_map = [0u8; 256];
+    for (level, label) in label_map.iter_mut().enumerate() {
+        *label = cuts.iter().filter(|&&c| level as u8 > c).count() as u8;
+    }
+    let mut out = image.clone();
+    for p in out.iter_mut() {
+        *p = label_map[*p as usize];
+    }
+    out
+}
+
+/// Methods of computing a threshold for every pixel from its neighborhood.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum AdaptiveMethod {
+    /// Local mean minus `k`.
+    #[default]
+    Mean,
+    /// Gaussian weighted local mean minus `k`.
+    Gaussian,
+    /// `m * (1 + k * (s / 128 - 1))` from the local mean `m` and deviation `s` (Sauvola 2000).
+    Sauvola,
+    /// `m - k * s` from the local mean `m` and deviation `s` (Niblack 1986).
+    Niblack,
+    /// Midrange of the local minimum and maximum, or 128 where their contrast is below `k`
+    /// (Bernsen 1986).
+    Bernsen,
+}
+
+impl AdaptiveMethod {
+    pub fn name(self) -> &'static str {
+        match self {
+            AdaptiveMethod::Mean => "mean",
+            AdaptiveMethod::Gaussian => "gaussian",
+            AdaptiveMethod::Sauvola => "sauvola",
+            AdaptiveMethod::Niblack => "niblack",
+            AdaptiveMethod::Bernsen => "bernsen",
+        }
+    }
+
+    /// The usual `k` of the method.
+    pub fn default_k(self) -> f32 {
+        match self {
+            AdaptiveMethod::Mean | AdaptiveMethod::Gaussian => 5.0,
+            AdaptiveMethod::Sauvola | AdaptiveMethod::Niblack => 0.2,
+            AdaptiveMethod::Bernsen => 15.0,
+        }
+    }
+}
+
+impl fmt::Display for AdaptiveMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
     }
+}
+
+/// Mean of every `(2 * radius + 1)` square window of `values`, cut at the image border.
+fn box_mean(values: &[f64], width: u32, height: u32, radius: u32) -> Vec<f64> {
+    let integral = IntegralImage::new(width, height, values.iter().copied());
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| integral.window_mean(x, y, radius))
+        .collect()
+}
+
+/// Minimum or maximum (by `pick`) of every `(2 * radius + 1)` square window, computed separably.
+fn window_extreme(image: &GrayImage, radius: u32, pick: fn(u8, u8) -> u8) -> GrayImage {
+    let (width, height) = image.dimensions();
+    let extreme = |get: &dyn Fn(u32) -> u8, at: u32, len: u32| {
+        let lo = at.saturating_sub(radius);
+        let hi = (at + radius).min(len - 1);
+        (lo..=hi).map(get).reduce(pick).unwrap()
+    };
+    let rows = GrayImage::from_fn(width, height, |x, y| {
+        image::Luma([extreme(&|i| image.get_pixel(i, y).0[0], x, width)])
+    });
+    GrayImage::from_fn(width, height, |x, y| {
+        image::Luma([extreme(&|i| rows.get_pixel(x, i).0[0], y, height)])
+    })
+}
+
+/// Threshold of every pixel of `image` computed by `method` over a `window` wide square
+/// neighborhood. The thresholds are floored to 0~255, so `threshold_by_surface` with the result
+/// binarizes exactly as comparing with the unrounded values would (within 0~255).
+pub fn adaptive_threshold_surface(
+    image: &GrayImage,
+    method: AdaptiveMethod,
+    window: u32,
+    k: f32,
+) -> GrayImage {
+    let (width, height) = image.dimensions();
+    let radius = window / 2;
+    let k = k as f64;
+    let samples: Vec<f64> = image.iter().map(|&p| p as f64).collect();
+
+    let thresholds: Vec<f64> = match method {
+        AdaptiveMethod::Mean => {
+            let mean = box_mean(&samples, width, height, radius);
+            mean.into_iter().map(|m| m - k).collect()
+        }
+        AdaptiveMethod::Gaussian => {
+            // Three box passes approximate a gaussian; sigma follows the window size the way
+            // OpenCV derives it for a kernel of that size.
+            let sigma = 0.3 * ((window.max(3) - 1) as f64 * 0.5 - 1.0) + 0.8;
+            let box_radius = (((4.0 * sigma * sigma + 1.0).sqrt() - 1.0) / 2.0).round() as u32;
+            let mut mean = samples;
+            for _ in 0..3 {
+                mean = box_mean(&mean, width, height, box_radius);
+            }
+            mean.into_iter().map(|m| m - k).collect()
+        }
+        AdaptiveMethod::Sauvola | AdaptiveMethod::Niblack => {
+            let sums = IntegralImage::from_gray(image);
+            let squares = IntegralImage::from_gray_squared(image);
+            (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    let mean = sums.window_mean(x, y, radius);
+                    let deviation = (squares.window_mean(x, y, radius) - mean * mean)
+                        .max(0.0)
+                        .sqrt();
+                    match method {
+                        AdaptiveMethod::Sauvola => mean * (1.0 + k * (deviation / 128.0 - 1.0)),
+                        _ => mean - k * deviation,
+                    }
+                })
+                .collect()
+        }
+        AdaptiveMethod::Bernsen => {
+            let min = window_extreme(image, radius, u8::min);
+            let max = window_extreme(image, radius, u8::max);
+            min.iter()
+                .zip(max.iter())
+                .map(|(&lo, &hi)| {
+                    if ((hi - lo) as f64) < k {
+                        128.0
+                    } else {
+                        (lo as f64 + hi as f64) / 2.0
+                    }
+                })
+                .collect()
+        }
+    };
+
+    let levels = thresholds
+        .into_iter()
+        .map(|t| t.floor().clamp(0.0, 255.0) as u8)
+        .collect();
+    GrayImage::from_raw(width, height, levels).unwrap()
+}
 
+/// Binarizes `image` against the per-pixel thresholds of `surface`, which must have the same size:
+/// pixels above their threshold become white.
+pub fn threshold_by_surface(image: &GrayImage, surface: &GrayImage) -> GrayImage {
+    assert_eq!(image.dimensions(), surface.dimensions());
     let mut out = image.clone();
-    for luma in out.iter_mut() {
-        *luma = gray_map[*luma as usize];
+    for (p, t) in out.iter_mut().zip(surface.iter()) {
+        *p = if *p > *t { 255 } else { 0 };
     }
     out
 }
 
+pub fn histogram_equalize(image: &GrayImage) -> GrayImage {
+    Lut::equalization(&gray_histogram(image)).apply_plane(0, image)
+}
+
 pub fn split_planes(image: &DynamicImage) -> Vec<GrayImage> {
     let mut out = Vec::new();
     for _ in 0..3 {
@@ -48,3 +454,590 @@ pub fn split_planes(image: &DynamicImage) -> Vec<GrayImage> {
     }
     out
 }
+
+/// Bin (of 256 over `range`) that `value` falls in, and its position inside the bin.
+pub(crate) fn plane_bin(value: f32, range: (f32, f32)) -> (usize, f32) {
+    let pos = ((value - range.0) / (range.1 - range.0) * 256.0).clamp(0.0, 256.0);
+    let bin = (pos as usize).min(255);
+    (bin, pos - bin as f32)
+}
+
+/// Number of values of `plane` in each of 256 bins over `range`.
+pub fn plane_histogram(plane: &[f32], range: (f32, f32)) -> [u64; 256] {
+    let mut count = [0u64; 256];
+    for v in plane {
+        count[plane_bin(*v, range).0] += 1;
+    }
+    count
+}
+
+/// Equalizes the histogram of a plane whose values lie in `range`. Values are counted in 256 bins
+/// and mapped through the cumulative histogram, interpolated inside each bin so that the result is
+/// not quantized.
+pub fn equalize_plane(plane: &mut [f32], range: (f32, f32)) {
+    let (lo, hi) = range;
+    let count = plane_histogram(plane, range);
+
+    let mut cdf = [0u64; 257];
+    for i in 0..256 {
+        cdf[i + 1] = cdf[i] + count[i];
+    }
+
+    let len = plane.len() as f32;
+    for v in plane.iter_mut() {
+        let (bin, frac) = plane_bin(*v, range);
+        let rank = cdf[bin] as f32 + frac * count[bin] as f32;
+        *v = lo + rank / len * (hi - lo);
+    }
+}
+
+/// Shapes of target histograms for histogram matching.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum TargetCurve {
+    /// Flat, the same as equalization.
+    #[default]
+    Uniform,
+    /// Gaussian centered in the range, with the range spanning six standard deviations.
+    Gaussian,
+    /// Exponential decay from the dark end, falling to 1/e at a quarter of the range.
+    Exponential,
+}
+
+impl TargetCurve {
+    /// Relative weight of each of the 256 bins.
+    pub fn histogram(self) -> [f64; 256] {
+        std::array::from_fn(|i| {
+            let x = (i as f64 + 0.5) / 256.0;
+            match self {
+                TargetCurve::Uniform => 1.0,
+                TargetCurve::Gaussian => (-((x - 0.5) * 6.0).powi(2) / 2.0).exp(),
+                TargetCurve::Exponential => (-x * 4.0).exp(),
+            }
+        })
+    }
+}
+
+/// Cumulative `target` normalized to end at 1, with a leading zero.
+fn target_cdf(target: &[f64; 256]) -> [f64; 257] {
+    let total: f64 = target.iter().sum();
+    let mut cdf = [0.0; 257];
+    for (i, t) in target.iter().enumerate() {
+        cdf[i + 1] = cdf[i] + t / total;
+    }
+    cdf
+}
+
+/// Maps the gray levels of `image` so that its histogram follows `target`, the relative weight of
+/// every level: each level goes to the first target level whose cumulative weight reaches its own.
+pub fn match_histogram(image: &GrayImage, target: &[f64; 256]) -> GrayImage {
+    let len = image.width() as u64 * image.height() as u64;
+    if len == 0 || target.iter().sum::<f64>() <= 0.0 {
+        return image.clone();
+    }
+    let hist = gray_histogram(image);
+    let target = target_cdf(target);
+
+    let mut gray_map = [0u8; 256];
+    let mut sum = 0u64;
+    for (level, count) in hist.iter().enumerate() {
+        sum += count;
+        let rank = sum as f64 / len as f64;
+        // Guard against the last cumulative weight rounding to just below 1.
+        let t = target[1..]
+            .iter()
+            .position(|&c| c >= rank - 1e-9)
+            .unwrap_or(255);
+        gray_map[level] = t as u8;
+    }
+
+    let mut out = image.clone();
+    for luma in out.iter_mut() {
+        *luma = gray_map[*luma as usize];
+    }
+    out
+}
+
+/// Maps a plane whose values lie in `range` so that its histogram over 256 bins follows `target`,
+/// interpolating inside the bins as `equalize_plane` does, which is the case of a flat target.
+pub fn match_plane(plane: &mut [f32], range: (f32, f32), target: &[f64; 256]) {
+    if plane.is_empty() || target.iter().sum::<f64>() <= 0.0 {
+        return;
+    }
+    let (lo, hi) = range;
+    let count = plane_histogram(plane, range);
+    let mut cdf = [0u64; 257];
+    for i in 0..256 {
+        cdf[i + 1] = cdf[i] + count[i];
+    }
+    let target = target_cdf(target);
+
+    let len = plane.len() as f64;
+    for v in plane.iter_mut() {
+        let (bin, frac) = plane_bin(*v, range);
+        let rank = (cdf[bin] as f64 + frac as f64 * count[bin] as f64) / len;
+        let t = target[1..].iter().position(|&c| c >= rank).unwrap_or(255);
+        let width = target[t + 1] - target[t];
+        let inside = if width > 0.0 {
+            ((rank - target[t]) / width).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        *v = lo + ((t as f64 + inside) / 256.0) as f32 * (hi - lo);
+    }
+}
+
+/// Settings of contrast-limited adaptive histogram equalization.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClaheParams {
+    /// Number of tiles across and down the image.
+    pub tiles: (u32, u32),
+    /// Highest count of a histogram bin in a tile, as a multiple of the mean count; the excess is
+    /// spread over all bins.
+    pub clip_limit: f32,
+}
+
+impl Default for ClaheParams {
+    fn default() -> Self {
+        ClaheParams {
+            tiles: (8, 8),
+            clip_limit: 2.0,
+        }
+    }
+}
+
+/// Tile interpolation along one axis: for every pixel, the two tiles whose centers surround it and
+/// the weight of the second one.
+fn tile_weights(len: usize, tiles: usize) -> Vec<(usize, usize, f32)> {
+    let center = |i: usize| (i * len / tiles + (i + 1) * len / tiles) as f32 / 2.0;
+    (0..len)
+        .map(|p| {
+            let pc = p as f32 + 0.5;
+            if pc <= center(0) {
+                (0, 0, 0.0)
+            } else if pc >= center(tiles - 1) {
+                (tiles - 1, tiles - 1, 0.0)
+            } else {
+                let i = (0..tiles - 1).rfind(|&i| center(i) <= pc).unwrap();
+                (i, i + 1, (pc - center(i)) / (center(i + 1) - center(i)))
+            }
+        })
+        .collect()
+}
+
+/// Contrast-limited adaptive histogram equalization of a `width` by `height` plane whose values
+/// lie in `range`. Every tile gets its own clipped cumulative histogram, mapped as in
+/// `equalize_plane`, and each pixel blends the mappings of the four nearest tiles bilinearly.
+pub fn clahe_plane(
+    plane: &mut [f32],
+    width: u32,
+    height: u32,
+    range: (f32, f32),
+    params: ClaheParams,
+) {
+    let (w, h) = (width as usize, height as usize);
+    if w == 0 || h == 0 {
+        return;
+    }
+    let (lo, hi) = range;
+    let pos_of = |v: f32| ((v - lo) / (hi - lo) * 256.0).clamp(0.0, 256.0);
+    let tiles_x = (params.tiles.0 as usize).clamp(1, w);
+    let tiles_y = (params.tiles.1 as usize).clamp(1, h);
+
+    // Normalized cumulative histogram of every tile, with a leading zero.
+    let mut cdfs = vec![[0.0f32; 257]; tiles_x * tiles_y];
+    for ty in 0..tiles_y {
+        let (y0, y1) = (ty * h / tiles_y, (ty + 1) * h / tiles_y);
+        for tx in 0..tiles_x {
+            let (x0, x1) = (tx * w / tiles_x, (tx + 1) * w / tiles_x);
+            let mut count = [0.0f64; 256];
+            for y in y0..y1 {
+                for v in &plane[y * w + x0..y * w + x1] {
+                    count[(pos_of(*v) as usize).min(255)] += 1.0;
+                }
+            }
+            let n = ((x1 - x0) * (y1 - y0)) as f64;
+            let limit = params.clip_limit as f64 * n / 256.0;
+            let excess: f64 = count.iter().map(|c| (c - limit).max(0.0)).sum();
+            let cdf = &mut cdfs[ty * tiles_x + tx];
+            let mut sum = 0.0;
+            for (i, c) in count.iter().enumerate() {
+                sum += c.min(limit) + excess / 256.0;
+                cdf[i + 1] = (sum / n) as f32;
+            }
+        }
+    }
+
+    let columns = tile_weights(w, tiles_x);
+    let rows = tile_weights(h, tiles_y);
+    for (y, &(ty0, ty1, wy)) in rows.iter().enumerate() {
+        for (x, &(tx0, tx1, wx)) in columns.iter().enumerate() {
+            let v = &mut plane[y * w + x];
+            let pos = pos_of(*v);
+            let bin = (pos as usize).min(255);
+            let frac = pos - bin as f32;
+            let map = |tx: usize, ty: usize| {
+                let cdf = &cdfs[ty * tiles_x + tx];
+                cdf[bin] + frac * (cdf[bin + 1] - cdf[bin])
+            };
+            let top = map(tx0, ty0) * (1.0 - wx) + map(tx1, ty0) * wx;
+            let bottom = map(tx0, ty1) * (1.0 - wx) + map(tx1, ty1) * wx;
+            *v = lo + (top * (1.0 - wy) + bottom * wy) * (hi - lo);
+        }
+    }
+}
+
+/// Contrast-limited adaptive histogram equalization of a grayscale image.
+pub fn clahe(image: &GrayImage, params: ClaheParams) -> GrayImage {
+    let mut plane: Vec<f32> = image.iter().map(|&p| p as f32 / 255.0).collect();
+    clahe_plane(
+        &mut plane,
+        image.width(),
+        image.height(),
+        (0.0, 1.0),
+        params,
+    );
+    let samples = plane.into_iter().map(unit_to_u8).collect();
+    GrayImage::from_raw(image.width(), image.height(), samples).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray_ramp(width: u32, height: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            image::Luma([((x + y * width) * 255 / (width * height - 1)) as u8])
+        })
+    }
+
+    #[test]
+    fn threshold_splits_at_level() {
+        let image = GrayImage::from_raw(4, 1, vec![0, 99, 100, 101]).unwrap();
+        let binary = threshold(&image, 100);
+        assert_eq!(binary.as_raw(), &vec![0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn threshold_extremes() {
+        let image = gray_ramp(16, 16);
+        assert!(threshold(&image, 255).iter().all(|&p| p == 0));
+        assert!(threshold(&image, 0).iter().skip(1).all(|&p| p == 255));
+    }
+
+    #[test]
+    fn average_gray_level_of_uniform_image() {
+        let image = GrayImage::from_pixel(5, 3, image::Luma([77]));
+        assert_eq!(average_gray_level(&image), 77);
+    }
+
+    #[test]
+    fn average_gray_level_truncates_mean() {
+        let image = GrayImage::from_raw(3, 1, vec![0, 100, 201]).unwrap();
+        assert_eq!(average_gray_level(&image), 100);
+    }
+
+    #[test]
+    fn average_gray_level_of_empty_image() {
+        assert_eq!(average_gray_level(&GrayImage::new(0, 0)), 0);
+        assert_eq!(average_gray_level(&GrayImage::new(8, 0)), 0);
+    }
+
+    /// Two gaussian modes: a large dark one at 50 and a smaller, wider bright one at 190.
+    fn bimodal_histogram() -> [u64; 256] {
+        std::array::from_fn(|i| {
+            let mode = |center: f64, sigma: f64, height: f64| {
+                height * (-((i as f64 - center) / sigma).powi(2) / 2.0).exp()
+            };
+            (mode(50.0, 12.0, 1000.0) + mode(190.0, 15.0, 400.0)).round() as u64
+        })
+    }
+
+    const METHODS: [ThresholdMethod; 6] = [
+        ThresholdMethod::Mean,
+        ThresholdMethod::Otsu,
+        ThresholdMethod::Triangle,
+        ThresholdMethod::Isodata,
+        ThresholdMethod::Kapur,
+        ThresholdMethod::Li,
+    ];
+
+    #[test]
+    fn thresholds_separate_bimodal_histogram() {
+        let hist = bimodal_histogram();
+        let pixels: Vec<u8> = (0..256)
+            .flat_map(|i| std::iter::repeat_n(i as u8, hist[i] as usize))
+            .collect();
+        let image = GrayImage::from_raw(pixels.len() as u32, 1, pixels).unwrap();
+        for method in METHODS {
+            let t = auto_threshold(&image, method);
+            assert!((51..190).contains(&t), "{} threshold {}", method, t);
+        }
+        // The valley between the modes is empty, so Otsu and isodata split the pixels alike.
+        let otsu = threshold(&image, otsu_threshold(&hist));
+        assert_eq!(otsu, threshold(&image, isodata_threshold(&hist)));
+    }
+
+    #[test]
+    fn thresholds_of_degenerate_histograms() {
+        let empty = [0u64; 256];
+        let mut single = [0u64; 256];
+        single[42] = 10;
+        for hist in [empty, single] {
+            let expected = if hist[42] > 0 { 42 } else { 0 };
+            assert_eq!(otsu_threshold(&hist), expected);
+            assert_eq!(triangle_threshold(&hist), expected);
+            assert_eq!(isodata_threshold(&hist), expected);
+            assert_eq!(kapur_threshold(&hist), expected);
+            assert_eq!(li_threshold(&hist), expected);
+        }
+        for method in METHODS {
+            assert_eq!(auto_threshold(&GrayImage::new(0, 0), method), 0);
+        }
+    }
+
+    #[test]
+    fn otsu_splits_two_levels() {
+        let mut hist = [0u64; 256];
+        hist[30] = 100;
+        hist[220] = 50;
+        let t = otsu_threshold(&hist);
+        assert!((30..220).contains(&t));
+        let image = GrayImage::from_raw(2, 1, vec![30, 220]).unwrap();
+        assert_eq!(threshold(&image, t).as_raw(), &vec![0, 255]);
+    }
+
+    #[test]
+    fn multi_otsu_with_two_levels_is_otsu() {
+        let hist = bimodal_histogram();
+        assert_eq!(multi_otsu_thresholds(&hist, 2), vec![otsu_threshold(&hist)]);
+    }
+
+    #[test]
+    fn multi_otsu_separates_modes() {
+        let mut hist = [0u64; 256];
+        for (center, height) in [(30, 500.0), (120, 300.0), (210, 800.0)] {
+            for (i, n) in hist.iter_mut().enumerate() {
+                let d = (i as f64 - center as f64) / 10.0;
+                *n += (height * (-d * d / 2.0).exp()).round() as u64;
+            }
+        }
+        let cuts = multi_otsu_thresholds(&hist, 3);
+        assert_eq!(cuts.len(), 2);
+        assert!((31..120).contains(&cuts[0]), "{:?}", cuts);
+        assert!((121..210).contains(&cuts[1]), "{:?}", cuts);
+
+        // More levels than distinct values leave classes empty but keep the cuts increasing.
+        let cuts = multi_otsu_thresholds(&hist, 8);
+        assert!(cuts.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn quantize_labels_by_cuts() {
+        let image = GrayImage::from_raw(6, 1, vec![0, 10, 11, 100, 101, 255]).unwrap();
+        assert_eq!(
+            quantize(&image, &[10, 100]).as_raw(),
+            &vec![0, 0, 1, 1, 2, 2]
+        );
+        assert_eq!(quantize(&image, &[]).as_raw(), &vec![0; 6]);
+        let binary = threshold(&image, 10);
+        let labels = quantize(&image, &[10]);
+        assert!(binary
+            .iter()
+            .zip(labels.iter())
+            .all(|(&b, &l)| b == l * 255));
+    }
+
+    const ADAPTIVE_METHODS: [AdaptiveMethod; 5] = [
+        AdaptiveMethod::Mean,
+        AdaptiveMethod::Gaussian,
+        AdaptiveMethod::Sauvola,
+        AdaptiveMethod::Niblack,
+        AdaptiveMethod::Bernsen,
+    ];
+
+    #[test]
+    fn adaptive_thresholds_follow_uneven_lighting() {
+        // Paper lit from dark on the left to bright on the right, with a dark stroke every 8th
+        // column that is brighter on the right than the paper on the left.
+        let (width, height) = (96, 24);
+        let paper = |x: u32| 60 + x * 160 / (width - 1);
+        let is_stroke = |x: u32| x % 8 == 3;
+        let image = GrayImage::from_fn(width, height, |x, _| {
+            let v = paper(x);
+            image::Luma([if is_stroke(x) { v / 3 } else { v } as u8])
+        });
+        let expected = GrayImage::from_fn(width, height, |x, _| {
+            image::Luma([if is_stroke(x) { 0 } else { 255 }])
+        });
+
+        let hist = gray_histogram(&image);
+        assert_ne!(threshold(&image, otsu_threshold(&hist)), expected);
+        for method in ADAPTIVE_METHODS {
+            let surface = adaptive_threshold_surface(&image, method, 15, method.default_k());
+            assert_eq!(
+                threshold_by_surface(&image, &surface),
+                expected,
+                "{}",
+                method
+            );
+        }
+    }
+
+    #[test]
+    fn adaptive_threshold_of_flat_image() {
+        let image = GrayImage::from_pixel(9, 9, image::Luma([100]));
+        let surface = adaptive_threshold_surface(&image, AdaptiveMethod::Mean, 5, 0.0);
+        assert!(surface.iter().all(|&t| t == 100));
+        let surface = adaptive_threshold_surface(&image, AdaptiveMethod::Bernsen, 5, 15.0);
+        assert!(surface.iter().all(|&t| t == 128));
+        assert!(threshold_by_surface(&image, &surface)
+            .iter()
+            .all(|&p| p == 0));
+        let empty = GrayImage::new(0, 0);
+        for method in ADAPTIVE_METHODS {
+            assert_eq!(adaptive_threshold_surface(&empty, method, 15, 0.0).len(), 0);
+        }
+    }
+
+    #[test]
+    fn histogram_equalize_is_monotonic() {
+        // A skewed image: most pixels are dark.
+        let image = GrayImage::from_fn(64, 64, |x, y| {
+            let v = (x * y) as f32 / (63.0 * 63.0);
+            image::Luma([(v * v * 255.0) as u8])
+        });
+        let equalized = histogram_equalize(&image);
+        let mut pairs: Vec<(u8, u8)> = image
+            .iter()
+            .copied()
+            .zip(equalized.iter().copied())
+            .collect();
+        pairs.sort();
+        for w in pairs.windows(2) {
+            assert!(w[0].1 <= w[1].1, "{:?} is not monotonic", w);
+        }
+        assert_eq!(equalized.iter().max(), Some(&255));
+    }
+
+    #[test]
+    fn histogram_equalize_of_uniform_image() {
+        let image = GrayImage::from_pixel(4, 4, image::Luma([10]));
+        assert!(histogram_equalize(&image).iter().all(|&p| p == 255));
+    }
+
+    #[test]
+    fn histogram_equalize_of_empty_image() {
+        assert_eq!(histogram_equalize(&GrayImage::new(0, 0)).len(), 0);
+    }
+
+    #[test]
+    fn clahe_with_one_unclipped_tile_is_global_equalization() {
+        let (width, height) = (40, 25);
+        let original: Vec<f32> = (0..width * height)
+            .map(|i| ((i * 7919) % 1000) as f32 / 1000.0)
+            .collect();
+        let mut global = original.clone();
+        equalize_plane(&mut global, (0.0, 1.0));
+        let mut adaptive = original;
+        let params = ClaheParams {
+            tiles: (1, 1),
+            clip_limit: 256.0,
+        };
+        clahe_plane(&mut adaptive, width, height, (0.0, 1.0), params);
+        for (a, b) in global.iter().zip(&adaptive) {
+            assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn clahe_limits_noise_amplification_in_flat_regions() {
+        // A flat gray field with slight noise: global equalization stretches the noise over the
+        // whole range, clipping keeps it small.
+        let image = GrayImage::from_fn(64, 64, |x, y| {
+            image::Luma([(120 + (x * 7 + y * 13) % 5) as u8])
+        });
+        let spread = |image: &GrayImage| {
+            let max = *image.iter().max().unwrap() as i32;
+            let min = *image.iter().min().unwrap() as i32;
+            max - min
+        };
+        let limited = clahe(&image, ClaheParams::default());
+        assert!(spread(&limited) < 60, "spread {}", spread(&limited));
+        assert!(spread(&histogram_equalize(&image)) > 150);
+    }
+
+    #[test]
+    fn clahe_follows_local_contrast() {
+        // Left half dark, right half bright, each with a small gradient: every half is stretched
+        // on its own, so the gradients grow while their order is kept.
+        let image = GrayImage::from_fn(64, 32, |x, y| {
+            let base = if x < 32 { 20 } else { 200 };
+            image::Luma([(base + y / 2) as u8])
+        });
+        let params = ClaheParams {
+            tiles: (2, 1),
+            clip_limit: 4.0,
+        };
+        let out = clahe(&image, params);
+        for x in [0, 63] {
+            let column: Vec<u8> = (0..32).map(|y| out.get_pixel(x, y).0[0]).collect();
+            assert!(column.windows(2).all(|w| w[0] <= w[1]));
+            assert!(column[31] - column[0] > 15);
+        }
+        assert_eq!(clahe(&GrayImage::new(0, 0), params).len(), 0);
+    }
+
+    #[test]
+    fn match_histogram_to_own_histogram_is_identity() {
+        let image = GrayImage::from_fn(32, 32, |x, y| image::Luma([((x * y) % 200 + 20) as u8]));
+        let own = gray_histogram(&image).map(|n| n as f64);
+        assert_eq!(match_histogram(&image, &own), image);
+    }
+
+    #[test]
+    fn match_histogram_follows_target_curve() {
+        let image = gray_ramp(64, 64);
+        let matched = match_histogram(&image, &TargetCurve::Gaussian.histogram());
+        let values: Vec<f64> = matched.iter().map(|&p| p as f64).collect();
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let deviation =
+            (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64).sqrt();
+        assert!((mean - 127.5).abs() < 2.0, "mean {}", mean);
+        assert!((deviation - 42.7).abs() < 3.0, "deviation {}", deviation);
+
+        let matched = match_histogram(&image, &TargetCurve::Exponential.histogram());
+        let dark = matched.iter().filter(|&&p| p < 64).count();
+        assert!(dark > matched.len() * 6 / 10, "{} dark pixels", dark);
+    }
+
+    #[test]
+    fn match_plane_to_uniform_is_equalization() {
+        let original: Vec<f32> = (0..1000)
+            .map(|i| ((i * 7919) % 1000) as f32 / 3000.0)
+            .collect();
+        let mut equalized = original.clone();
+        equalize_plane(&mut equalized, (0.0, 1.0));
+        let mut matched = original;
+        match_plane(&mut matched, (0.0, 1.0), &TargetCurve::Uniform.histogram());
+        for (a, b) in equalized.iter().zip(&matched) {
+            assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn equalize_plane_is_monotonic_and_in_range() {
+        let original: Vec<f32> = (0..1000)
+            .map(|i| ((i * 7919) % 1000) as f32 / 2000.0)
+            .collect();
+        let mut plane = original.clone();
+        equalize_plane(&mut plane, (0.0, 1.0));
+        let mut pairs: Vec<(f32, f32)> = original.into_iter().zip(plane).collect();
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for w in pairs.windows(2) {
+            assert!(w[0].1 <= w[1].1);
+        }
+        assert!(pairs.iter().all(|p| (0.0..=1.0).contains(&p.1)));
+        assert!(pairs.last().unwrap().1 > 0.99);
+    }
+}
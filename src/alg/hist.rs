@@ -0,0 +1,458 @@
+use image::{DynamicImage, GenericImageView, GrayImage, Pixel};
+use std::fmt::{self, Write};
+
+use super::color::{ColorOptions, ColorSpace};
+use super::gray::{gray_histogram, plane_bin, plane_histogram};
+
+/// Percentiles reported by `Histogram::stats` besides the median.
+pub const PERCENTILES: [u8; 6] = [1, 5, 25, 75, 95, 99];
+
+/// Pixel counts of the 256 levels of one channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Histogram {
+    counts: [u64; 256],
+}
+
+/// Summary statistics of a non-empty histogram.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistogramStats {
+    pub count: u64,
+    pub min: u8,
+    pub max: u8,
+    pub mean: f64,
+    pub median: u8,
+    pub std_dev: f64,
+    /// Shannon entropy in bits.
+    pub entropy: f64,
+    /// Levels at `PERCENTILES`.
+    pub percentiles: [u8; 6],
+    /// Pixels at level 0.
+    pub clipped_low: u64,
+    /// Pixels at level 255.
+    pub clipped_high: u64,
+}
+
+impl Histogram {
+    pub fn from_counts(counts: [u64; 256]) -> Self {
+        Histogram { counts }
+    }
+
+    pub fn from_gray(image: &GrayImage) -> Self {
+        Histogram::from_counts(gray_histogram(image))
+    }
+
+    /// Histogram of a plane whose values lie in `range`, counted in 256 bins.
+    pub fn from_plane(plane: &[f32], range: (f32, f32)) -> Self {
+        Histogram::from_counts(plane_histogram(plane, range))
+    }
+
+    /// Histogram of a hue plane, leaving out the pixels whose saturation or chroma falls in the
+    /// lowest of 256 bins over `chroma_range`: the hue of a nearly gray pixel is meaningless and
+    /// would pile up at red.
+    pub fn from_hue(
+        hue: &[f32],
+        hue_range: (f32, f32),
+        chroma: &[f32],
+        chroma_range: (f32, f32),
+    ) -> Self {
+        let gray_limit = chroma_range.0 + (chroma_range.1 - chroma_range.0) / 256.0;
+        let chromatic: Vec<f32> = hue
+            .iter()
+            .zip(chroma)
+            .filter(|(_, &c)| c >= gray_limit)
+            .map(|(&h, _)| h)
+            .collect();
+        Histogram::from_plane(&chromatic, hue_range)
+    }
+
+    pub fn counts(&self) -> &[u64; 256] {
+        &self.counts
+    }
+
+    /// Number of pixels counted.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Largest count of a level.
+    pub fn max_count(&self) -> u64 {
+        self.counts.iter().copied().max().unwrap()
+    }
+
+    /// Lowest level with a cumulative count of at least `p` percent of the pixels (nearest rank),
+    /// or `None` for an empty histogram.
+    pub fn percentile(&self, p: f64) -> Option<u8> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        let rank = ((p / 100.0 * total as f64).ceil() as u64).clamp(1, total);
+        let mut sum = 0;
+        self.counts
+            .iter()
+            .position(|&n| {
+                sum += n;
+                sum >= rank
+            })
+            .map(|level| level as u8)
+    }
+
+    pub fn stats(&self) -> Option<HistogramStats> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        let n = total as f64;
+        let levels = self.counts.iter().enumerate().filter(|(_, &c)| c > 0);
+        let min = levels.clone().next().unwrap().0 as u8;
+        let max = levels.clone().next_back().unwrap().0 as u8;
+        let mean = levels
+            .clone()
+            .map(|(i, &c)| i as f64 * c as f64)
+            .sum::<f64>()
+            / n;
+        let variance = levels
+            .clone()
+            .map(|(i, &c)| (i as f64 - mean).powi(2) * c as f64)
+            .sum::<f64>()
+            / n;
+        let entropy = -levels
+            .map(|(_, &c)| {
+                let p = c as f64 / n;
+                p * p.log2()
+            })
+            .sum::<f64>();
+        Some(HistogramStats {
+            count: total,
+            min,
+            max,
+            mean,
+            median: self.percentile(50.0).unwrap(),
+            std_dev: variance.sqrt(),
+            entropy: entropy.max(0.0),
+            percentiles: PERCENTILES.map(|p| self.percentile(p as f64).unwrap()),
+            clipped_low: self.counts[0],
+            clipped_high: self.counts[255],
+        })
+    }
+}
+
+/// Histograms of the red, green and blue channels of an image and of its luma.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageHistogram {
+    pub red: Histogram,
+    pub green: Histogram,
+    pub blue: Histogram,
+    pub gray: Histogram,
+}
+
+impl ImageHistogram {
+    pub fn from_image(image: &DynamicImage) -> Self {
+        let mut counts = [[0u64; 256]; 4];
+        for (_, _, pixel) in image.pixels() {
+            let luma = pixel.to_luma();
+            counts[0][pixel.0[0] as usize] += 1;
+            counts[1][pixel.0[1] as usize] += 1;
+            counts[2][pixel.0[2] as usize] += 1;
+            counts[3][luma.0[0] as usize] += 1;
+        }
+        let [red, green, blue, gray] = counts.map(Histogram::from_counts);
+        ImageHistogram {
+            red,
+            green,
+            blue,
+            gray,
+        }
+    }
+
+    /// The channels with their names, in the order red, green, blue, gray.
+    pub fn channels(&self) -> [(&'static str, &Histogram); 4] {
+        [
+            ("red", &self.red),
+            ("green", &self.green),
+            ("blue", &self.blue),
+            ("gray", &self.gray),
+        ]
+    }
+
+    /// Largest count of a level in any channel.
+    pub fn max_count(&self) -> u64 {
+        self.channels()
+            .iter()
+            .map(|(_, h)| h.max_count())
+            .max()
+            .unwrap()
+    }
+
+    /// Table with one column per channel: the statistics, then the count of every level.
+    /// Statistics of empty channels are left blank.
+    pub fn to_csv(&self) -> String {
+        let channels = self.channels();
+        let stats = channels.map(|(_, h)| h.stats());
+        let mut out = String::from("key");
+        for (name, _) in channels {
+            write!(out, ",{}", name).unwrap();
+        }
+        out.push('\n');
+
+        let mut row = |key: &str, value: &dyn Fn(&HistogramStats) -> String| {
+            out.push_str(key);
+            for s in &stats {
+                out.push(',');
+                if let Some(s) = s {
+                    out.push_str(&value(s));
+                }
+            }
+            out.push('\n');
+        };
+        row("count", &|s| s.count.to_string());
+        row("min", &|s| s.min.to_string());
+        row("max", &|s| s.max.to_string());
+        row("mean", &|s| format!("{:.4}", s.mean));
+        row("median", &|s| s.median.to_string());
+        row("std_dev", &|s| format!("{:.4}", s.std_dev));
+        row("entropy", &|s| format!("{:.4}", s.entropy));
+        for (i, p) in PERCENTILES.iter().enumerate() {
+            row(&format!("p{}", p), &|s| s.percentiles[i].to_string());
+        }
+        row("clipped_low", &|s| s.clipped_low.to_string());
+        row("clipped_high", &|s| s.clipped_high.to_string());
+
+        for level in 0..256 {
+            write!(out, "bin_{}", level).unwrap();
+            for (_, h) in channels {
+                write!(out, ",{}", h.counts[level]).unwrap();
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Object with one member per channel holding its sample `count`, its `stats` (`null` for an
+    /// empty channel) and its `bins`.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\n");
+        for (c, (name, h)) in self.channels().iter().enumerate() {
+            writeln!(out, "  \"{}\": {{", name).unwrap();
+            writeln!(out, "    \"count\": {},", h.total()).unwrap();
+            match h.stats() {
+                Some(s) => {
+                    out.push_str("    \"stats\": {\n");
+                    writeln!(out, "      \"min\": {},", s.min).unwrap();
+                    writeln!(out, "      \"max\": {},", s.max).unwrap();
+                    writeln!(out, "      \"mean\": {:.4},", s.mean).unwrap();
+                    writeln!(out, "      \"median\": {},", s.median).unwrap();
+                    writeln!(out, "      \"std_dev\": {:.4},", s.std_dev).unwrap();
+                    writeln!(out, "      \"entropy\": {:.4},", s.entropy).unwrap();
+                    let percentiles: Vec<String> = PERCENTILES
+                        .iter()
+                        .zip(s.percentiles)
+                        .map(|(p, v)| format!("\"p{}\": {}", p, v))
+                        .collect();
+                    let percentiles = percentiles.join(", ");
+                    writeln!(out, "      \"percentiles\": {{{}}},", percentiles).unwrap();
+                    writeln!(out, "      \"clipped_low\": {},", s.clipped_low).unwrap();
+                    writeln!(out, "      \"clipped_high\": {}", s.clipped_high).unwrap();
+                    out.push_str("    },\n");
+                }
+                None => out.push_str("    \"stats\": null,\n"),
+            }
+            let bins: Vec<String> = h.counts.iter().map(|n| n.to_string()).collect();
+            writeln!(out, "    \"bins\": [{}]", bins.join(", ")).unwrap();
+            out.push_str(if c < 3 { "  },\n" } else { "  }\n" });
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Pairs of channels whose joint distribution `JointHistogram::from_image` counts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ChannelPair {
+    /// Red across, green up.
+    Rg,
+    /// Blue-difference chroma across, red-difference chroma up.
+    #[cfg_attr(feature = "cli", value(name = "cbcr"))]
+    CbCr,
+    /// CIELAB a* across, b* up.
+    Ab,
+    /// HSV hue across, saturation up, meant to be drawn around a wheel.
+    Hs,
+}
+
+impl ChannelPair {
+    pub const ALL: [ChannelPair; 4] = [
+        ChannelPair::Rg,
+        ChannelPair::CbCr,
+        ChannelPair::Ab,
+        ChannelPair::Hs,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChannelPair::Rg => "rg",
+            ChannelPair::CbCr => "cbcr",
+            ChannelPair::Ab => "ab",
+            ChannelPair::Hs => "hs",
+        }
+    }
+
+    /// The color space and the indices of its planes counted across and up.
+    fn planes(self) -> (ColorSpace, usize, usize) {
+        match self {
+            ChannelPair::Rg => (ColorSpace::Rgb, 0, 1),
+            ChannelPair::CbCr => (ColorSpace::YCbCr, 1, 2),
+            ChannelPair::Ab => (ColorSpace::Lab, 1, 2),
+            ChannelPair::Hs => (ColorSpace::Hsv, 0, 1),
+        }
+    }
+}
+
+impl fmt::Display for ChannelPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Pixel counts of the 256×256 combinations of the levels of two channels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JointHistogram {
+    /// Row-major counts, the row being the level of the second channel.
+    counts: Vec<u64>,
+}
+
+impl JointHistogram {
+    /// Counts the pairs of values of the planes `x` and `y`, which lie in `x_range` and `y_range`.
+    pub fn from_planes(x: &[f32], x_range: (f32, f32), y: &[f32], y_range: (f32, f32)) -> Self {
+        let mut counts = vec![0; 256 * 256];
+        for (&u, &v) in x.iter().zip(y) {
+            counts[plane_bin(v, y_range).0 * 256 + plane_bin(u, x_range).0] += 1;
+        }
+        JointHistogram { counts }
+    }
+
+    pub fn from_image(image: &DynamicImage, pair: ChannelPair, options: ColorOptions) -> Self {
+        let (space, x, y) = pair.planes();
+        match space.conversion_with(&options) {
+            Some(conversion) => {
+                let planes = conversion.rgb_to_model(image);
+                let ranges = conversion.ranges();
+                Self::from_planes(planes.plane(x), ranges[x], planes.plane(y), ranges[y])
+            }
+            None => {
+                let rgb = image.to_rgb8();
+                let plane = |c: usize| -> Vec<f32> { rgb.pixels().map(|p| p.0[c] as f32).collect() };
+                Self::from_planes(&plane(x), (0.0, 256.0), &plane(y), (0.0, 256.0))
+            }
+        }
+    }
+
+    /// Pixels whose first channel falls in bin `x` and second channel in bin `y`.
+    pub fn count(&self, x: u8, y: u8) -> u64 {
+        self.counts[y as usize * 256 + x as usize]
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    pub fn max_count(&self) -> u64 {
+        self.counts.iter().copied().max().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn histogram(levels: &[(usize, u64)]) -> Histogram {
+        let mut counts = [0u64; 256];
+        for &(level, n) in levels {
+            counts[level] = n;
+        }
+        Histogram::from_counts(counts)
+    }
+
+    #[test]
+    fn stats_of_two_levels() {
+        let stats = histogram(&[(0, 2), (100, 1), (255, 1)]).stats().unwrap();
+        assert_eq!(stats.count, 4);
+        assert_eq!((stats.min, stats.max), (0, 255));
+        assert!((stats.mean - 88.75).abs() < 1e-9);
+        assert_eq!(stats.median, 0);
+        assert!((stats.entropy - 1.5).abs() < 1e-9);
+        assert_eq!((stats.clipped_low, stats.clipped_high), (2, 1));
+        assert_eq!(stats.percentiles, [0, 0, 0, 100, 255, 255]);
+    }
+
+    #[test]
+    fn stats_of_uniform_histogram() {
+        let stats = Histogram::from_counts([10; 256]).stats().unwrap();
+        assert!((stats.mean - 127.5).abs() < 1e-9);
+        assert!((stats.entropy - 8.0).abs() < 1e-9);
+        assert!((stats.std_dev - (65535.0f64 / 12.0).sqrt()).abs() < 1e-9);
+        assert_eq!(stats.median, 127);
+        assert_eq!(histogram(&[(7, 3)]).stats().unwrap().std_dev, 0.0);
+    }
+
+    #[test]
+    fn empty_histogram_has_no_stats() {
+        let empty = Histogram::from_counts([0; 256]);
+        assert_eq!(empty.stats(), None);
+        assert_eq!(empty.percentile(50.0), None);
+        let image = ImageHistogram::from_image(&DynamicImage::new_rgb8(0, 0));
+        assert!(image.to_json().contains("\"stats\": null"));
+        assert!(image.to_csv().lines().nth(1).unwrap() == "count,,,,");
+    }
+
+    #[test]
+    fn hue_leaves_out_grays() {
+        let hue = [0.0, 0.0, 120.0, 359.9];
+        let saturation = [0.0, 0.001, 0.5, 1.0];
+        let hist = Histogram::from_hue(&hue, (0.0, 360.0), &saturation, (0.0, 1.0));
+        assert_eq!(hist.total(), 2);
+        assert_eq!((hist.counts()[85], hist.counts()[255]), (1, 1));
+        assert_eq!(Histogram::from_plane(&hue, (0.0, 360.0)).counts()[0], 2);
+    }
+
+    #[test]
+    fn joint_histogram_counts_pairs() {
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_fn(4, 1, |x, _| {
+            image::Rgb([x as u8 * 60, 200, 128])
+        }));
+        let options = ColorOptions::default();
+        let rg = JointHistogram::from_image(&image, ChannelPair::Rg, options);
+        assert_eq!(rg.total(), 4);
+        assert_eq!((rg.count(0, 200), rg.count(180, 200)), (1, 1));
+        assert_eq!(rg.max_count(), 1);
+
+        let gray = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(3, 3, image::Rgb([90; 3])));
+        for pair in [ChannelPair::CbCr, ChannelPair::Ab] {
+            let joint = JointHistogram::from_image(&gray, pair, options);
+            // Neutral colors sit at the middle of both chroma axes.
+            assert_eq!(joint.count(128, 128), 9, "{}", pair);
+        }
+        let hs = JointHistogram::from_image(&gray, ChannelPair::Hs, options);
+        assert_eq!(hs.count(0, 0), 9);
+    }
+
+    #[test]
+    fn exports_have_every_bin() {
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_fn(16, 16, |x, y| {
+            image::Rgb([x as u8 * 16, y as u8 * 16, 255])
+        }));
+        let hist = ImageHistogram::from_image(&image);
+        assert_eq!(hist.red.total(), 256);
+        assert_eq!(hist.blue.stats().unwrap().clipped_high, 256);
+
+        let csv = hist.to_csv();
+        assert_eq!(csv.lines().count(), 1 + 15 + 256);
+        assert!(csv.contains("\nbin_16,16,16,0,"));
+        let json = hist.to_json();
+        assert_eq!(json.matches("\"bins\": [").count(), 4);
+        assert_eq!(json.matches("\"stats\": {").count(), 4);
+        assert_eq!(json.matches("\"count\": 256,").count(), 4);
+        assert_eq!(json.matches('{').count(), json.matches('}').count());
+    }
+}
@@ -0,0 +1,102 @@
+use image::GrayImage;
+
+/// Summed-area table: the sum of any rectangle of an image in constant time.
+#[derive(Clone, Debug)]
+pub struct IntegralImage {
+    width: u32,
+    height: u32,
+    /// `(width + 1) * (height + 1)` sums, the first row and column being zero.
+    sums: Vec<f64>,
+}
+
+impl IntegralImage {
+    /// Builds the table of `values` given in row-major order.
+    pub fn new(width: u32, height: u32, values: impl IntoIterator<Item = f64>) -> Self {
+        let stride = width as usize + 1;
+        let mut sums = vec![0.0; stride * (height as usize + 1)];
+        let mut values = values.into_iter();
+        for y in 0..height as usize {
+            let mut row_sum = 0.0;
+            for x in 0..width as usize {
+                row_sum += values.next().expect("too few values for the image size");
+                sums[(y + 1) * stride + x + 1] = sums[y * stride + x + 1] + row_sum;
+            }
+        }
+        IntegralImage {
+            width,
+            height,
+            sums,
+        }
+    }
+
+    pub fn from_gray(image: &GrayImage) -> Self {
+        Self::new(
+            image.width(),
+            image.height(),
+            image.iter().map(|&p| p as f64),
+        )
+    }
+
+    /// Table of the squared samples, for local variances.
+    pub fn from_gray_squared(image: &GrayImage) -> Self {
+        Self::new(
+            image.width(),
+            image.height(),
+            image.iter().map(|&p| (p as f64) * (p as f64)),
+        )
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Sum of the samples in columns `x0..x1` and rows `y0..y1`.
+    pub fn sum(&self, x0: u32, y0: u32, x1: u32, y1: u32) -> f64 {
+        let stride = self.width as usize + 1;
+        let at = |x: u32, y: u32| self.sums[y as usize * stride + x as usize];
+        at(x1, y1) - at(x0, y1) - at(x1, y0) + at(x0, y0)
+    }
+
+    /// Mean of the samples within `radius` of `(x, y)`, the window being cut at the image border.
+    pub fn window_mean(&self, x: u32, y: u32, radius: u32) -> f64 {
+        let x0 = x.saturating_sub(radius);
+        let y0 = y.saturating_sub(radius);
+        let x1 = (x + radius + 1).min(self.width);
+        let y1 = (y + radius + 1).min(self.height);
+        let count = (x1 - x0) as f64 * (y1 - y0) as f64;
+        self.sum(x0, y0, x1, y1) / count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sums_match_brute_force() {
+        let image = GrayImage::from_fn(7, 5, |x, y| image::Luma([(x * 31 + y * 17) as u8]));
+        let integral = IntegralImage::from_gray(&image);
+        for (x0, y0, x1, y1) in [(0, 0, 7, 5), (2, 1, 5, 4), (6, 4, 7, 5), (3, 2, 3, 4)] {
+            let mut expected = 0.0;
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    expected += image.get_pixel(x, y).0[0] as f64;
+                }
+            }
+            assert_eq!(integral.sum(x0, y0, x1, y1), expected);
+        }
+    }
+
+    #[test]
+    fn window_mean_is_cut_at_border() {
+        let image = GrayImage::from_fn(3, 3, |x, _| image::Luma([x as u8 * 10]));
+        let integral = IntegralImage::from_gray(&image);
+        assert_eq!(integral.window_mean(1, 1, 1), 10.0);
+        assert_eq!(integral.window_mean(0, 0, 1), 5.0);
+        assert_eq!(integral.window_mean(2, 2, 5), 10.0);
+    }
+}
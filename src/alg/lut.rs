@@ -0,0 +1,264 @@
+use image::{DynamicImage, GrayImage, RgbaImage};
+
+/// Input and output ranges with a midtone gamma, as in the levels dialog of image editors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Levels {
+    /// Input black and white points: levels below and above are clipped.
+    pub input: (u8, u8),
+    /// Output levels the input black and white points are mapped to. A reversed range inverts.
+    pub output: (u8, u8),
+    /// Midtone gamma, above 1 to brighten.
+    pub gamma: f32,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Levels {
+            input: (0, 255),
+            output: (0, 255),
+            gamma: 1.0,
+        }
+    }
+}
+
+/// Lookup table mapping every level of the red, green and blue channels to a new level. Alpha is
+/// left untouched.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lut {
+    tables: [[u8; 256]; 3],
+}
+
+impl Lut {
+    pub fn identity() -> Self {
+        Lut::from_fn(|level| level)
+    }
+
+    /// The same table for all channels.
+    pub fn from_table(table: [u8; 256]) -> Self {
+        Lut {
+            tables: [table; 3],
+        }
+    }
+
+    pub fn per_channel(tables: [[u8; 256]; 3]) -> Self {
+        Lut { tables }
+    }
+
+    /// The same function of the level for all channels.
+    pub fn from_fn(f: impl Fn(u8) -> u8) -> Self {
+        Lut::from_table(std::array::from_fn(|level| f(level as u8)))
+    }
+
+    /// Same function of the level, taken from 0~1 to 0~1, for all channels.
+    fn from_unit_fn(f: impl Fn(f32) -> f32) -> Self {
+        Lut::from_fn(|level| {
+            let value = f(level as f32 / 255.0) * 255.0;
+            value.round().clamp(0.0, 255.0) as u8
+        })
+    }
+
+    pub fn invert() -> Self {
+        Lut::from_fn(|level| 255 - level)
+    }
+
+    /// Gamma correction: a level `x` in 0~1 becomes `x^(1/value)`, so that values above 1 brighten.
+    pub fn gamma(value: f32) -> Self {
+        Lut::from_unit_fn(|x| x.powf(1.0 / value))
+    }
+
+    pub fn levels(levels: &Levels) -> Self {
+        let (in_black, in_white) = (levels.input.0 as f32, levels.input.1 as f32);
+        let (out_black, out_white) = (levels.output.0 as f32, levels.output.1 as f32);
+        Lut::from_fn(|level| {
+            let t = ((level as f32 - in_black) / (in_white - in_black).max(1.0)).clamp(0.0, 1.0);
+            let t = t.powf(1.0 / levels.gamma);
+            (out_black + t * (out_white - out_black)).round() as u8
+        })
+    }
+
+    /// Mapping of every level through the normalized cumulative histogram `counts`, which
+    /// flattens the histogram. An empty histogram gives the identity.
+    pub fn equalization(counts: &[u64; 256]) -> Self {
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return Lut::identity();
+        }
+        let mut table = [0u8; 256];
+        let mut sum = 0u64;
+        for (entry, count) in table.iter_mut().zip(counts) {
+            sum += count;
+            *entry = ((sum * 255 + total / 2) / total) as u8;
+        }
+        Lut::from_table(table)
+    }
+
+    /// Monotone cubic spline (Fritsch–Carlson) through `points` of input and output levels, flat
+    /// outside of them. The spline does not overshoot: it only rises or falls where the points do.
+    ///
+    /// Panics unless there are at least two points with strictly increasing inputs.
+    pub fn curve(points: &[(u8, u8)]) -> Self {
+        assert!(points.len() >= 2, "a curve needs at least two points");
+        assert!(
+            points.windows(2).all(|w| w[0].0 < w[1].0),
+            "curve inputs must be strictly increasing"
+        );
+        let xs: Vec<f32> = points.iter().map(|p| p.0 as f32).collect();
+        let ys: Vec<f32> = points.iter().map(|p| p.1 as f32).collect();
+        let n = points.len();
+        let secants: Vec<f32> = (0..n - 1)
+            .map(|k| (ys[k + 1] - ys[k]) / (xs[k + 1] - xs[k]))
+            .collect();
+        let mut tangents = vec![0.0; n];
+        tangents[0] = secants[0];
+        tangents[n - 1] = secants[n - 2];
+        for k in 1..n - 1 {
+            if secants[k - 1] * secants[k] > 0.0 {
+                tangents[k] = (secants[k - 1] + secants[k]) / 2.0;
+            }
+        }
+        for k in 0..n - 1 {
+            if secants[k] == 0.0 {
+                tangents[k] = 0.0;
+                tangents[k + 1] = 0.0;
+                continue;
+            }
+            let a = tangents[k] / secants[k];
+            let b = tangents[k + 1] / secants[k];
+            let norm = a.hypot(b);
+            if norm > 3.0 {
+                tangents[k] = 3.0 / norm * a * secants[k];
+                tangents[k + 1] = 3.0 / norm * b * secants[k];
+            }
+        }
+
+        Lut::from_fn(|level| {
+            let x = level as f32;
+            if x <= xs[0] {
+                return points[0].1;
+            }
+            if x >= xs[n - 1] {
+                return points[n - 1].1;
+            }
+            let k = xs.iter().rposition(|&xk| xk <= x).unwrap();
+            let h = xs[k + 1] - xs[k];
+            let t = (x - xs[k]) / h;
+            let (t2, t3) = (t * t, t * t * t);
+            let y = (2.0 * t3 - 3.0 * t2 + 1.0) * ys[k]
+                + (t3 - 2.0 * t2 + t) * h * tangents[k]
+                + (-2.0 * t3 + 3.0 * t2) * ys[k + 1]
+                + (t3 - t2) * h * tangents[k + 1];
+            y.round().clamp(0.0, 255.0) as u8
+        })
+    }
+
+    /// Table of `channel` (0 for red, 1 for green, 2 for blue).
+    pub fn table(&self, channel: usize) -> &[u8; 256] {
+        &self.tables[channel]
+    }
+
+    /// Whether all channels share one table.
+    pub fn is_uniform(&self) -> bool {
+        self.tables[0] == self.tables[1] && self.tables[1] == self.tables[2]
+    }
+
+    /// Composition applying `self` first, then `next`.
+    pub fn then(&self, next: &Lut) -> Lut {
+        Lut::per_channel(std::array::from_fn(|c| {
+            self.tables[c].map(|level| next.tables[c][level as usize])
+        }))
+    }
+
+    pub fn apply(&self, image: &DynamicImage) -> DynamicImage {
+        let mut out: RgbaImage = image.to_rgba8();
+        for pixel in out.pixels_mut() {
+            for c in 0..3 {
+                pixel.0[c] = self.tables[c][pixel.0[c] as usize];
+            }
+        }
+        DynamicImage::from(out)
+    }
+
+    /// Maps a single plane through the table of `channel`.
+    pub fn apply_plane(&self, channel: usize, plane: &GrayImage) -> GrayImage {
+        let mut out = plane.clone();
+        for level in out.iter_mut() {
+            *level = self.tables[channel][*level as usize];
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gamma_and_levels() {
+        let gamma = Lut::gamma(2.2);
+        assert_eq!((gamma.table(0)[0], gamma.table(0)[255]), (0, 255));
+        assert_eq!(gamma.table(1)[128], 186);
+        assert_eq!(Lut::gamma(1.0), Lut::identity());
+
+        let levels = Lut::levels(&Levels {
+            input: (10, 240),
+            ..Levels::default()
+        });
+        assert_eq!(levels.table(0)[5], 0);
+        assert_eq!(levels.table(0)[125], 128);
+        assert_eq!(levels.table(0)[250], 255);
+        let reversed = Lut::levels(&Levels {
+            output: (255, 0),
+            ..Levels::default()
+        });
+        assert_eq!(reversed, Lut::invert());
+        assert_eq!(Lut::levels(&Levels::default()), Lut::identity());
+    }
+
+    #[test]
+    fn composition_applies_in_order() {
+        let brighten = Lut::from_fn(|l| l.saturating_add(100));
+        let inverted_then_brightened = Lut::invert().then(&brighten);
+        assert_eq!(inverted_then_brightened.table(2)[200], 155);
+        assert_eq!(brighten.then(&Lut::invert()).table(2)[200], 0);
+        assert_eq!(Lut::invert().then(&Lut::invert()), Lut::identity());
+
+        let mut tables = [*Lut::identity().table(0); 3];
+        tables[1] = *Lut::invert().table(0);
+        let lut = Lut::per_channel(tables);
+        assert!(!lut.is_uniform());
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, image::Rgba([10; 4])));
+        assert_eq!(lut.apply(&image).to_rgba8().get_pixel(0, 0).0, [10, 245, 10, 10]);
+    }
+
+    #[test]
+    fn curve_passes_through_points_without_overshoot() {
+        let points = [(0, 0), (64, 40), (192, 220), (255, 255)];
+        let curve = Lut::curve(&points);
+        for (x, y) in points {
+            assert_eq!(curve.table(0)[x as usize], y);
+        }
+        assert!(curve.table(0).windows(2).all(|w| w[0] <= w[1]));
+
+        // A flat stretch stays flat and the ends are held outside of the points.
+        let plateau = Lut::curve(&[(20, 10), (100, 200), (150, 200), (230, 250)]);
+        assert!(plateau.table(0)[100..=150].iter().all(|&y| y == 200));
+        assert_eq!((plateau.table(0)[0], plateau.table(0)[255]), (10, 250));
+        assert_eq!(Lut::curve(&[(0, 0), (255, 255)]), Lut::identity());
+    }
+
+    #[test]
+    #[should_panic(expected = "strictly increasing")]
+    fn curve_rejects_unordered_points() {
+        Lut::curve(&[(10, 0), (10, 255)]);
+    }
+
+    #[test]
+    fn equalization_of_two_levels() {
+        let mut counts = [0; 256];
+        counts[10] = 1;
+        counts[20] = 3;
+        let lut = Lut::equalization(&counts);
+        assert_eq!((lut.table(0)[10], lut.table(0)[20]), (64, 255));
+        assert_eq!(Lut::equalization(&[0; 256]), Lut::identity());
+    }
+}
@@ -0,0 +1,647 @@
+use image::{DynamicImage, GenericImageView, RgbImage};
+use std::fmt::{self, Write};
+
+use super::lut::Lut;
+use crate::{Error, Result};
+
+/// Largest lattice size accepted when parsing.
+pub const MAX_SIZE: usize = 256;
+
+/// Most conjugate gradient iterations of `Lut3d::fit`.
+const FIT_ITERATIONS: usize = 200;
+
+/// How `Lut3d::sample` interpolates between the lattice nodes around a color.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Interpolation {
+    /// Weighted mean of the 8 nodes of the enclosing cell.
+    Trilinear,
+    /// Weighted mean of the 4 nodes of the tetrahedron of the cell holding the color, which keeps
+    /// the gray axis exact.
+    #[default]
+    Tetrahedral,
+}
+
+impl Interpolation {
+    pub fn name(self) -> &'static str {
+        match self {
+            Interpolation::Trilinear => "trilinear",
+            Interpolation::Tetrahedral => "tetrahedral",
+        }
+    }
+}
+
+impl fmt::Display for Interpolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Color lookup table: output RGB colors, in 0~1, on a `size`×`size`×`size` lattice over the RGB
+/// cube, red varying fastest as in `.cube` files and Hald images.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut3d {
+    size: usize,
+    /// Input values mapped to the first and last nodes of each channel.
+    domain: ([f32; 3], [f32; 3]),
+    nodes: Vec<[f32; 3]>,
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::InvalidData(message.into())
+}
+
+impl Lut3d {
+    /// Table of `size` nodes per channel holding `f` of the color at each node.
+    pub fn from_fn(size: usize, f: impl Fn([f32; 3]) -> [f32; 3]) -> Self {
+        assert!(size >= 2, "a 3D LUT needs at least 2 nodes per channel");
+        let step = 1.0 / (size - 1) as f32;
+        let mut nodes = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    nodes.push(f([r as f32 * step, g as f32 * step, b as f32 * step]));
+                }
+            }
+        }
+        Lut3d {
+            size,
+            domain: ([0.0; 3], [1.0; 3]),
+            nodes,
+        }
+    }
+
+    pub fn identity(size: usize) -> Self {
+        Lut3d::from_fn(size, |rgb| rgb)
+    }
+
+    /// Table of `size` nodes per channel applying the 1D `lut`, interpolated between its levels.
+    pub fn from_lut(lut: &Lut, size: usize) -> Self {
+        Lut3d::from_fn(size, |rgb| {
+            std::array::from_fn(|c| {
+                let table = lut.table(c);
+                let pos = rgb[c] * 255.0;
+                let i = (pos as usize).min(254);
+                let t = pos - i as f32;
+                (table[i] as f32 * (1.0 - t) + table[i + 1] as f32 * t) / 255.0
+            })
+        })
+    }
+
+    /// Parses the text of an Adobe / Resolve `.cube` file holding a 3D table.
+    pub fn parse_cube(text: &str) -> Result<Self> {
+        let mut size = None;
+        let mut domain = ([0.0; 3], [1.0; 3]);
+        let mut nodes = Vec::new();
+        let triple = |fields: &[&str], line: usize| -> Result<[f32; 3]> {
+            match fields {
+                [a, b, c] => {
+                    let parse = |s: &str| {
+                        s.parse::<f32>()
+                            .ok()
+                            .filter(|v| v.is_finite())
+                            .ok_or_else(|| {
+                                invalid(format!("line {}: invalid number '{}'", line, s))
+                            })
+                    };
+                    Ok([parse(a)?, parse(b)?, parse(c)?])
+                }
+                _ => Err(invalid(format!("line {}: expected three numbers", line))),
+            }
+        };
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = line.split('#').next().unwrap().trim();
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.first() {
+                None => {}
+                Some(&"TITLE") => {}
+                Some(&"LUT_3D_SIZE") => {
+                    let n = fields
+                        .get(1)
+                        .and_then(|s| s.parse::<usize>().ok())
+                        .filter(|n| (2..=MAX_SIZE).contains(n))
+                        .ok_or_else(|| {
+                            invalid(format!(
+                                "line {}: LUT_3D_SIZE must be in 2~{}",
+                                number, MAX_SIZE
+                            ))
+                        })?;
+                    size = Some(n);
+                }
+                Some(&"LUT_1D_SIZE") => {
+                    return Err(invalid("1D tables in .cube files are not supported"));
+                }
+                Some(&"DOMAIN_MIN") => domain.0 = triple(&fields[1..], number)?,
+                Some(&"DOMAIN_MAX") => domain.1 = triple(&fields[1..], number)?,
+                Some(keyword) if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    // Other keywords, such as LUT_3D_INPUT_RANGE of some tools, do not change the
+                    // table.
+                }
+                Some(_) => nodes.push(triple(&fields, number)?),
+            }
+        }
+        let size = size.ok_or_else(|| invalid("missing LUT_3D_SIZE"))?;
+        if nodes.len() != size * size * size {
+            return Err(invalid(format!(
+                "expected {} table entries for LUT_3D_SIZE {}, found {}",
+                size * size * size,
+                size,
+                nodes.len()
+            )));
+        }
+        if (0..3).any(|c| domain.1[c] <= domain.0[c]) {
+            return Err(invalid("DOMAIN_MAX must be above DOMAIN_MIN"));
+        }
+        Ok(Lut3d {
+            size,
+            domain,
+            nodes,
+        })
+    }
+
+    /// Reads a Hald CLUT: a square image of `level³` pixels per side holding the `level²` nodes
+    /// per channel in row-major order.
+    pub fn from_hald(image: &RgbImage) -> Result<Self> {
+        let (width, height) = image.dimensions();
+        let level = (1..=16).find(|l| l * l * l == width);
+        let level = match level {
+            Some(level) if width == height && level >= 2 => level,
+            _ => {
+                return Err(invalid(format!(
+                    "a Hald image must be square with a side of level³ pixels for a level in 2~16, got {}x{}",
+                    width, height
+                )))
+            }
+        };
+        let nodes = image
+            .pixels()
+            .map(|p| p.0.map(|v| v as f32 / 255.0))
+            .collect();
+        Ok(Lut3d {
+            size: (level * level) as usize,
+            domain: ([0.0; 3], [1.0; 3]),
+            nodes,
+        })
+    }
+
+    /// Estimates the table mapping every pixel of `input` to the pixel of `output` at the same
+    /// place, as after any chain of operations on `input`. The color changes at the nodes are
+    /// fitted by least squares to the changes of the pixels, with a smoothness term through which
+    /// the nodes that no pixel comes close to take the changes of their neighbors.
+    pub fn fit(input: &DynamicImage, output: &DynamicImage, size: usize) -> Self {
+        assert_eq!(
+            input.dimensions(),
+            output.dimensions(),
+            "input and output must have the same size"
+        );
+        let input = input.to_rgb8();
+        let output = output.to_rgb8();
+        let n = size;
+        let count = n * n * n;
+        // Normal equations of the data term: `coupling` holds the products of the weights of every
+        // node and its 27 neighbors, `targets` the weighted changes of the pixels.
+        let mut coupling = vec![[0.0f64; 27]; count];
+        let mut targets = vec![[0.0f64; 3]; count];
+        let mut weights = vec![0.0f64; count];
+        for (a, b) in input.pixels().zip(output.pixels()) {
+            let rgb = a.0.map(|v| v as f32 / 255.0);
+            let change: [f64; 3] = std::array::from_fn(|c| (b.0[c] as f64 - a.0[c] as f64) / 255.0);
+            let (base, frac) = cell(rgb, n, ([0.0; 3], [1.0; 3]));
+            let corners: [(usize, [usize; 3], f64); 8] = std::array::from_fn(|corner| {
+                let step = [corner & 1, corner >> 1 & 1, corner >> 2 & 1];
+                let weight = (0..3)
+                    .map(|c| if step[c] == 1 { frac[c] } else { 1.0 - frac[c] } as f64)
+                    .product();
+                let index = (0..3).map(|c| (base[c] + step[c]) * n.pow(c as u32)).sum();
+                (index, step, weight)
+            });
+            for &(index, step, weight) in &corners {
+                weights[index] += weight;
+                for (target, change) in targets[index].iter_mut().zip(change) {
+                    *target += weight * change;
+                }
+                for &(_, other, other_weight) in &corners {
+                    let offset: usize = (0..3)
+                        .map(|c| (1 + other[c] - step[c]) * 3usize.pow(c as u32))
+                        .sum();
+                    coupling[index][offset] += weight * other_weight;
+                }
+            }
+        }
+        let total: f64 = weights.iter().sum();
+        if total == 0.0 {
+            return Lut3d::identity(size);
+        }
+
+        // Start from the mean change around every node, spread outwards to the empty ones.
+        let mut changes: Vec<Option<[f64; 3]>> = targets
+            .iter()
+            .zip(&weights)
+            .map(|(target, &w)| (w > 1e-6).then(|| target.map(|t| t / w)))
+            .collect();
+        while changes.iter().any(Option::is_none) {
+            let previous = changes.clone();
+            for (index, change) in changes.iter_mut().enumerate() {
+                if change.is_some() {
+                    continue;
+                }
+                let known: Vec<[f64; 3]> =
+                    neighbors(index, n).filter_map(|i| previous[i]).collect();
+                if !known.is_empty() {
+                    *change = Some(std::array::from_fn(|c| {
+                        known.iter().map(|k| k[c]).sum::<f64>() / known.len() as f64
+                    }));
+                }
+            }
+        }
+        let changes: Vec<[f64; 3]> = changes.into_iter().map(Option::unwrap).collect();
+
+        // Conjugate gradients on the data term plus `smoothness` times the squared differences of
+        // neighboring nodes, for each channel.
+        let smoothness = 1e-3 * total / count as f64;
+        let rows: Vec<Vec<(usize, f64)>> = (0..count)
+            .map(|index| {
+                let position = [index % n, index / n % n, index / (n * n)];
+                let mut row = Vec::with_capacity(33);
+                for (offset, &value) in coupling[index].iter().enumerate() {
+                    if value != 0.0 {
+                        let step = [offset % 3, offset / 3 % 3, offset / 9];
+                        let other = (0..3)
+                            .map(|c| (position[c] + step[c] - 1) * n.pow(c as u32))
+                            .sum();
+                        row.push((other, value));
+                    }
+                }
+                for other in neighbors(index, n) {
+                    row.push((index, smoothness));
+                    row.push((other, -smoothness));
+                }
+                row
+            })
+            .collect();
+        let multiply = |x: &[f64]| -> Vec<f64> {
+            rows.iter()
+                .map(|row| row.iter().map(|&(other, value)| value * x[other]).sum())
+                .collect()
+        };
+        let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f64>();
+        let mut lut = Lut3d::identity(size);
+        for c in 0..3 {
+            let mut x: Vec<f64> = changes.iter().map(|change| change[c]).collect();
+            let product = multiply(&x);
+            let mut residual: Vec<f64> = (0..count).map(|i| targets[i][c] - product[i]).collect();
+            let mut direction = residual.clone();
+            let mut norm = dot(&residual, &residual);
+            let tolerance = 1e-12 * targets.iter().map(|t| t[c] * t[c]).sum::<f64>();
+            for _ in 0..FIT_ITERATIONS {
+                if norm <= tolerance {
+                    break;
+                }
+                let product = multiply(&direction);
+                let step = norm / dot(&direction, &product);
+                for i in 0..count {
+                    x[i] += step * direction[i];
+                    residual[i] -= step * product[i];
+                }
+                let next = dot(&residual, &residual);
+                for i in 0..count {
+                    direction[i] = residual[i] + next / norm * direction[i];
+                }
+                norm = next;
+            }
+            for (node, change) in lut.nodes.iter_mut().zip(x) {
+                node[c] = (node[c] + change as f32).clamp(0.0, 1.0);
+            }
+        }
+        lut
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    fn node(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.nodes[r + self.size * (g + self.size * b)]
+    }
+
+    /// Output color of the color `rgb`, both in 0~1.
+    pub fn sample(&self, rgb: [f32; 3], interpolation: Interpolation) -> [f32; 3] {
+        let ([r, g, b], [fr, fg, fb]) = cell(rgb, self.size, self.domain);
+        let c = |dr: usize, dg: usize, db: usize| self.node(r + dr, g + dg, b + db);
+        let mix = |weights: [(f32, [f32; 3]); 4]| -> [f32; 3] {
+            std::array::from_fn(|k| weights.iter().map(|(w, node)| w * node[k]).sum())
+        };
+        match interpolation {
+            Interpolation::Trilinear => {
+                let mut out = [0.0; 3];
+                for corner in 0..8usize {
+                    let (dr, dg, db) = (corner & 1, corner >> 1 & 1, corner >> 2 & 1);
+                    let w = [fr, fg, fb]
+                        .iter()
+                        .zip([dr, dg, db])
+                        .map(|(f, d)| if d == 1 { *f } else { 1.0 - f })
+                        .product::<f32>();
+                    let node = c(dr, dg, db);
+                    for k in 0..3 {
+                        out[k] += w * node[k];
+                    }
+                }
+                out
+            }
+            // Each tetrahedron runs from the node at the cell origin to the opposite node through
+            // the nodes stepping the largest fraction first.
+            Interpolation::Tetrahedral => {
+                let (c000, c111) = (c(0, 0, 0), c(1, 1, 1));
+                if fr > fg {
+                    if fg > fb {
+                        mix([
+                            (1.0 - fr, c000),
+                            (fr - fg, c(1, 0, 0)),
+                            (fg - fb, c(1, 1, 0)),
+                            (fb, c111),
+                        ])
+                    } else if fr > fb {
+                        mix([
+                            (1.0 - fr, c000),
+                            (fr - fb, c(1, 0, 0)),
+                            (fb - fg, c(1, 0, 1)),
+                            (fg, c111),
+                        ])
+                    } else {
+                        mix([
+                            (1.0 - fb, c000),
+                            (fb - fr, c(0, 0, 1)),
+                            (fr - fg, c(1, 0, 1)),
+                            (fg, c111),
+                        ])
+                    }
+                } else if fb > fg {
+                    mix([
+                        (1.0 - fb, c000),
+                        (fb - fg, c(0, 0, 1)),
+                        (fg - fr, c(0, 1, 1)),
+                        (fr, c111),
+                    ])
+                } else if fb > fr {
+                    mix([
+                        (1.0 - fg, c000),
+                        (fg - fb, c(0, 1, 0)),
+                        (fb - fr, c(0, 1, 1)),
+                        (fr, c111),
+                    ])
+                } else {
+                    mix([
+                        (1.0 - fg, c000),
+                        (fg - fr, c(0, 1, 0)),
+                        (fr - fb, c(1, 1, 0)),
+                        (fb, c111),
+                    ])
+                }
+            }
+        }
+    }
+
+    /// Maps every pixel of `image` through the table. Alpha is left untouched.
+    pub fn apply(&self, image: &DynamicImage, interpolation: Interpolation) -> DynamicImage {
+        let mut out = image.to_rgba8();
+        for pixel in out.pixels_mut() {
+            let rgb = [0, 1, 2].map(|c| pixel.0[c] as f32 / 255.0);
+            let mapped = self.sample(rgb, interpolation);
+            for (level, value) in pixel.0.iter_mut().zip(mapped) {
+                *level = (value * 255.0).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+        DynamicImage::from(out)
+    }
+
+    /// Text of a `.cube` file holding the table.
+    pub fn to_cube(&self, title: &str) -> String {
+        let mut out = String::new();
+        writeln!(out, "TITLE \"{}\"", title.replace('"', "'")).unwrap();
+        writeln!(out, "LUT_3D_SIZE {}", self.size).unwrap();
+        if self.domain != ([0.0; 3], [1.0; 3]) {
+            let [r, g, b] = self.domain.0;
+            writeln!(out, "DOMAIN_MIN {} {} {}", r, g, b).unwrap();
+            let [r, g, b] = self.domain.1;
+            writeln!(out, "DOMAIN_MAX {} {} {}", r, g, b).unwrap();
+        }
+        for [r, g, b] in &self.nodes {
+            writeln!(out, "{:.6} {:.6} {:.6}", r, g, b).unwrap();
+        }
+        out
+    }
+}
+
+/// Indices of the nodes next to node `index` along each channel in a table of `size` nodes per
+/// channel.
+fn neighbors(index: usize, size: usize) -> impl Iterator<Item = usize> {
+    let position = [index % size, index / size % size, index / (size * size)];
+    (0..3).flat_map(move |c| {
+        let stride = size.pow(c as u32);
+        [
+            (position[c] > 0).then(|| index - stride),
+            (position[c] + 1 < size).then(|| index + stride),
+        ]
+        .into_iter()
+        .flatten()
+    })
+}
+
+/// Lattice cell holding `rgb` in a table of `size` nodes per channel over `domain`: the node at
+/// its origin and the position of the color inside it, each in 0~1.
+fn cell(rgb: [f32; 3], size: usize, domain: ([f32; 3], [f32; 3])) -> ([usize; 3], [f32; 3]) {
+    let mut base = [0; 3];
+    let mut frac = [0.0; 3];
+    for c in 0..3 {
+        let t = (rgb[c] - domain.0[c]) / (domain.1[c] - domain.0[c]);
+        let pos = t.clamp(0.0, 1.0) * (size - 1) as f32;
+        base[c] = (pos as usize).min(size - 2);
+        frac[c] = pos - base[c] as f32;
+    }
+    (base, frac)
+}
+
+/// Reads a Photoshop `.acv` curves file: the curve of every channel followed by the composite
+/// curve, each a monotone spline through its points.
+pub fn parse_acv(bytes: &[u8]) -> Result<Lut> {
+    let mut words = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]));
+    let mut next = || words.next().ok_or_else(|| invalid("truncated curves file"));
+    let version = next()?;
+    if version != 1 && version != 4 {
+        return Err(invalid(format!("unknown curves file version {}", version)));
+    }
+    let count = next()?;
+    let mut curves = Vec::new();
+    for _ in 0..count.min(4) {
+        let points = next()?;
+        let mut curve = Vec::new();
+        for _ in 0..points {
+            let output = next()?;
+            let input = next()?;
+            if input > 255 || output > 255 {
+                return Err(invalid("curve points must be levels in 0~255"));
+            }
+            curve.push((input as u8, output as u8));
+        }
+        if curve.len() < 2 || curve.windows(2).any(|w| w[0].0 >= w[1].0) {
+            return Err(invalid(
+                "a curve needs at least two points with increasing inputs",
+            ));
+        }
+        curves.push(Lut::curve(&curve));
+    }
+    let identity = Lut::identity();
+    let curve = |index: usize| curves.get(index).unwrap_or(&identity);
+    let channels = Lut::per_channel([0, 1, 2].map(|c| *curve(c + 1).table(c)));
+    Ok(channels.then(curve(0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    fn hald_identity(level: u32) -> RgbImage {
+        let size = level * level;
+        let side = level * level * level;
+        RgbImage::from_fn(side, side, |x, y| {
+            let i = y * side + x;
+            let value = |n: u32| (n as f32 / (size - 1) as f32 * 255.0).round() as u8;
+            Rgb([
+                value(i % size),
+                value(i / size % size),
+                value(i / (size * size)),
+            ])
+        })
+    }
+
+    fn close(a: [f32; 3], b: [f32; 3], tolerance: f32) -> bool {
+        (0..3).all(|c| (a[c] - b[c]).abs() <= tolerance)
+    }
+
+    #[test]
+    fn identity_maps_colors_to_themselves() {
+        let lut = Lut3d::identity(5);
+        for rgb in [
+            [0.0, 0.0, 0.0],
+            [0.3, 0.7, 0.1],
+            [1.0, 0.5, 0.25],
+            [0.9, 0.9, 0.9],
+        ] {
+            for interpolation in [Interpolation::Trilinear, Interpolation::Tetrahedral] {
+                assert!(close(lut.sample(rgb, interpolation), rgb, 1e-5));
+            }
+        }
+    }
+
+    #[test]
+    fn interpolations_agree_on_linear_tables() {
+        let matrix = |[r, g, b]: [f32; 3]| [0.5 * r + 0.25 * g, g, 0.1 + 0.8 * b];
+        let lut = Lut3d::from_fn(3, matrix);
+        let rgb = [0.8, 0.35, 0.6];
+        let expected = matrix(rgb);
+        assert!(close(
+            lut.sample(rgb, Interpolation::Trilinear),
+            expected,
+            1e-5
+        ));
+        assert!(close(
+            lut.sample(rgb, Interpolation::Tetrahedral),
+            expected,
+            1e-5
+        ));
+    }
+
+    #[test]
+    fn cube_round_trip() {
+        let lut = Lut3d::from_fn(4, |[r, g, b]| [1.0 - r, g * g, b]);
+        let text = lut.to_cube("inverse \"red\"");
+        assert!(text.starts_with("TITLE \"inverse 'red'\"\nLUT_3D_SIZE 4\n"));
+        let parsed = Lut3d::parse_cube(&format!("# comment\n{}", text)).unwrap();
+        assert_eq!(parsed.size(), 4);
+        for (a, b) in parsed.nodes.iter().zip(&lut.nodes) {
+            assert!(close(*a, *b, 1e-6));
+        }
+    }
+
+    #[test]
+    fn cube_domain_and_errors() {
+        let text = "LUT_3D_SIZE 2\nDOMAIN_MIN 0 0 0\nDOMAIN_MAX 2 2 2\n\
+                    0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+        let lut = Lut3d::parse_cube(text).unwrap();
+        let half = lut.sample([1.0, 1.0, 1.0], Interpolation::Tetrahedral);
+        assert!(close(half, [0.5; 3], 1e-6));
+
+        let missing = "LUT_3D_SIZE 2\n0 0 0\n";
+        assert!(matches!(
+            Lut3d::parse_cube(missing),
+            Err(Error::InvalidData(_))
+        ));
+        assert!(Lut3d::parse_cube("0 0 0\n").is_err());
+        assert!(Lut3d::parse_cube("LUT_3D_SIZE 2\n0 0 x\n").is_err());
+        assert!(Lut3d::parse_cube("LUT_1D_SIZE 2\n0 0 0\n1 1 1\n").is_err());
+    }
+
+    #[test]
+    fn hald_identity_is_identity() {
+        let lut = Lut3d::from_hald(&hald_identity(3)).unwrap();
+        assert_eq!(lut.size(), 9);
+        assert!(close(
+            lut.sample([0.2, 0.5, 0.8], Interpolation::Tetrahedral),
+            [0.2, 0.5, 0.8],
+            1.0 / 255.0
+        ));
+        assert!(Lut3d::from_hald(&RgbImage::new(10, 10)).is_err());
+    }
+
+    #[test]
+    fn fit_recovers_pixelwise_operation() {
+        let input = DynamicImage::ImageRgb8(hald_identity(4));
+        let output = Lut::invert().apply(&input);
+        let lut = Lut3d::fit(&input, &output, 9);
+        for rgb in [[0.0, 0.0, 0.0], [0.25, 0.5, 0.75], [1.0, 0.125, 0.5]] {
+            let expected = rgb.map(|v| 1.0 - v);
+            assert!(close(
+                lut.sample(rgb, Interpolation::Trilinear),
+                expected,
+                0.01
+            ));
+        }
+    }
+
+    #[test]
+    fn fit_extrapolates_missing_colors() {
+        // Only dark pixels, all brightened by 0.2.
+        let input = DynamicImage::ImageRgb8(RgbImage::from_fn(8, 8, |x, y| {
+            Rgb([x as u8 * 8, y as u8 * 8, 20])
+        }));
+        let output = Lut::from_fn(|l| l.saturating_add(51)).apply(&input);
+        let lut = Lut3d::fit(&input, &output, 5);
+        let far = lut.sample([0.5, 0.5, 0.5], Interpolation::Tetrahedral);
+        assert!(close(far, [0.7; 3], 0.01));
+        let empty = DynamicImage::new_rgb8(0, 0);
+        assert_eq!(Lut3d::fit(&empty, &empty, 3), Lut3d::identity(3));
+    }
+
+    #[test]
+    fn acv_applies_channel_then_composite() {
+        let mut bytes = Vec::new();
+        let mut push = |words: &[u16]| {
+            for w in words {
+                bytes.extend(w.to_be_bytes());
+            }
+        };
+        // Composite inverts, red doubles up to 128.
+        push(&[4, 2]);
+        push(&[2, 255, 0, 0, 255]);
+        push(&[2, 0, 0, 255, 128]);
+        let lut = parse_acv(&bytes).unwrap();
+        assert_eq!(lut.table(0)[64], 127);
+        assert_eq!(lut.table(1)[64], 191);
+        assert!(parse_acv(&bytes[..7]).is_err());
+        assert!(parse_acv(&[0, 9, 0, 0]).is_err());
+    }
+}
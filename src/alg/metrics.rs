@@ -0,0 +1,172 @@
+use image::{DynamicImage, Rgb, RgbImage};
+
+use super::color::unit_to_u8;
+use super::filter::{gaussian_blur, Border, BorderMode};
+use super::planes::Planes;
+
+/// Absolute error between two RGB images, per channel.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ChannelError {
+    /// Largest absolute difference of the R, G and B samples.
+    pub max: [u8; 3],
+    /// Mean absolute difference of the R, G and B samples.
+    pub mean: [f64; 3],
+}
+
+/// Compares `image` against `reference` channel by channel. Both images must have the same size.
+pub fn channel_error(reference: &DynamicImage, image: &DynamicImage) -> ChannelError {
+    assert_eq!(
+        (reference.width(), reference.height()),
+        (image.width(), image.height()),
+        "images must have the same size"
+    );
+    let reference = reference.to_rgb8();
+    let image = image.to_rgb8();
+
+    let mut max = [0u8; 3];
+    let mut sum = [0u64; 3];
+    for (a, b) in reference.pixels().zip(image.pixels()) {
+        for c in 0..3 {
+            let diff = a.0[c].abs_diff(b.0[c]);
+            max[c] = max[c].max(diff);
+            sum[c] += diff as u64;
+        }
+    }
+
+    let len = reference.width() as u64 * reference.height() as u64;
+    let mean = sum.map(|s| if len == 0 { 0.0 } else { s as f64 / len as f64 });
+    ChannelError { max, mean }
+}
+
+/// Maps `t` in 0~1 to a black-red-yellow-white heat color.
+pub fn heat_color(t: f32) -> Rgb<u8> {
+    let t = t * 3.0;
+    Rgb([unit_to_u8(t), unit_to_u8(t - 1.0), unit_to_u8(t - 2.0)])
+}
+
+/// Maps `t` in -1~1 to a blue-white-red diverging color, white at 0.
+pub fn diverging_color(t: f32) -> Rgb<u8> {
+    let t = t.clamp(-1.0, 1.0);
+    let end: [f32; 3] = if t < 0.0 {
+        [59.0, 76.0, 192.0]
+    } else {
+        [180.0, 4.0, 38.0]
+    };
+    let a = t.abs();
+    Rgb(end.map(|e| (255.0 + (e - 255.0) * a).round() as u8))
+}
+
+/// Renders the largest channel error of every pixel as a heat map, with an error of `full_scale`
+/// (at least 1) or more shown as white. Both images must have the same size.
+pub fn error_heat_map(reference: &DynamicImage, image: &DynamicImage, full_scale: u8) -> RgbImage {
+    let reference = reference.to_rgb8();
+    let image = image.to_rgb8();
+    let full_scale = full_scale.max(1) as f32;
+    RgbImage::from_fn(reference.width(), reference.height(), |x, y| {
+        let a = reference.get_pixel(x, y).0;
+        let b = image.get_pixel(x, y).0;
+        let diff = (0..3).map(|c| a[c].abs_diff(b[c])).max().unwrap_or(0);
+        heat_color(diff as f32 / full_scale)
+    })
+}
+
+/// Peak signal-to-noise ratio of `image` against `reference` over the R, G and B samples, in dB.
+/// Infinite for identical images. Both images must have the same size.
+pub fn psnr(reference: &DynamicImage, image: &DynamicImage) -> f64 {
+    assert_eq!(
+        (reference.width(), reference.height()),
+        (image.width(), image.height()),
+        "images must have the same size"
+    );
+    let reference = reference.to_rgb8();
+    let image = image.to_rgb8();
+    let squares: f64 = reference
+        .iter()
+        .zip(image.iter())
+        .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+        .sum();
+    let mse = squares / reference.len().max(1) as f64;
+    10.0 * (255.0 * 255.0 / mse).log10()
+}
+
+/// Mean structural similarity (Wang et al. 2004) of the gray levels of `image` and `reference`,
+/// with the usual gaussian window of deviation 1.5 and constants. 1 for identical images. Both
+/// images must have the same size.
+pub fn ssim(reference: &DynamicImage, image: &DynamicImage) -> f64 {
+    assert_eq!(
+        (reference.width(), reference.height()),
+        (image.width(), image.height()),
+        "images must have the same size"
+    );
+    const C1: f32 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f32 = (0.03 * 255.0) * (0.03 * 255.0);
+    let (width, height) = (reference.width(), reference.height());
+    let mut planes: Planes<f32, 5> = Planes::new(width, height);
+    let (a, b) = (reference.to_luma8(), image.to_luma8());
+    for (x, y, pixel) in a.enumerate_pixels() {
+        let u = pixel.0[0] as f32;
+        let v = b.get_pixel(x, y).0[0] as f32;
+        planes.put_pixel(x, y, [u, v, u * u, v * v, u * v]);
+    }
+    let means = gaussian_blur(&planes, 5, 1.5, Border::new(BorderMode::Reflect));
+    let mut sum = 0.0f64;
+    for y in 0..height {
+        for x in 0..width {
+            let [mu, mv, uu, vv, uv] = means.get_pixel(x, y);
+            let (su, sv, suv) = (uu - mu * mu, vv - mv * mv, uv - mu * mv);
+            let similarity = ((2.0 * mu * mv + C1) * (2.0 * suv + C2))
+                / ((mu * mu + mv * mv + C1) * (su + sv + C2));
+            sum += similarity as f64;
+        }
+    }
+    sum / (width as f64 * height as f64).max(1.0)
+}
+
+/// Fidelity of an image to a reference.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quality {
+    /// Peak signal-to-noise ratio in dB, see `psnr`.
+    pub psnr: f64,
+    /// Mean structural similarity, see `ssim`.
+    pub ssim: f64,
+}
+
+impl Quality {
+    /// Measures `image` against `reference`. Both images must have the same size.
+    pub fn measure(reference: &DynamicImage, image: &DynamicImage) -> Self {
+        Quality {
+            psnr: psnr(reference, image),
+            ssim: ssim(reference, image),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    fn checkerboard(low: u8, high: u8) -> DynamicImage {
+        let image = GrayImage::from_fn(32, 32, |x, y| {
+            Luma([if (x / 4 + y / 4) % 2 == 0 { low } else { high }])
+        });
+        DynamicImage::from(image)
+    }
+
+    #[test]
+    fn psnr_of_a_uniform_error() {
+        let a = DynamicImage::from(GrayImage::from_pixel(8, 8, Luma([100])));
+        let b = DynamicImage::from(GrayImage::from_pixel(8, 8, Luma([110])));
+        assert!((psnr(&a, &b) - 10.0 * (255.0f64 * 255.0 / 100.0).log10()).abs() < 1e-9);
+        assert_eq!(psnr(&a, &a), f64::INFINITY);
+    }
+
+    #[test]
+    fn ssim_falls_with_lost_contrast() {
+        let sharp = checkerboard(50, 200);
+        assert!((ssim(&sharp, &sharp) - 1.0).abs() < 1e-4);
+        let soft = ssim(&sharp, &checkerboard(100, 150));
+        let flat = ssim(&sharp, &checkerboard(125, 125));
+        assert!(flat < soft && soft < 0.9, "{} {}", soft, flat);
+    }
+}
@@ -1,2 +1,12 @@
 pub mod color;
+pub mod edge;
+pub mod filter;
 pub mod gray;
+pub mod hist;
+pub mod integral;
+pub mod lut;
+pub mod lut3d;
+pub mod metrics;
+pub mod noise;
+pub mod planes;
+pub mod sharpen;
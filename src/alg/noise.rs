@@ -0,0 +1,350 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+use super::filter::{bilateral, median, non_local_means, wiener, Border};
+use super::planes::Planes;
+
+/// Pseudo-random generator (SplitMix64), so that the same seed always gives the same noise.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in 0~1, excluding 1.
+    pub fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Standard normal, by the Box-Muller transform.
+    pub fn gaussian(&mut self) -> f64 {
+        let radius = (-2.0 * (1.0 - self.uniform()).ln()).sqrt();
+        radius * (2.0 * PI * self.uniform()).cos()
+    }
+
+    /// Poisson distributed count of `mean`: by Knuth's multiplication method for small means, by
+    /// the rounded normal approximation for large ones.
+    pub fn poisson(&mut self, mean: f64) -> f64 {
+        if mean <= 0.0 {
+            return 0.0;
+        }
+        if mean > 30.0 {
+            return (mean + mean.sqrt() * self.gaussian()).round().max(0.0);
+        }
+        let limit = (-mean).exp();
+        let mut count = 0.0;
+        let mut product = self.uniform();
+        while product > limit {
+            count += 1.0;
+            product *= self.uniform();
+        }
+        count
+    }
+}
+
+/// Noise distributions of the `noise` and `denoise` subcommands.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum NoiseKind {
+    /// Additive gaussian noise, of deviation `amount` levels.
+    #[default]
+    Gaussian,
+    /// A fraction `amount` of the pixels turned black or white.
+    SaltAndPepper,
+    /// Photon counting noise, with one photon every `amount` levels.
+    Poisson,
+    /// Multiplicative gaussian noise, of relative deviation `amount`.
+    Speckle,
+    /// Additive uniform noise, up to `amount` levels either way.
+    Uniform,
+}
+
+impl NoiseKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            NoiseKind::Gaussian => "gaussian",
+            NoiseKind::SaltAndPepper => "salt-and-pepper",
+            NoiseKind::Poisson => "poisson",
+            NoiseKind::Speckle => "speckle",
+            NoiseKind::Uniform => "uniform",
+        }
+    }
+
+    /// Amount giving noise of about 20 levels of deviation on mid gray, or 5% of the pixels for
+    /// salt and pepper.
+    pub fn default_amount(self) -> f32 {
+        match self {
+            NoiseKind::Gaussian => 20.0,
+            NoiseKind::SaltAndPepper => 0.05,
+            NoiseKind::Poisson => 3.0,
+            NoiseKind::Speckle => 0.15,
+            NoiseKind::Uniform => 35.0,
+        }
+    }
+}
+
+impl fmt::Display for NoiseKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Noise added by `add_noise`, with `amount` as described for its kind.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Noise {
+    pub kind: NoiseKind,
+    pub amount: f32,
+}
+
+impl Noise {
+    pub fn new(kind: NoiseKind) -> Self {
+        Noise {
+            kind,
+            amount: kind.default_amount(),
+        }
+    }
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Noise::new(NoiseKind::default())
+    }
+}
+
+/// `planes` with levels in 0~255 plus `noise` drawn from a generator seeded with `seed`, not
+/// clipped. Salt and pepper hits all planes of a pixel at once, the other kinds every sample
+/// independently.
+pub fn add_noise<const N: usize>(
+    planes: &Planes<f32, N>,
+    noise: Noise,
+    seed: u64,
+) -> Planes<f32, N> {
+    assert!(noise.amount >= 0.0, "noise amount must not be negative");
+    assert!(
+        noise.kind != NoiseKind::SaltAndPepper || noise.amount <= 1.0,
+        "salt and pepper amount must be a fraction"
+    );
+    let mut rng = Rng::new(seed);
+    let amount = noise.amount as f64;
+    let mut out = planes.clone();
+    for y in 0..planes.height() {
+        for x in 0..planes.width() {
+            let pixel = planes.get_pixel(x, y);
+            let noisy = if noise.kind == NoiseKind::SaltAndPepper {
+                if rng.uniform() < amount {
+                    [if rng.uniform() < 0.5 { 0.0 } else { 255.0 }; N]
+                } else {
+                    pixel
+                }
+            } else {
+                pixel.map(|v| {
+                    let v = v as f64;
+                    let noisy = match noise.kind {
+                        NoiseKind::Gaussian => v + amount * rng.gaussian(),
+                        NoiseKind::Poisson if amount > 0.0 => rng.poisson(v / amount) * amount,
+                        NoiseKind::Speckle => v * (1.0 + amount * rng.gaussian()),
+                        NoiseKind::Uniform => v + amount * (2.0 * rng.uniform() - 1.0),
+                        _ => v,
+                    };
+                    noisy as f32
+                })
+            };
+            out.put_pixel(x, y, noisy);
+        }
+    }
+    out
+}
+
+/// Deviation of the additive white noise of `plane`, `width` samples wide, by Immerkær's method
+/// (1996): the mean absolute response to a mask that cancels out the local mean and gradient.
+pub fn estimate_sigma(plane: &[f32], width: usize, height: usize) -> f32 {
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    const MASK: [f32; 9] = [1.0, -2.0, 1.0, -2.0, 4.0, -2.0, 1.0, -2.0, 1.0];
+    let mut sum = 0.0f64;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let mut response = 0.0;
+            for (k, weight) in MASK.iter().enumerate() {
+                response += weight * plane[(y + k / 3 - 1) * width + x + k % 3 - 1];
+            }
+            sum += response.abs() as f64;
+        }
+    }
+    let count = ((width - 2) * (height - 2)) as f64;
+    ((PI / 2.0).sqrt() * sum / (6.0 * count)) as f32
+}
+
+/// Denoising filters compared by the `denoise` subcommand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum DenoiseMethod {
+    /// 3×3 median.
+    Median,
+    /// 7×7 bilateral filter, with a range deviation of twice the noise.
+    Bilateral,
+    /// Non-local means of 3×3 patches over a 15×15 search window.
+    Nlm,
+    /// 5×5 adaptive Wiener filter.
+    Wiener,
+}
+
+impl DenoiseMethod {
+    pub const ALL: [DenoiseMethod; 4] = [
+        DenoiseMethod::Median,
+        DenoiseMethod::Bilateral,
+        DenoiseMethod::Nlm,
+        DenoiseMethod::Wiener,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DenoiseMethod::Median => "median",
+            DenoiseMethod::Bilateral => "bilateral",
+            DenoiseMethod::Nlm => "nlm",
+            DenoiseMethod::Wiener => "wiener",
+        }
+    }
+
+    /// Denoises `planes` tuned for noise of deviation `sigma`.
+    pub fn apply<const N: usize>(
+        self,
+        planes: &Planes<f32, N>,
+        sigma: f32,
+        border: Border,
+    ) -> Planes<f32, N> {
+        let sigma = sigma.max(1.0);
+        match self {
+            DenoiseMethod::Median => median(planes, 1, border),
+            DenoiseMethod::Bilateral => bilateral(planes, 3, 2.0, 2.0 * sigma, border),
+            DenoiseMethod::Nlm => non_local_means(planes, 1, 7, sigma, 0.55 * sigma, border),
+            DenoiseMethod::Wiener => wiener(planes, 2, sigma * sigma, border),
+        }
+    }
+}
+
+impl fmt::Display for DenoiseMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(width: u32, height: u32, value: f32) -> Planes<f32, 1> {
+        let mut planes = Planes::new(width, height);
+        planes.plane_mut(0).fill(value);
+        planes
+    }
+
+    #[test]
+    fn same_seed_gives_same_noise() {
+        let planes = gray(16, 16, 128.0);
+        for kind in [
+            NoiseKind::Gaussian,
+            NoiseKind::SaltAndPepper,
+            NoiseKind::Poisson,
+            NoiseKind::Speckle,
+            NoiseKind::Uniform,
+        ] {
+            let noise = Noise::new(kind);
+            assert_eq!(add_noise(&planes, noise, 7), add_noise(&planes, noise, 7));
+            assert_ne!(add_noise(&planes, noise, 7), add_noise(&planes, noise, 8));
+        }
+    }
+
+    #[test]
+    fn default_amounts_give_comparable_deviations() {
+        let planes = gray(128, 128, 128.0);
+        for kind in [
+            NoiseKind::Gaussian,
+            NoiseKind::Poisson,
+            NoiseKind::Speckle,
+            NoiseKind::Uniform,
+        ] {
+            let noisy = add_noise(&planes, Noise::new(kind), 1);
+            let n = noisy.plane(0).len() as f32;
+            let mean = noisy.plane(0).iter().sum::<f32>() / n;
+            let variance = noisy
+                .plane(0)
+                .iter()
+                .map(|v| (v - mean).powi(2))
+                .sum::<f32>()
+                / n;
+            assert!((mean - 128.0).abs() < 1.0, "{}: mean {}", kind, mean);
+            assert!(
+                (17.0..23.0).contains(&variance.sqrt()),
+                "{}: {}",
+                kind,
+                variance.sqrt()
+            );
+        }
+    }
+
+    #[test]
+    fn salt_and_pepper_hits_the_given_fraction() {
+        let planes = gray(100, 100, 128.0);
+        let noise = Noise {
+            kind: NoiseKind::SaltAndPepper,
+            amount: 0.1,
+        };
+        let noisy = add_noise(&planes, noise, 3);
+        let hits = noisy.plane(0).iter().filter(|&&v| v != 128.0).count();
+        assert!((900..1100).contains(&hits), "{}", hits);
+        assert!(noisy
+            .plane(0)
+            .iter()
+            .all(|&v| v == 0.0 || v == 128.0 || v == 255.0));
+    }
+
+    #[test]
+    fn estimated_sigma_matches_gaussian_noise() {
+        // A gradient, which the mask cancels, under noise of deviation 10.
+        let mut planes = Planes::<f32, 1>::new(128, 128);
+        for y in 0..128 {
+            for x in 0..128 {
+                planes.put_pixel(x, y, [(x + y) as f32]);
+            }
+        }
+        let noise = Noise {
+            kind: NoiseKind::Gaussian,
+            amount: 10.0,
+        };
+        let noisy = add_noise(&planes, noise, 5);
+        let sigma = estimate_sigma(noisy.plane(0), 128, 128);
+        assert!((sigma - 10.0).abs() < 0.5, "{}", sigma);
+        assert!(estimate_sigma(planes.plane(0), 128, 128) < 1e-3);
+    }
+
+    #[test]
+    fn every_method_reduces_gaussian_noise() {
+        let clean = gray(32, 32, 100.0);
+        let noisy = add_noise(&clean, Noise::default(), 11);
+        let error = |planes: &Planes<f32, 1>| -> f32 {
+            planes
+                .plane(0)
+                .iter()
+                .map(|v| (v - 100.0).powi(2))
+                .sum::<f32>()
+        };
+        for method in DenoiseMethod::ALL {
+            let denoised = method.apply(&noisy, 20.0, Border::default());
+            assert!(error(&denoised) < 0.5 * error(&noisy), "{}", method);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use image::{GrayImage, Luma};
+
+use super::color::encode_value;
+
+/// An image stored as `N` separate planes of samples, e.g. the H, S and V planes of an HSV image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Planes<T, const N: usize> {
+    width: u32,
+    height: u32,
+    planes: [Vec<T>; N],
+}
+
+impl<T: Copy + Default, const N: usize> Planes<T, N> {
+    pub fn new(width: u32, height: u32) -> Self {
+        let len = width as usize * height as usize;
+        Planes {
+            width,
+            height,
+            planes: std::array::from_fn(|_| vec![T::default(); len]),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Samples of plane `index` in row-major order.
+    pub fn plane(&self, index: usize) -> &[T] {
+        &self.planes[index]
+    }
+
+    pub fn plane_mut(&mut self, index: usize) -> &mut [T] {
+        &mut self.planes[index]
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> [T; N] {
+        let i = self.index(x, y);
+        std::array::from_fn(|p| self.planes[p][i])
+    }
+
+    pub fn put_pixel(&mut self, x: u32, y: u32, pixel: [T; N]) {
+        let i = self.index(x, y);
+        for (plane, value) in self.planes.iter_mut().zip(pixel) {
+            plane[i] = value;
+        }
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+}
+
+impl<const N: usize> Planes<f32, N> {
+    /// Encodes plane `index` as a grayscale image, mapping `range` to 0~255.
+    pub fn encode_plane(&self, index: usize, range: (f32, f32)) -> GrayImage {
+        let plane = self.plane(index);
+        GrayImage::from_fn(self.width, self.height, |x, y| {
+            Luma([encode_value(plane[self.index(x, y)], range)])
+        })
+    }
+}
@@ -0,0 +1,195 @@
+use std::fmt;
+
+use super::filter::{box_blur, convolve, default_sigma, gaussian_blur, Border, Kernel};
+use super::planes::Planes;
+
+/// Sharpening methods of the `sharpen` subcommand.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum SharpenMethod {
+    /// Adds the difference from a gaussian blur.
+    #[default]
+    Unsharp,
+    /// Adds the difference from the box mean, as in Gonzalez & Woods; amounts above 1 boost the
+    /// high frequencies over the original.
+    Highboost,
+    /// Subtracts the 8-neighbor Laplacian.
+    Laplacian,
+}
+
+impl SharpenMethod {
+    pub fn name(self) -> &'static str {
+        match self {
+            SharpenMethod::Unsharp => "unsharp",
+            SharpenMethod::Highboost => "highboost",
+            SharpenMethod::Laplacian => "laplacian",
+        }
+    }
+}
+
+impl fmt::Display for SharpenMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Parameters of `Sharpen::detail`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sharpen {
+    pub method: SharpenMethod,
+    /// Factor of the detail added to the original.
+    pub amount: f32,
+    /// Radius of the blur of `Unsharp` and `Highboost`. `Laplacian` always works on 3×3.
+    pub radius: u32,
+    /// Detail of at most this magnitude, in the units of the samples, is dropped so that noise
+    /// and smooth gradients are left alone.
+    pub threshold: f32,
+}
+
+impl Default for Sharpen {
+    fn default() -> Self {
+        Sharpen {
+            method: SharpenMethod::default(),
+            amount: 1.0,
+            radius: 2,
+            threshold: 0.0,
+        }
+    }
+}
+
+impl Sharpen {
+    /// Detail to add to every plane of `planes` to sharpen them, already scaled by the amount and
+    /// thresholded.
+    pub fn detail<const N: usize>(
+        &self,
+        planes: &Planes<f32, N>,
+        border: Border,
+    ) -> Planes<f32, N> {
+        let mut detail = match self.method {
+            SharpenMethod::Unsharp => {
+                let blurred =
+                    gaussian_blur(planes, self.radius, default_sigma(self.radius), border);
+                difference(planes, &blurred)
+            }
+            SharpenMethod::Highboost => difference(planes, &box_blur(planes, self.radius, border)),
+            SharpenMethod::Laplacian => {
+                let mut weights = vec![-1.0; 9];
+                weights[4] = 8.0;
+                convolve(planes, &Kernel::new(3, 3, weights), border)
+            }
+        };
+        for p in 0..N {
+            for v in detail.plane_mut(p) {
+                *v = if v.abs() > self.threshold {
+                    *v * self.amount
+                } else {
+                    0.0
+                };
+            }
+        }
+        detail
+    }
+
+    /// `planes` plus their detail.
+    pub fn apply<const N: usize>(&self, planes: &Planes<f32, N>, border: Border) -> Planes<f32, N> {
+        let mut out = self.detail(planes, border);
+        for p in 0..N {
+            for (o, v) in out.plane_mut(p).iter_mut().zip(planes.plane(p)) {
+                *o += v;
+            }
+        }
+        out
+    }
+}
+
+fn difference<const N: usize>(planes: &Planes<f32, N>, blurred: &Planes<f32, N>) -> Planes<f32, N> {
+    let mut out = planes.clone();
+    for p in 0..N {
+        for (o, b) in out.plane_mut(p).iter_mut().zip(blurred.plane(p)) {
+            *o -= b;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alg::filter::BorderMode;
+
+    fn step(width: u32, height: u32) -> Planes<f32, 1> {
+        let mut planes = Planes::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let v = if x < width / 2 { 50.0 } else { 150.0 };
+                planes.put_pixel(x, y, [v]);
+            }
+        }
+        planes
+    }
+
+    #[test]
+    fn flat_planes_are_unchanged() {
+        let mut planes = Planes::<f32, 1>::new(9, 7);
+        planes.plane_mut(0).fill(80.0);
+        for method in [
+            SharpenMethod::Unsharp,
+            SharpenMethod::Highboost,
+            SharpenMethod::Laplacian,
+        ] {
+            let sharpen = Sharpen {
+                method,
+                amount: 2.0,
+                ..Sharpen::default()
+            };
+            let out = sharpen.apply(&planes, Border::new(BorderMode::Reflect));
+            assert!(out.plane(0).iter().all(|&v| (v - 80.0).abs() < 1e-4));
+        }
+    }
+
+    #[test]
+    fn steps_overshoot_on_both_sides() {
+        let planes = step(12, 5);
+        for method in [
+            SharpenMethod::Unsharp,
+            SharpenMethod::Highboost,
+            SharpenMethod::Laplacian,
+        ] {
+            let sharpen = Sharpen {
+                method,
+                ..Sharpen::default()
+            };
+            let out = sharpen.apply(&planes, Border::default());
+            let row = &out.plane(0)[2 * 12..3 * 12];
+            assert!(row[5] < 50.0, "{}: {:?}", method, row);
+            assert!(row[6] > 150.0, "{}: {:?}", method, row);
+            assert!((row[0] - 50.0).abs() < 1e-4 && (row[11] - 150.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn laplacian_of_amount_1_is_the_classic_kernel() {
+        let mut planes = Planes::<f32, 1>::new(3, 3);
+        planes
+            .plane_mut(0)
+            .copy_from_slice(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 20.0]);
+        let sharpen = Sharpen {
+            method: SharpenMethod::Laplacian,
+            ..Sharpen::default()
+        };
+        let out = sharpen.apply(&planes, Border::default());
+        let expected = 9.0 * 5.0 - (1.0 + 2.0 + 3.0 + 4.0 + 6.0 + 7.0 + 8.0 + 20.0);
+        assert!((out.get_pixel(1, 1)[0] - expected).abs() < 1e-4);
+    }
+
+    #[test]
+    fn detail_up_to_the_threshold_is_dropped() {
+        let planes = step(12, 5);
+        let sharpen = Sharpen {
+            threshold: 100.0,
+            ..Sharpen::default()
+        };
+        let detail = sharpen.detail(&planes, Border::default());
+        assert!(detail.plane(0).iter().all(|&v| v == 0.0));
+    }
+}
@@ -1,4 +1,5 @@
-use image::{DynamicImage, GenericImageView, GrayImage};
+use image::{DynamicImage, GenericImageView};
+use imgproc::proc::NamedImage;
 use softbuffer::Surface;
 use std::num::NonZeroU32;
 
@@ -7,42 +8,30 @@ pub trait Draw {
 }
 
 pub struct ImageDrawer {
-    width: u32,
-    height: u32,
-    draw: Box<dyn Draw>,
+    image: NamedImage,
 }
 
 impl ImageDrawer {
-    pub fn new(width: u32, height: u32, draw: Box<dyn Draw>) -> Self {
-        ImageDrawer {
-            width,
-            height,
-            draw,
-        }
+    pub fn name(&self) -> &str {
+        &self.image.name
     }
 
     pub fn width(&self) -> u32 {
-        self.width
+        self.image.image.width()
     }
 
     pub fn height(&self) -> u32 {
-        self.height
+        self.image.image.height()
     }
 
     pub fn draw(&self, surface: &mut Surface) {
-        self.draw.draw(surface);
-    }
-}
-
-impl From<DynamicImage> for ImageDrawer {
-    fn from(image: DynamicImage) -> Self {
-        ImageDrawer::new(image.width(), image.height(), Box::new(image))
+        self.image.image.draw(surface);
     }
 }
 
-impl From<GrayImage> for ImageDrawer {
-    fn from(image: GrayImage) -> Self {
-        ImageDrawer::new(image.width(), image.height(), Box::new(image))
+impl From<NamedImage> for ImageDrawer {
+    fn from(image: NamedImage) -> Self {
+        ImageDrawer { image }
     }
 }
 
@@ -70,28 +59,3 @@ impl Draw for DynamicImage {
         buffer.present().unwrap();
     }
 }
-
-impl Draw for GrayImage {
-    fn draw(&self, surface: &mut Surface) {
-        let width = self.width();
-        let height = self.height();
-        surface
-            .resize(
-                NonZeroU32::new(width).unwrap(),
-                NonZeroU32::new(height).unwrap(),
-            )
-            .unwrap();
-
-        let mut buffer = surface.buffer_mut().unwrap();
-        for y in 0..height {
-            for x in 0..width {
-                let luma = self.get_pixel(x, y).0[0] as u32;
-                let color = luma | (luma << 8) | (luma << 16);
-                let index = y as usize * width as usize + x as usize;
-                buffer[index] = color;
-            }
-        }
-
-        buffer.present().unwrap();
-    }
-}
@@ -0,0 +1,64 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Errors reported by the processing functions and the `imgproc` tool.
+#[derive(Debug)]
+pub enum Error {
+    /// A file or directory could not be read or written.
+    Io { path: PathBuf, source: io::Error },
+    /// A file could not be decoded as an image.
+    Decode {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    /// A command line argument or function parameter has an invalid value.
+    InvalidArgument(String),
+    /// The requested color space is not supported.
+    UnknownColorSpace(String),
+    /// No window can be opened to show the results.
+    DisplayUnavailable(String),
+    /// A file, such as a lookup table, does not hold valid data of its format.
+    InvalidData(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Process exit code for the error, distinct for each kind so scripts can tell them apart.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::InvalidArgument(_) => 2,
+            Error::Io { .. } => 3,
+            Error::Decode { .. } => 4,
+            Error::UnknownColorSpace(_) => 5,
+            Error::DisplayUnavailable(_) => 6,
+            Error::InvalidData(_) => 7,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Decode { path, source } => {
+                write!(f, "{}: cannot decode image: {}", path.display(), source)
+            }
+            Error::InvalidArgument(msg) => write!(f, "invalid argument: {}", msg),
+            Error::UnknownColorSpace(name) => write!(f, "unknown color space: {}", name),
+            Error::DisplayUnavailable(msg) => write!(f, "display unavailable: {}", msg),
+            Error::InvalidData(msg) => write!(f, "invalid data: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Decode { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
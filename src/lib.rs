@@ -0,0 +1,11 @@
+//! Image processing algorithms and a GUI-free processing API.
+//!
+//! The `imgproc` binary built on top of this library needs the `cli` feature, and the `viewer`
+//! feature to show the results in windows.
+
+pub mod alg;
+mod error;
+pub mod proc;
+pub mod render;
+
+pub use error::{Error, Result};
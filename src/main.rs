@@ -1,34 +1,181 @@
-use crate::view::ImageView;
-
-use clap::{arg, Command};
-use image::DynamicImage;
-use proc::*;
-use std::cmp::max;
-use std::collections::HashMap;
-use winit::event::{Event, WindowEvent};
-use winit::event_loop::{ControlFlow, EventLoop};
-use winit::window::{WindowBuilder, WindowButtons};
-
-mod alg;
+use clap::{arg, value_parser, Arg, ArgGroup, ArgMatches, Command};
+use image::{DynamicImage, ImageError};
+use imgproc::alg::color::{
+    ColorOptions, ColorSpace, WhitePoint, YCbCrMatrix, YCbCrRange, YCbCrStandard,
+};
+use imgproc::alg::edge::EdgeOperator;
+use imgproc::alg::filter::{self as filters, Border, BorderMode, Filter, FilterKind, Kernel};
+use imgproc::alg::gray::{AdaptiveMethod, ClaheParams, TargetCurve, ThresholdMethod};
+use imgproc::alg::hist::{ChannelPair, ImageHistogram};
+use imgproc::alg::lut::Levels;
+use imgproc::alg::lut3d::{self, Interpolation, Lut3d};
+use imgproc::alg::metrics::{ChannelError, Quality};
+use imgproc::alg::noise::{DenoiseMethod, Noise, NoiseKind};
+use imgproc::alg::sharpen::{Sharpen, SharpenMethod};
+use imgproc::proc::*;
+use imgproc::render::histogram::{self as chart, AxisScale, HistogramStyle, Layout};
+use imgproc::{Error, Result};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::ExitCode;
+
+#[cfg(feature = "viewer")]
 mod draw;
-mod proc;
+#[cfg(feature = "viewer")]
 mod view;
 
+fn color_space_arg() -> Arg {
+    arg!(--color_space <COLOR_SPACE>)
+        .help("color space to convert to")
+        .value_parser(value_parser!(ColorSpace))
+        .ignore_case(true)
+        .require_equals(true)
+}
+
+fn white_point_arg() -> Arg {
+    arg!(--white_point <WHITE_POINT>)
+        .help("reference white of the CIE color spaces (xyz, lab, luv, lch)")
+        .value_parser(value_parser!(WhitePoint))
+        .ignore_case(true)
+        .require_equals(true)
+}
+
+fn ycbcr_matrix_arg() -> Arg {
+    arg!(--ycbcr_matrix <MATRIX>)
+        .help("matrix coefficients of YCbCr")
+        .value_parser(value_parser!(YCbCrMatrix))
+        .ignore_case(true)
+        .require_equals(true)
+}
+
+fn ycbcr_range_arg() -> Arg {
+    arg!(--ycbcr_range <RANGE>)
+        .help("quantization range of YCbCr")
+        .value_parser(value_parser!(YCbCrRange))
+        .ignore_case(true)
+        .require_equals(true)
+}
+
+fn border_arg() -> Arg {
+    arg!(--border <MODE>)
+        .help("samples assumed outside of the image [default: clamp]")
+        .value_parser(value_parser!(BorderMode))
+        .ignore_case(true)
+        .require_equals(true)
+}
+
+fn border_value_arg() -> Arg {
+    arg!(--border_value <LEVEL>)
+        .help("level outside of the image with --border=constant [default: 0]")
+        .require_equals(true)
+}
+
+fn border(matches: &ArgMatches) -> Result<Border> {
+    Ok(Border {
+        mode: matches
+            .get_one::<BorderMode>("border")
+            .copied()
+            .unwrap_or_default(),
+        value: matches
+            .get_one::<String>("border_value")
+            .map(|s| parse_border_value(s))
+            .transpose()?
+            .unwrap_or(0.0),
+    })
+}
+
+fn noise_arg() -> Arg {
+    arg!(--noise <KIND>)
+        .help("distribution of the noise [default: gaussian]")
+        .value_parser(value_parser!(NoiseKind))
+        .ignore_case(true)
+        .require_equals(true)
+}
+
+fn noise_amount_arg() -> Arg {
+    arg!(--amount <F>)
+        .help("deviation in levels for gaussian, fraction of the pixels for salt-and-pepper, levels per photon for poisson, relative deviation for speckle, largest offset in levels for uniform [default: 20, 0.05, 3, 0.15, 35]")
+        .require_equals(true)
+}
+
+fn seed_arg() -> Arg {
+    arg!(--seed <N>)
+        .help("seed of the pseudo-random noise [default: 0]")
+        .require_equals(true)
+}
+
+fn noise_params(matches: &ArgMatches) -> Result<(Noise, u64)> {
+    let kind = matches
+        .get_one::<NoiseKind>("noise")
+        .copied()
+        .unwrap_or_default();
+    let amount = matches
+        .get_one::<String>("amount")
+        .map(|s| parse_positive("amount", s))
+        .transpose()?
+        .unwrap_or(kind.default_amount());
+    if kind == NoiseKind::SaltAndPepper && amount > 1.0 {
+        return Err(Error::InvalidArgument(format!(
+            "salt-and-pepper amount must be a fraction of the pixels in 0~1, got {}",
+            amount
+        )));
+    }
+    let seed = matches
+        .get_one::<String>("seed")
+        .map(|s| parse_seed(s))
+        .transpose()?
+        .unwrap_or(0);
+    Ok((Noise { kind, amount }, seed))
+}
+
+fn color_options(matches: &ArgMatches) -> ColorOptions {
+    ColorOptions {
+        white_point: matches
+            .get_one::<WhitePoint>("white_point")
+            .copied()
+            .unwrap_or_default(),
+        ycbcr: YCbCrStandard {
+            matrix: matches
+                .get_one::<YCbCrMatrix>("ycbcr_matrix")
+                .copied()
+                .unwrap_or_default(),
+            range: matches
+                .get_one::<YCbCrRange>("ycbcr_range")
+                .copied()
+                .unwrap_or_default(),
+        },
+    }
+}
+
 fn cli() -> Command {
     Command::new("imgproc")
         .about("A image processing tool")
         .subcommand_required(true)
         .arg_required_else_help(true)
-        .allow_external_subcommands(true)
+        .arg(
+            arg!(--output <DIR>)
+                .help("save the result images to DIR instead of showing them in windows")
+                .require_equals(true)
+                .global(true),
+        )
         .subcommand(
             Command::new("grayscale")
                 .about("convert to grayscale image")
                 .arg(arg!([PATH] ... "path of the image to process"))
-                .arg(
-                    arg!(--color_space <COLOR_SPACE>)
-                        .help("HSV, HSI, HSL, YUV or RGB")
-                        .require_equals(true),
-                ),
+                .arg(color_space_arg())
+                .arg(white_point_arg())
+                .arg(ycbcr_matrix_arg())
+                .arg(ycbcr_range_arg()),
+        )
+        .subcommand(
+            Command::new("roundtrip")
+                .about("convert to a color space and back, and report the conversion error")
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(color_space_arg().required(true))
+                .arg(white_point_arg())
+                .arg(ycbcr_matrix_arg())
+                .arg(ycbcr_range_arg()),
         )
         .subcommand(
             Command::new("binarize")
@@ -38,12 +185,124 @@ fn cli() -> Command {
                     arg!(--threshold <VALUE>)
                         .help("threshold value (0~255) for binarization.")
                         .require_equals(true),
+                )
+                .arg(
+                    arg!(--method <METHOD>)
+                        .help("method to choose the threshold when none is given")
+                        .value_parser(value_parser!(ThresholdMethod))
+                        .ignore_case(true)
+                        .require_equals(true)
+                        .conflicts_with("threshold"),
+                )
+                .arg(
+                    arg!(--adaptive <METHOD>)
+                        .help("compute a threshold for every pixel from its neighborhood")
+                        .value_parser(value_parser!(AdaptiveMethod))
+                        .ignore_case(true)
+                        .require_equals(true)
+                        .conflicts_with_all(["threshold", "method"]),
+                )
+                .arg(
+                    arg!(--window <N>)
+                        .help("odd width of the neighborhood for --adaptive [default: 15]")
+                        .require_equals(true)
+                        .requires("adaptive"),
+                )
+                .arg(
+                    arg!(--k <F>)
+                        .help("parameter of the --adaptive method, defaults to its usual value")
+                        .value_parser(value_parser!(f32))
+                        .allow_negative_numbers(true)
+                        .require_equals(true)
+                        .requires("adaptive"),
+                ),
+        )
+        .subcommand(
+            Command::new("quantize")
+                .about("split into several gray levels")
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(
+                    arg!(--levels <N>)
+                        .help("number of levels (2~256) to find by multi-level Otsu [default: 3]")
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--cuts <LIST>)
+                        .help("increasing comma separated levels (0~255) to cut at")
+                        .require_equals(true)
+                        .conflicts_with("levels"),
+                )
+                .arg(
+                    arg!(--per_channel)
+                        .help("quantize every RGB channel separately")
+                        .alias("per-channel")
+                        .action(clap::ArgAction::SetTrue),
                 ),
         )
         .subcommand(
             Command::new("histogram")
                 .about("show histogram of the image")
-                .arg(arg!([PATH] ... "path of the image to process")),
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(
+                    arg!(--format <FORMAT>)
+                        .help("also export the RGB and gray bin counts and statistics, to histogram.csv or histogram.json with --output, otherwise to stdout")
+                        .value_parser(["csv", "json"])
+                        .ignore_case(true)
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--scale <SCALE>)
+                        .help("mapping of the counts to the bar heights [default: linear]")
+                        .value_parser(value_parser!(AxisScale))
+                        .ignore_case(true)
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--layout <LAYOUT>)
+                        .help("one band per channel or all channels in one band [default: stacked]")
+                        .value_parser(value_parser!(Layout))
+                        .ignore_case(true)
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--bins <N>)
+                        .help("number of bins the 256 levels are grouped into, 16~256 [default: 256]")
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--size <SIZE>)
+                        .help("size of the chart as WxH or N, 64~4096 [default: 512x512]")
+                        .require_equals(true),
+                )
+                .arg(arg!(--cdf "draw the cumulative distribution over the bars"))
+                .arg(arg!(--labels "draw the counts, levels and channel names"))
+                .arg(color_space_arg().help("plot the planes of this color space instead of RGB"))
+                .arg(white_point_arg())
+                .arg(ycbcr_matrix_arg())
+                .arg(ycbcr_range_arg()),
+        )
+        .subcommand(
+            Command::new("histogram2d")
+                .about("show joint histograms of channel pairs as density images")
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(
+                    arg!(--pairs <PAIRS>)
+                        .help("comma-separated channel pairs to plot [default: all]")
+                        .value_parser(value_parser!(ChannelPair))
+                        .value_delimiter(',')
+                        .ignore_case(true)
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--scale <SCALE>)
+                        .help("mapping of the counts to the brightness [default: log]")
+                        .value_parser(value_parser!(AxisScale))
+                        .ignore_case(true)
+                        .require_equals(true),
+                )
+                .arg(white_point_arg())
+                .arg(ycbcr_matrix_arg())
+                .arg(ycbcr_range_arg()),
         )
         .subcommand(
             Command::new("equalize")
@@ -55,10 +314,53 @@ fn cli() -> Command {
                         .action(clap::ArgAction::SetTrue),
                 )
                 .arg(
-                    arg!(--color_space <COLOR_SPACE>)
-                        .help("HSV, HSI, HSL, YUV or RGB")
+                    arg!(--adaptive)
+                        .help("use contrast-limited adaptive histogram equalization (CLAHE)")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    arg!(--tiles <TILES>)
+                        .help("tiles across and down for --adaptive, as WxH or N [default: 8x8]")
+                        .require_equals(true)
+                        .requires("adaptive"),
+                )
+                .arg(
+                    arg!(--clip_limit <F>)
+                        .help("clip limit of --adaptive, in mean bin counts [default: 2]")
+                        .alias("clip-limit")
+                        .require_equals(true)
+                        .requires("adaptive"),
+                )
+                .arg(color_space_arg())
+                .arg(white_point_arg())
+                .arg(ycbcr_matrix_arg())
+                .arg(ycbcr_range_arg()),
+        )
+        .subcommand(
+            Command::new("match")
+                .about("match histogram to a reference image or a target curve")
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(
+                    arg!(--reference <PATH>)
+                        .help("path of the image whose histogram to match")
                         .require_equals(true),
-                ),
+                )
+                .arg(
+                    arg!(--target <CURVE>)
+                        .help("target histogram curve to match")
+                        .value_parser(value_parser!(TargetCurve))
+                        .ignore_case(true)
+                        .require_equals(true),
+                )
+                .group(
+                    ArgGroup::new("match_target")
+                        .args(["reference", "target"])
+                        .required(true),
+                )
+                .arg(color_space_arg())
+                .arg(white_point_arg())
+                .arg(ycbcr_matrix_arg())
+                .arg(ycbcr_range_arg()),
         )
         .subcommand(
             Command::new("invert")
@@ -70,114 +372,1048 @@ fn cli() -> Command {
                 .about("show image with complementary colors")
                 .arg(arg!([PATH] ... "path of the image to process")),
         )
+        .subcommand(
+            Command::new("gamma")
+                .about("apply gamma correction")
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(
+                    arg!(--value <GAMMA>)
+                        .help("gamma, above 1 to brighten [default: 2.2]")
+                        .require_equals(true),
+                ),
+        )
+        .subcommand(
+            Command::new("levels")
+                .about("remap input levels to output levels with a midtone gamma")
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(
+                    Arg::new("in")
+                        .long("in")
+                        .value_name("LO,HI")
+                        .help("input black and white points [default: 0,255]")
+                        .require_equals(true),
+                )
+                .arg(
+                    Arg::new("out")
+                        .long("out")
+                        .value_name("LO,HI")
+                        .help("output levels of the black and white points, reversed to invert [default: 0,255]")
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--gamma <GAMMA>)
+                        .help("midtone gamma, above 1 to brighten [default: 1]")
+                        .require_equals(true),
+                ),
+        )
+        .subcommand(
+            Command::new("curves")
+                .about("map levels through a monotone curve")
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(
+                    arg!(--points <POINTS>)
+                        .help("comma-separated IN:OUT levels with increasing inputs, e.g. 0:0,64:40,192:220,255:255")
+                        .required(true)
+                        .require_equals(true),
+                ),
+        )
+        .subcommand(
+            Command::new("filter")
+                .about("smooth with a neighborhood filter")
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(
+                    arg!(--kind <KIND>)
+                        .help("filter to apply [default: gaussian]")
+                        .value_parser(value_parser!(FilterKind))
+                        .ignore_case(true)
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--radius <N>)
+                        .help("radius of the square window, 1~32 [default: 2, or 3 sigmas for a gaussian with --sigma]")
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--sigma <F>)
+                        .help("deviation of the gaussian and of the spatial weights of bilateral, defaults to fit the radius")
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--sigma_range <F>)
+                        .help("deviation of the level difference weights of bilateral [default: 25]")
+                        .require_equals(true),
+                )
+                .arg(border_arg())
+                .arg(border_value_arg()),
+        )
+        .subcommand(
+            Command::new("convolve")
+                .about("convolve with a kernel read from a file")
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(
+                    arg!(--kernel <FILE>)
+                        .help("kernel of odd width and height, as a JSON array of rows or as rows of whitespace separated weights")
+                        .required(true)
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--divisor <F>)
+                        .help("number the sums are divided by [default: sum of the weights, or 1 if it is 0]")
+                        .allow_negative_numbers(true)
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--offset <F>)
+                        .help("level added after the division [default: 0]")
+                        .allow_negative_numbers(true)
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--per_channel)
+                        .help("convolve every RGB channel instead of the gray levels")
+                        .alias("per-channel")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(border_arg())
+                .arg(border_value_arg()),
+        )
+        .subcommand(
+            Command::new("edges")
+                .about("detect edges from the gradient or the Laplacian of Gaussian")
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(
+                    arg!(--operator <OPERATOR>)
+                        .help("edge detector [default: canny]")
+                        .value_parser(value_parser!(EdgeOperator))
+                        .ignore_case(true)
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--sigma <F>)
+                        .help("deviation of the gaussian smoothing [default: 1.4 for canny, 2 for log, none otherwise]")
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--low <F>)
+                        .help("lower hysteresis threshold of canny, in levels per pixel [default: half of --high]")
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--high <F>)
+                        .help("upper hysteresis threshold of canny, or smallest step of the log zero crossings [default: by Otsu's method]")
+                        .require_equals(true),
+                )
+                .arg(border_arg())
+                .arg(border_value_arg()),
+        )
+        .subcommand(
+            Command::new("sharpen")
+                .about("sharpen by adding the detail lost to a blur or the negative Laplacian")
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(
+                    arg!(--method <METHOD>)
+                        .help("sharpening method [default: unsharp]")
+                        .value_parser(value_parser!(SharpenMethod))
+                        .ignore_case(true)
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--amount <F>)
+                        .help("factor of the detail added [default: 1]")
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--radius <N>)
+                        .help("radius of the blur of unsharp and highboost [default: 2]")
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--threshold <LEVEL>)
+                        .help("largest detail left alone, in levels of 0~255 [default: 0]")
+                        .require_equals(true),
+                )
+                .arg(color_space_arg().help("sharpen only the intensity plane of this color space instead of every RGB channel"))
+                .arg(white_point_arg())
+                .arg(ycbcr_matrix_arg())
+                .arg(ycbcr_range_arg())
+                .arg(border_arg())
+                .arg(border_value_arg()),
+        )
+        .subcommand(
+            Command::new("noise")
+                .about("add pseudo-random noise")
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(noise_arg())
+                .arg(noise_amount_arg())
+                .arg(seed_arg()),
+        )
+        .subcommand(
+            Command::new("denoise")
+                .about("add noise, remove it again and compare the results to the clean image by PSNR and SSIM")
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(
+                    arg!(--methods <METHODS>)
+                        .help("comma-separated denoising methods to compare [default: all]")
+                        .value_parser(value_parser!(DenoiseMethod))
+                        .value_delimiter(',')
+                        .ignore_case(true)
+                        .require_equals(true),
+                )
+                .arg(noise_arg())
+                .arg(noise_amount_arg())
+                .arg(seed_arg())
+                .arg(border_arg())
+                .arg(border_value_arg()),
+        )
+        .subcommand(
+            Command::new("lut3d")
+                .about("map colors through a 3D lookup table, read from a file or fitted to a chain of subcommands")
+                .arg(arg!([PATH] ... "path of the image to process"))
+                .arg(
+                    arg!(--cube <FILE>)
+                        .help("path of a .cube file with a 3D table")
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--hald <FILE>)
+                        .help("path of a Hald CLUT image")
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--acv <FILE>)
+                        .help("path of a Photoshop .acv curves file")
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--chain <STEPS>)
+                        .help("semicolon-separated subcommands with their options to fit the table to, e.g. \"equalize --color_space=hsv; gamma --value=1.2\"")
+                        .require_equals(true),
+                )
+                .group(
+                    ArgGroup::new("lut3d_source")
+                        .args(["cube", "hald", "acv", "chain"])
+                        .required(true),
+                )
+                .arg(
+                    arg!(--interpolation <METHOD>)
+                        .help("interpolation between the table nodes [default: tetrahedral]")
+                        .value_parser(value_parser!(Interpolation))
+                        .ignore_case(true)
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--size <N>)
+                        .help("nodes per channel of the table made from --acv or --chain, 2~65 [default: 33]")
+                        .require_equals(true),
+                )
+                .arg(
+                    arg!(--save <FILE>)
+                        .help("also write the table to FILE in the .cube format")
+                        .require_equals(true),
+                ),
+        )
 }
 
 fn load_default_image() -> DynamicImage {
     image::load_from_memory(include_bytes!("../res/lena.jpg")).unwrap()
 }
 
-fn load_image(path: Option<&str>) -> DynamicImage {
+fn load_image(path: Option<&str>) -> Result<DynamicImage> {
     match path {
         Some(path) => {
-            println!("Using image: {}", path);
-            image::open(path).unwrap()
+            eprintln!("Using image: {}", path);
+            let bytes = fs::read(path).map_err(|source| Error::Io {
+                path: path.into(),
+                source,
+            })?;
+            image::load_from_memory(&bytes).map_err(|source| Error::Decode {
+                path: path.into(),
+                source,
+            })
         }
         None => {
-            println!("No image path provided, using default image.");
-            load_default_image()
+            eprintln!("No image path provided, using default image.");
+            Ok(load_default_image())
         }
     }
 }
 
-fn main() {
+fn read_file(path: &str) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|source| Error::Io {
+        path: path.into(),
+        source,
+    })
+}
+
+/// Names the file `path` in the message of a data error about its content.
+fn in_file<T>(path: &str, result: Result<T>) -> Result<T> {
+    result.map_err(|e| match e {
+        Error::InvalidData(msg) => Error::InvalidData(format!("{}: {}", path, msg)),
+        e => e,
+    })
+}
+
+fn save_images(dir: &Path, images: &[NamedImage]) -> Result<()> {
+    fs::create_dir_all(dir).map_err(|source| Error::Io {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    for (i, image) in images.iter().enumerate() {
+        let path = dir.join(format!("{:02}-{}.png", i + 1, image.name));
+        image.image.save(&path).map_err(|e| Error::Io {
+            source: match e {
+                ImageError::IoError(e) => e,
+                e => io::Error::other(e),
+            },
+            path: path.clone(),
+        })?;
+        eprintln!("Saved image: {}", path.display());
+    }
+    Ok(())
+}
+
+fn save_text(dir: &Path, name: &str, text: &str) -> Result<()> {
+    fs::create_dir_all(dir).map_err(|source| Error::Io {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    let path = dir.join(name);
+    fs::write(&path, text).map_err(|source| Error::Io {
+        path: path.clone(),
+        source,
+    })?;
+    eprintln!("Saved text: {}", path.display());
+    Ok(())
+}
+
+#[cfg(feature = "viewer")]
+fn show_images(images: Vec<NamedImage>) -> Result<()> {
+    view::show_images(images)
+}
+
+#[cfg(not(feature = "viewer"))]
+fn show_images(_images: Vec<NamedImage>) -> Result<()> {
+    Err(Error::DisplayUnavailable(
+        "imgproc was built without the viewer feature, use --output to save the images".to_string(),
+    ))
+}
+
+fn parse_threshold(value: &str) -> Result<u8> {
+    value.parse::<u8>().map_err(|_| {
+        Error::InvalidArgument(format!(
+            "threshold must be an integer in 0~255, got '{}'",
+            value
+        ))
+    })
+}
+
+fn parse_window(value: &str) -> Result<u32> {
+    match value.parse::<u32>() {
+        Ok(n) if n >= 3 && n % 2 == 1 => Ok(n),
+        _ => Err(Error::InvalidArgument(format!(
+            "window must be an odd integer of at least 3, got '{}'",
+            value
+        ))),
+    }
+}
+
+fn parse_levels(value: &str) -> Result<usize> {
+    match value.parse::<usize>() {
+        Ok(n) if (2..=256).contains(&n) => Ok(n),
+        _ => Err(Error::InvalidArgument(format!(
+            "levels must be an integer in 2~256, got '{}'",
+            value
+        ))),
+    }
+}
+
+fn parse_cuts(value: &str) -> Result<Vec<u8>> {
+    let invalid = || {
+        Error::InvalidArgument(format!(
+            "cuts must be increasing comma separated integers in 0~254, got '{}'",
+            value
+        ))
+    };
+    let cuts = value
+        .split(',')
+        .map(|s| s.trim().parse::<u8>().map_err(|_| invalid()))
+        .collect::<Result<Vec<u8>>>()?;
+    if cuts.windows(2).any(|w| w[0] >= w[1]) || cuts.last() == Some(&255) {
+        return Err(invalid());
+    }
+    Ok(cuts)
+}
+
+fn parse_tiles(value: &str) -> Result<(u32, u32)> {
+    let invalid = || {
+        Error::InvalidArgument(format!(
+            "tiles must be WxH or N with positive integers, got '{}'",
+            value
+        ))
+    };
+    let parse = |s: &str| match s.trim().parse::<u32>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(invalid()),
+    };
+    match value.split_once(['x', 'X']) {
+        Some((w, h)) => Ok((parse(w)?, parse(h)?)),
+        None => parse(value).map(|n| (n, n)),
+    }
+}
+
+fn parse_bins(value: &str) -> Result<usize> {
+    match value.parse::<usize>() {
+        Ok(n) if (chart::MIN_BINS..=chart::MAX_BINS).contains(&n) => Ok(n),
+        _ => Err(Error::InvalidArgument(format!(
+            "bins must be an integer in {}~{}, got '{}'",
+            chart::MIN_BINS,
+            chart::MAX_BINS,
+            value
+        ))),
+    }
+}
+
+fn parse_size(value: &str) -> Result<(u32, u32)> {
+    let (width, height) = parse_tiles(value).map_err(|_| {
+        Error::InvalidArgument(format!(
+            "size must be WxH or N with positive integers, got '{}'",
+            value
+        ))
+    })?;
+    let range = chart::MIN_SIZE..=chart::MAX_SIZE;
+    if !range.contains(&width) || !range.contains(&height) {
+        return Err(Error::InvalidArgument(format!(
+            "size must be within {}~{} on each side, got '{}'",
+            chart::MIN_SIZE,
+            chart::MAX_SIZE,
+            value
+        )));
+    }
+    Ok((width, height))
+}
+
+fn parse_level_range(value: &str) -> Result<(u8, u8)> {
+    let invalid = || {
+        Error::InvalidArgument(format!(
+            "level range must be LO,HI with levels in 0~255, got '{}'",
+            value
+        ))
+    };
+    let (lo, hi) = value.split_once(',').ok_or_else(invalid)?;
+    let parse = |s: &str| s.trim().parse::<u8>().map_err(|_| invalid());
+    Ok((parse(lo)?, parse(hi)?))
+}
+
+fn parse_points(value: &str) -> Result<Vec<(u8, u8)>> {
+    let invalid = || {
+        Error::InvalidArgument(format!(
+            "points must be at least two IN:OUT pairs of levels in 0~255 with increasing inputs, got '{}'",
+            value
+        ))
+    };
+    let points = value
+        .split(',')
+        .map(|point| {
+            let (x, y) = point.split_once(':').ok_or_else(invalid)?;
+            let parse = |s: &str| s.trim().parse::<u8>().map_err(|_| invalid());
+            Ok((parse(x)?, parse(y)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if points.len() < 2 || points.windows(2).any(|w| w[0].0 >= w[1].0) {
+        return Err(invalid());
+    }
+    Ok(points)
+}
+
+fn parse_lut_size(value: &str) -> Result<usize> {
+    match value.parse::<usize>() {
+        Ok(n) if (2..=MAX_FIT_SIZE).contains(&n) => Ok(n),
+        _ => Err(Error::InvalidArgument(format!(
+            "size must be an integer in 2~{}, got '{}'",
+            MAX_FIT_SIZE, value
+        ))),
+    }
+}
+
+fn parse_radius(value: &str) -> Result<u32> {
+    match value.parse::<u32>() {
+        Ok(n) if (1..=MAX_RADIUS).contains(&n) => Ok(n),
+        _ => Err(Error::InvalidArgument(format!(
+            "radius must be an integer in 1~{}, got '{}'",
+            MAX_RADIUS, value
+        ))),
+    }
+}
+
+fn parse_positive(name: &str, value: &str) -> Result<f32> {
+    match value.parse::<f32>() {
+        Ok(v) if v > 0.0 && v.is_finite() => Ok(v),
+        _ => Err(Error::InvalidArgument(format!(
+            "{} must be a positive number, got '{}'",
+            name, value
+        ))),
+    }
+}
+
+fn parse_seed(value: &str) -> Result<u64> {
+    value.parse::<u64>().map_err(|_| {
+        Error::InvalidArgument(format!(
+            "seed must be a non-negative integer, got '{}'",
+            value
+        ))
+    })
+}
+
+fn parse_border_value(value: &str) -> Result<f32> {
+    match value.parse::<f32>() {
+        Ok(v) if (0.0..=255.0).contains(&v) => Ok(v),
+        _ => Err(Error::InvalidArgument(format!(
+            "border value must be a level in 0~255, got '{}'",
+            value
+        ))),
+    }
+}
+
+fn parse_divisor(value: &str) -> Result<f32> {
+    match value.parse::<f32>() {
+        Ok(v) if v != 0.0 && v.is_finite() => Ok(v),
+        _ => Err(Error::InvalidArgument(format!(
+            "divisor must be a nonzero number, got '{}'",
+            value
+        ))),
+    }
+}
+
+fn parse_offset(value: &str) -> Result<f32> {
+    match value.parse::<f32>() {
+        Ok(v) if v.is_finite() => Ok(v),
+        _ => Err(Error::InvalidArgument(format!(
+            "offset must be a number, got '{}'",
+            value
+        ))),
+    }
+}
+
+fn parse_threshold_level(name: &str, value: &str) -> Result<f32> {
+    match value.parse::<f32>() {
+        Ok(v) if v >= 0.0 && v.is_finite() => Ok(v),
+        _ => Err(Error::InvalidArgument(format!(
+            "{} threshold must be a non-negative number, got '{}'",
+            name, value
+        ))),
+    }
+}
+
+fn print_channel_error(color_space: ColorSpace, error: &ChannelError) {
+    println!("Round trip through {}:", color_space);
+    println!("channel  max error  mean error");
+    for (c, channel) in ['r', 'g', 'b'].iter().enumerate() {
+        println!(
+            "{:<7}  {:>9}  {:>10.4}",
+            channel, error.max[c], error.mean[c]
+        );
+    }
+}
+
+fn format_cuts(cuts: &[u8]) -> String {
+    cuts.iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn print_quality(name: &str, quality: &Quality) {
+    println!(
+        "{:<10} PSNR {:.2} dB, SSIM {:.4}",
+        name, quality.psnr, quality.ssim
+    );
+}
+
+/// Largest radius of the `filter` windows.
+const MAX_RADIUS: u32 = 32;
+
+/// Largest number of nodes per channel of the tables made by `lut3d`.
+const MAX_FIT_SIZE: usize = 65;
+
+/// Subcommands that `lut3d --chain` can run, with the name of the image each passes on to the next.
+const CHAIN_RESULTS: [(&str, &str); 8] = [
+    ("equalize", "equalized"),
+    ("match", "matched"),
+    ("invert", "inverted"),
+    ("complement", "complement"),
+    ("gamma", "gamma"),
+    ("levels", "levels"),
+    ("curves", "curves"),
+    ("lut3d", "lut3d"),
+];
+
+/// Runs the semicolon-separated subcommands of `chain` one after the other, starting from `image`,
+/// and returns the last result.
+fn run_chain(chain: &str, image: &DynamicImage) -> Result<DynamicImage> {
+    let mut image = image.clone();
+    for step in chain.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = |msg: &str| Error::InvalidArgument(format!("chain step '{}': {}", step, msg));
+        let args = std::iter::once("imgproc").chain(step.split_whitespace());
+        let matches = cli().try_get_matches_from(args).map_err(|e| {
+            let rendered = e.render().to_string();
+            let first = rendered.lines().next().unwrap_or_default();
+            invalid(first.trim_start_matches("error: "))
+        })?;
+        let Some((name, sub_matches)) = matches.subcommand() else {
+            return Err(invalid("missing subcommand"));
+        };
+        let Some(&(_, result)) = CHAIN_RESULTS.iter().find(|(n, _)| *n == name) else {
+            let names: Vec<&str> = CHAIN_RESULTS.iter().map(|(n, _)| *n).collect();
+            return Err(invalid(&format!(
+                "'{}' cannot be chained, use one of {}",
+                name,
+                names.join(", ")
+            )));
+        };
+        if sub_matches.contains_id("PATH") {
+            return Err(invalid(
+                "steps work on the image of the previous step and take no path",
+            ));
+        }
+        let images = process(name, sub_matches, image, None)?;
+        image = images
+            .into_iter()
+            .find(|i| i.name == result)
+            .ok_or_else(|| invalid(&format!("'{}' gave no '{}' image", name, result)))?
+            .image;
+    }
+    Ok(image)
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+fn run() -> Result<()> {
     let mut command = cli();
-    let drawers;
 
     let matches = cli().get_matches_mut();
-    match matches.subcommand() {
-        Some(("grayscale", sub_matches)) => {
-            let path = sub_matches.get_one::<String>("PATH").map(|s| s.as_str());
-            let color_space = sub_matches.get_one::<String>("color_space");
-            drawers = grayscale(load_image(path), color_space);
-        }
-        Some(("binarize", sub_matches)) => {
-            let path = sub_matches.get_one::<String>("PATH").map(|s| s.as_str());
+    let Some((name, sub_matches)) = matches.subcommand() else {
+        command.print_help().unwrap();
+        return Ok(());
+    };
+    let path = sub_matches.get_one::<String>("PATH").map(|s| s.as_str());
+    let output = matches.get_one::<String>("output").map(Path::new);
+    let images = process(name, sub_matches, load_image(path)?, output)?;
+
+    match output {
+        Some(dir) => save_images(dir, &images),
+        None => show_images(images),
+    }
+}
+
+/// Runs the subcommand `name` on `image`. Text exports go to `output` when given.
+fn process(
+    name: &str,
+    sub_matches: &ArgMatches,
+    image: DynamicImage,
+    output: Option<&Path>,
+) -> Result<Vec<NamedImage>> {
+    let images = match name {
+        "grayscale" => {
+            let color_space = sub_matches.get_one::<ColorSpace>("color_space").copied();
+            grayscale(image, color_space, color_options(sub_matches))
+        }
+        "roundtrip" => {
+            let color_space = *sub_matches.get_one::<ColorSpace>("color_space").unwrap();
+            let (images, error) = roundtrip(image, color_space, color_options(sub_matches));
+            print_channel_error(color_space, &error);
+            images
+        }
+        "binarize" => {
             let threshold = sub_matches
                 .get_one::<String>("threshold")
-                .map(|s| s.parse::<u8>().unwrap());
-            drawers = binarize(load_image(path), threshold);
+                .map(|s| parse_threshold(s))
+                .transpose()?;
+            match sub_matches.get_one::<AdaptiveMethod>("adaptive").copied() {
+                Some(adaptive) => {
+                    let window = sub_matches
+                        .get_one::<String>("window")
+                        .map(|s| parse_window(s))
+                        .transpose()?
+                        .unwrap_or(15);
+                    let k = sub_matches
+                        .get_one::<f32>("k")
+                        .copied()
+                        .unwrap_or(adaptive.default_k());
+                    println!(
+                        "Adaptive threshold ({}): window {}, k {}",
+                        adaptive, window, k
+                    );
+                    binarize_adaptive(image, adaptive, window, Some(k))
+                }
+                None => {
+                    let method = sub_matches
+                        .get_one::<ThresholdMethod>("method")
+                        .copied()
+                        .unwrap_or_default();
+                    let (images, level) = binarize(image, threshold, method);
+                    match threshold {
+                        Some(_) => println!("Binary threshold: {}", level),
+                        None => println!("Binary threshold ({}): {}", method, level),
+                    }
+                    images
+                }
+            }
+        }
+        "quantize" => {
+            let levels = sub_matches
+                .get_one::<String>("levels")
+                .map(|s| parse_levels(s))
+                .transpose()?
+                .unwrap_or(3);
+            let cuts = sub_matches
+                .get_one::<String>("cuts")
+                .map(|s| parse_cuts(s))
+                .transpose()?;
+            let per_channel = sub_matches.get_flag("per_channel");
+            let (images, cuts) = quantize(image, levels, cuts.as_deref(), per_channel);
+            if per_channel {
+                for (channel, cuts) in ['r', 'g', 'b'].iter().zip(&cuts) {
+                    println!("Cuts of {}: {}", channel, format_cuts(cuts));
+                }
+            } else {
+                println!("Cuts: {}", format_cuts(&cuts[0]));
+            }
+            images
+        }
+        "histogram" => {
+            if let Some(format) = sub_matches.get_one::<String>("format") {
+                let format = format.to_ascii_lowercase();
+                let hist = ImageHistogram::from_image(&image);
+                let text = match format.as_str() {
+                    "csv" => hist.to_csv(),
+                    _ => hist.to_json(),
+                };
+                match output {
+                    Some(dir) => save_text(dir, &format!("histogram.{}", format), &text)?,
+                    None => print!("{}", text),
+                }
+            }
+            let defaults = HistogramStyle::default();
+            let (width, height) = sub_matches
+                .get_one::<String>("size")
+                .map(|s| parse_size(s))
+                .transpose()?
+                .unwrap_or((defaults.width, defaults.height));
+            let style = HistogramStyle {
+                width,
+                height,
+                bins: sub_matches
+                    .get_one::<String>("bins")
+                    .map(|s| parse_bins(s))
+                    .transpose()?
+                    .unwrap_or(defaults.bins),
+                scale: sub_matches
+                    .get_one::<AxisScale>("scale")
+                    .copied()
+                    .unwrap_or_default(),
+                layout: sub_matches
+                    .get_one::<Layout>("layout")
+                    .copied()
+                    .unwrap_or_default(),
+                cdf: sub_matches.get_flag("cdf"),
+                labels: sub_matches.get_flag("labels"),
+            };
+            let color_space = sub_matches.get_one::<ColorSpace>("color_space").copied();
+            histogram(image, color_space, color_options(sub_matches), &style)
         }
-        Some(("histogram", sub_matches)) => {
-            let path = sub_matches.get_one::<String>("PATH").map(|s| s.as_str());
-            drawers = histogram(load_image(path));
+        "histogram2d" => {
+            let pairs: Vec<ChannelPair> = match sub_matches.get_many::<ChannelPair>("pairs") {
+                Some(pairs) => pairs.copied().collect(),
+                None => ChannelPair::ALL.to_vec(),
+            };
+            let scale = sub_matches
+                .get_one::<AxisScale>("scale")
+                .copied()
+                .unwrap_or(AxisScale::Log);
+            histogram2d(image, &pairs, color_options(sub_matches), scale)
         }
-        Some(("equalize", sub_matches)) => {
-            let path = sub_matches.get_one::<String>("PATH").map(|s| s.as_str());
+        "equalize" => {
             let grayscale = sub_matches.get_flag("grayscale");
-            let color_space = sub_matches.get_one::<String>("color_space");
-            drawers = equalize(load_image(path), grayscale, color_space);
-        }
-        Some(("invert", sub_matches)) => {
-            let path = sub_matches.get_one::<String>("PATH").map(|s| s.as_str());
-            drawers = invert(load_image(path));
-        }
-        Some(("complement", sub_matches)) => {
-            let path = sub_matches.get_one::<String>("PATH").map(|s| s.as_str());
-            drawers = complement(load_image(path));
-        }
-        _ => {
-            command.print_help().unwrap();
-            return;
-        }
-    }
-
-    let event_loop = EventLoop::new();
-    let mut views = HashMap::new();
-
-    let mut pos = winit::dpi::PhysicalPosition::new(0, 0);
-    let mut y_offset = 0;
-    for drawer in drawers {
-        let window = WindowBuilder::new()
-            .with_inner_size(winit::dpi::PhysicalSize::new(
-                drawer.width(),
-                drawer.height(),
-            ))
-            .with_resizable(false)
-            .with_enabled_buttons(WindowButtons::CLOSE)
-            .build(&event_loop)
-            .unwrap();
-        if let Some(mon) = window.current_monitor() {
-            // 如果不是第一个窗口并且按当前位置排列会超出显示器边界，则将该窗口排到下一行
-            if pos.x > 0 && (pos.x + window.outer_size().width) > mon.size().width {
-                pos = winit::dpi::PhysicalPosition::new(0, y_offset);
+            let color_space = sub_matches.get_one::<ColorSpace>("color_space").copied();
+            let adaptive = if sub_matches.get_flag("adaptive") {
+                let defaults = ClaheParams::default();
+                Some(ClaheParams {
+                    tiles: sub_matches
+                        .get_one::<String>("tiles")
+                        .map(|s| parse_tiles(s))
+                        .transpose()?
+                        .unwrap_or(defaults.tiles),
+                    clip_limit: sub_matches
+                        .get_one::<String>("clip_limit")
+                        .map(|s| parse_positive("clip limit", s))
+                        .transpose()?
+                        .unwrap_or(defaults.clip_limit),
+                })
+            } else {
+                None
+            };
+            equalize(
+                image,
+                grayscale,
+                color_space,
+                color_options(sub_matches),
+                adaptive,
+            )
+        }
+        "match" => {
+            let target = match sub_matches.get_one::<String>("reference") {
+                Some(reference) => MatchTarget::Image(load_image(Some(reference))?),
+                None => MatchTarget::Curve(*sub_matches.get_one::<TargetCurve>("target").unwrap()),
+            };
+            let color_space = sub_matches.get_one::<ColorSpace>("color_space").copied();
+            match_histogram(image, target, color_space, color_options(sub_matches))
+        }
+        "invert" => invert(image),
+        "complement" => complement(image),
+        "gamma" => {
+            let value = sub_matches
+                .get_one::<String>("value")
+                .map(|s| parse_positive("gamma", s))
+                .transpose()?
+                .unwrap_or(2.2);
+            gamma(image, value)
+        }
+        "levels" => {
+            let defaults = Levels::default();
+            let range = |name: &str, default: (u8, u8)| {
+                sub_matches
+                    .get_one::<String>(name)
+                    .map(|s| parse_level_range(s))
+                    .transpose()
+                    .map(|r| r.unwrap_or(default))
+            };
+            let params = Levels {
+                input: range("in", defaults.input)?,
+                output: range("out", defaults.output)?,
+                gamma: sub_matches
+                    .get_one::<String>("gamma")
+                    .map(|s| parse_positive("gamma", s))
+                    .transpose()?
+                    .unwrap_or(defaults.gamma),
+            };
+            if params.input.0 >= params.input.1 {
+                return Err(Error::InvalidArgument(format!(
+                    "input black point must be below the white point, got {},{}",
+                    params.input.0, params.input.1
+                )));
             }
+            levels(image, &params)
         }
-        window.set_outer_position(pos);
-        pos.x += window.outer_size().width;
-        y_offset = max(y_offset, pos.y + window.outer_size().height);
-
-        let view = ImageView::new(window, drawer);
-        views.insert(view.window_id(), view);
-    }
-
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Wait;
-
-        match event {
-            Event::RedrawRequested(window_id) => {
-                let view = views.get_mut(&window_id).unwrap();
-                view.draw();
+        "curves" => {
+            let points = parse_points(sub_matches.get_one::<String>("points").unwrap())?;
+            curves(image, &points)
+        }
+        "filter" => {
+            let kind = sub_matches
+                .get_one::<FilterKind>("kind")
+                .copied()
+                .unwrap_or_default();
+            let sigma = sub_matches
+                .get_one::<String>("sigma")
+                .map(|s| parse_positive("sigma", s))
+                .transpose()?;
+            let radius = match sub_matches.get_one::<String>("radius") {
+                Some(radius) => parse_radius(radius)?,
+                None => match (kind, sigma) {
+                    (FilterKind::Gaussian, Some(sigma)) => {
+                        ((3.0 * sigma).ceil() as u32).clamp(1, MAX_RADIUS)
+                    }
+                    _ => 2,
+                },
+            };
+            let sigma = sigma.unwrap_or_else(|| filters::default_sigma(radius));
+            let params = match kind {
+                FilterKind::Box => Filter::Box { radius },
+                FilterKind::Gaussian => Filter::Gaussian { radius, sigma },
+                FilterKind::Median => Filter::Median { radius },
+                FilterKind::Bilateral => Filter::Bilateral {
+                    radius,
+                    sigma_space: sigma,
+                    sigma_range: sub_matches
+                        .get_one::<String>("sigma_range")
+                        .map(|s| parse_positive("sigma range", s))
+                        .transpose()?
+                        .unwrap_or(25.0),
+                },
+            };
+            filter(image, &params, border(sub_matches)?)
+        }
+        "convolve" => {
+            let path = sub_matches.get_one::<String>("kernel").unwrap();
+            let text = String::from_utf8_lossy(&read_file(path)?).into_owned();
+            let kernel = in_file(path, Kernel::parse(&text))?;
+            let divisor = match sub_matches.get_one::<String>("divisor") {
+                Some(divisor) => parse_divisor(divisor)?,
+                None if kernel.sum().abs() > 1e-6 => kernel.sum(),
+                None => 1.0,
+            };
+            let offset = sub_matches
+                .get_one::<String>("offset")
+                .map(|s| parse_offset(s))
+                .transpose()?
+                .unwrap_or(0.0);
+            let per_channel = sub_matches.get_flag("per_channel");
+            convolve(
+                image,
+                &kernel,
+                divisor,
+                offset,
+                border(sub_matches)?,
+                per_channel,
+            )
+        }
+        "edges" => {
+            let operator = sub_matches
+                .get_one::<EdgeOperator>("operator")
+                .copied()
+                .unwrap_or_default();
+            let number = |name: &str| {
+                sub_matches
+                    .get_one::<String>(name)
+                    .map(|s| parse_threshold_level(name, s))
+                    .transpose()
+            };
+            let sigma = sub_matches
+                .get_one::<String>("sigma")
+                .map(|s| parse_positive("sigma", s))
+                .transpose()?;
+            let (low, high) = (number("low")?, number("high")?);
+            if let (Some(low), Some(high)) = (low, high) {
+                if low > high {
+                    return Err(Error::InvalidArgument(format!(
+                        "low threshold must not be above the high threshold, got {} and {}",
+                        low, high
+                    )));
+                }
             }
-            Event::WindowEvent {
-                event: WindowEvent::CloseRequested,
-                window_id,
-            } => {
-                views.remove(&window_id);
-                if views.is_empty() {
-                    *control_flow = ControlFlow::Exit;
+            let (images, thresholds) =
+                edges(image, operator, sigma, low, high, border(sub_matches)?);
+            let method = if high.is_some() { "" } else { " (otsu)" };
+            match thresholds {
+                Some(EdgeThresholds::Canny { low, high }) => println!(
+                    "Canny thresholds{}: low {:.2}, high {:.2}",
+                    method, low, high
+                ),
+                Some(EdgeThresholds::ZeroCrossing(threshold)) => {
+                    println!("Zero crossing threshold{}: {:.2}", method, threshold)
                 }
+                None => {}
+            }
+            images
+        }
+        "sharpen" => {
+            let defaults = Sharpen::default();
+            let method = sub_matches
+                .get_one::<SharpenMethod>("method")
+                .copied()
+                .unwrap_or_default();
+            let radius = sub_matches
+                .get_one::<String>("radius")
+                .map(|s| parse_radius(s))
+                .transpose()?;
+            if method == SharpenMethod::Laplacian && radius.is_some() {
+                return Err(Error::InvalidArgument(
+                    "--radius does not apply to --method=laplacian".to_string(),
+                ));
+            }
+            let params = Sharpen {
+                method,
+                amount: sub_matches
+                    .get_one::<String>("amount")
+                    .map(|s| parse_positive("amount", s))
+                    .transpose()?
+                    .unwrap_or(defaults.amount),
+                radius: radius.unwrap_or(defaults.radius),
+                threshold: sub_matches
+                    .get_one::<String>("threshold")
+                    .map(|s| parse_threshold_level("detail", s))
+                    .transpose()?
+                    .unwrap_or(defaults.threshold),
+            };
+            let color_space = sub_matches.get_one::<ColorSpace>("color_space").copied();
+            sharpen(
+                image,
+                &params,
+                color_space,
+                color_options(sub_matches),
+                border(sub_matches)?,
+            )
+        }
+        "noise" => {
+            let (params, seed) = noise_params(sub_matches)?;
+            let (images, quality) = noise(image, params, seed);
+            print_quality("noisy", &quality);
+            images
+        }
+        "denoise" => {
+            let (params, seed) = noise_params(sub_matches)?;
+            let methods: Vec<DenoiseMethod> = match sub_matches.get_many::<DenoiseMethod>("methods")
+            {
+                Some(methods) => methods.copied().collect(),
+                None => DenoiseMethod::ALL.to_vec(),
+            };
+            let (images, report) = denoise(image, params, seed, &methods, border(sub_matches)?);
+            println!("Estimated noise deviation: {:.2}", report.sigma);
+            for (name, quality) in &report.quality {
+                print_quality(name, quality);
+            }
+            images
+        }
+        "lut3d" => {
+            let size = sub_matches
+                .get_one::<String>("size")
+                .map(|s| parse_lut_size(s))
+                .transpose()?
+                .unwrap_or(33);
+            let interpolation = sub_matches
+                .get_one::<Interpolation>("interpolation")
+                .copied()
+                .unwrap_or_default();
+            let file = |name: &str| sub_matches.get_one::<String>(name).map(|s| s.as_str());
+            let mut chained = None;
+            let (lut, title) = if let Some(path) = file("cube") {
+                let text = String::from_utf8_lossy(&read_file(path)?).into_owned();
+                (in_file(path, Lut3d::parse_cube(&text))?, path)
+            } else if let Some(path) = file("hald") {
+                let hald = load_image(Some(path))?.to_rgb8();
+                (in_file(path, Lut3d::from_hald(&hald))?, path)
+            } else if let Some(path) = file("acv") {
+                let curves = in_file(path, lut3d::parse_acv(&read_file(path)?))?;
+                (Lut3d::from_lut(&curves, size), path)
+            } else {
+                let chain = file("chain").unwrap();
+                let result = run_chain(chain, &image)?;
+                let lut = Lut3d::fit(&image, &result, size);
+                chained = Some(result);
+                (lut, chain)
+            };
+            if let Some(path) = file("save") {
+                fs::write(path, lut.to_cube(title)).map_err(|source| Error::Io {
+                    path: path.into(),
+                    source,
+                })?;
+                eprintln!("Saved table: {}", path);
+            }
+            let mut images = lut3d(image, &lut, interpolation);
+            if let Some(result) = chained {
+                images.insert(1, NamedImage::new("chain", result));
             }
-            _ => {}
+            images
         }
-    });
+        _ => unreachable!("unknown subcommand {}", name),
+    };
+    Ok(images)
 }
@@ -1,440 +1,1010 @@
-use image::{DynamicImage, GenericImageView, GrayImage, Pixel, Rgba, RgbaImage};
-use imageproc::{
-    drawing::{draw_filled_rect_mut, draw_line_segment_mut},
-    rect::Rect,
-};
+use image::{DynamicImage, GenericImageView, GrayImage, Rgb, RgbImage, Rgba, RgbaImage};
 
-use crate::{
-    alg::{
-        self,
-        color::*,
-        gray::histogram_equalize,
-        gray::{average_gray_level, split_planes},
+use crate::alg::{
+    self,
+    color::{ColorModel, ColorOptions, ColorSpace, Hsv},
+    edge::{self, EdgeOperator},
+    filter::{self as filters, filter_image, rgb_image, rgb_planes, Border, Filter, Kernel},
+    gray::{
+        adaptive_threshold_surface, auto_threshold, equalize_plane, gray_histogram,
+        multi_otsu_thresholds, split_planes, threshold_by_surface, AdaptiveMethod, ThresholdMethod,
     },
-    draw::ImageDrawer,
+    gray::{clahe, clahe_plane, histogram_equalize, ClaheParams},
+    gray::{match_plane, plane_histogram, TargetCurve},
+    hist::{ChannelPair, Histogram, ImageHistogram, JointHistogram},
+    lut::{Levels, Lut},
+    lut3d::{Interpolation, Lut3d},
+    metrics::{channel_error, diverging_color, error_heat_map, ChannelError, Quality},
+    noise::{add_noise, estimate_sigma, DenoiseMethod, Noise},
+    planes::Planes,
+    sharpen::Sharpen,
+};
+use crate::render::curve::render_transfer_curve;
+use crate::render::density::{render_density, render_polar_density, Guide};
+use crate::render::histogram::{
+    hue_level_colors, render_hue_wheel, AxisScale, HistogramChart, HistogramStyle, Series,
 };
 
-pub fn grayscale(image: DynamicImage, color_space: Option<&String>) -> Vec<ImageDrawer> {
-    let mut images = vec![image];
-    let src_image = &images[0];
+const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const GREEN: Rgba<u8> = Rgba([0, 255, 0, 255]);
+const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+/// Band of the gray channel in the charts drawn with the default style.
+const GRAY_BAND: u32 = 3;
+
+/// An image produced by one of the processing functions, together with a short descriptive name
+/// such as `hsv-plane-v`.
+pub struct NamedImage {
+    pub name: String,
+    pub image: DynamicImage,
+}
+
+impl NamedImage {
+    pub fn new(name: impl Into<String>, image: impl Into<DynamicImage>) -> Self {
+        NamedImage {
+            name: name.into(),
+            image: image.into(),
+        }
+    }
+}
+
+pub fn grayscale(
+    image: DynamicImage,
+    color_space: Option<ColorSpace>,
+    options: ColorOptions,
+) -> Vec<NamedImage> {
+    let mut images = vec![NamedImage::new("original", image)];
+    let src_image = &images[0].image;
 
     match color_space {
-        Some(str) => {
-            let (dst_image, recovered) = match str.as_str() {
-                "hsv" => {
-                    let hsv = rgb_to_hsv(&src_image);
-                    let recovered = hsv_to_rgb(&hsv);
-                    (Some(hsv), Some(recovered))
-                }
-                "hsi" => {
-                    let hsi = rgb_to_hsi(&src_image);
-                    let recovered = hsi_to_rgb(&hsi);
-                    (Some(hsi), Some(recovered))
-                }
-                "hsl" => {
-                    let hsl = rgb_to_hsl(&src_image);
-                    let recovered = hsl_to_rgb(&hsl);
-                    (Some(hsl), Some(recovered))
-                }
-                "yuv" => {
-                    let yuv = rgb_to_yuv(&src_image);
-                    let recovered = yuv_to_rgb(&yuv);
-                    (Some(yuv), Some(recovered))
-                }
-                "rgb" => (Some(src_image.clone()), None),
-                _ => {
-                    println!("Unknown color space: {}", str);
-                    (None, None)
+        Some(color_space) => {
+            let (dst_image, planes, recovered) = match color_space.conversion_with(&options) {
+                Some(conversion) => {
+                    let converted = conversion.rgb_to_model(src_image);
+                    let ranges = conversion.ranges();
+                    let planes = (0..3)
+                        .map(|i| converted.encode_plane(i, ranges[i]))
+                        .collect();
+                    let recovered = conversion.model_to_rgb(&converted);
+                    (conversion.encode(&converted), planes, Some(recovered))
                 }
+                None => (src_image.clone(), split_planes(src_image), None),
             };
-            if let Some(dst_image) = dst_image {
-                let planes = alg::gray::split_planes(&dst_image);
-                images.push(DynamicImage::from(dst_image));
-                planes
-                    .into_iter()
-                    .for_each(|p| images.push(DynamicImage::from(p)));
-                if let Some(recovered) = recovered {
-                    images.push(DynamicImage::from(recovered));
-                }
+            let name = color_space.name();
+            images.push(NamedImage::new(name, dst_image));
+            planes
+                .into_iter()
+                .zip(color_space.channels())
+                .for_each(|(p, c)| {
+                    images.push(NamedImage::new(format!("{}-plane-{}", name, c), p))
+                });
+            if let Some(recovered) = recovered {
+                images.push(NamedImage::new(format!("{}-recovered", name), recovered));
             }
         }
         None => {
-            images.push(DynamicImage::from(src_image.to_luma8()));
+            images.push(NamedImage::new("gray", src_image.to_luma8()));
         }
     }
-    images.into_iter().map(|i| ImageDrawer::from(i)).collect()
+    images
 }
 
-pub fn binarize(image: DynamicImage, threshold: Option<u8>) -> Vec<ImageDrawer> {
-    let gray_image = image.to_luma8();
-    let level = match threshold {
-        Some(v) => v,
-        None => average_gray_level(&gray_image),
+/// Converts `image` to `color_space` and back. Returns the original, the recovered image and a heat
+/// map of the largest channel error of every pixel, scaled to the largest error found, together
+/// with the per-channel error of the round trip.
+pub fn roundtrip(
+    image: DynamicImage,
+    color_space: ColorSpace,
+    options: ColorOptions,
+) -> (Vec<NamedImage>, ChannelError) {
+    let recovered = match color_space.conversion_with(&options) {
+        Some(conversion) => conversion.model_to_rgb(&conversion.rgb_to_model(&image)),
+        None => image.clone(),
     };
-    println!("Binary threshold: {}", level);
+    let error = channel_error(&image, &recovered);
+    let full_scale = error.max.into_iter().max().unwrap_or(0);
+    let heat_map = error_heat_map(&image, &recovered, full_scale);
+    let name = color_space.name();
+    let images = vec![
+        NamedImage::new("original", image),
+        NamedImage::new(format!("{}-recovered", name), recovered),
+        NamedImage::new(format!("{}-error", name), heat_map),
+    ];
+    (images, error)
+}
+
+/// Binarizes `image` at `threshold`, or at a level chosen by `method` when no threshold is given.
+/// The output ends with the gray level histogram, marked in red at the threshold, and comes with
+/// the threshold used.
+pub fn binarize(
+    image: DynamicImage,
+    threshold: Option<u8>,
+    method: ThresholdMethod,
+) -> (Vec<NamedImage>, u8) {
+    let gray_image = image.to_luma8();
+    let level = threshold.unwrap_or_else(|| auto_threshold(&gray_image, method));
     let binary_image = alg::gray::threshold(&gray_image, level);
+    let mut histogram = render_gray_histogram(&gray_image, None);
+    histogram.mark_levels(GRAY_BAND, &[level], RED);
+    let images = vec![
+        NamedImage::new("original", image),
+        NamedImage::new("gray", gray_image),
+        NamedImage::new("binary", binary_image),
+        NamedImage::new("histogram", histogram.into_image()),
+    ];
+    (images, level)
+}
+
+/// Binarizes `image` against a threshold computed for every pixel by `method` over a `window` wide
+/// neighborhood, with `k` defaulting to the method's usual value. The output ends with the
+/// threshold surface.
+pub fn binarize_adaptive(
+    image: DynamicImage,
+    method: AdaptiveMethod,
+    window: u32,
+    k: Option<f32>,
+) -> Vec<NamedImage> {
+    let gray_image = image.to_luma8();
+    let k = k.unwrap_or(method.default_k());
+    let surface = adaptive_threshold_surface(&gray_image, method, window, k);
+    let binary_image = threshold_by_surface(&gray_image, &surface);
     vec![
-        ImageDrawer::from(image),
-        ImageDrawer::from(gray_image),
-        ImageDrawer::from(binary_image),
+        NamedImage::new("original", image),
+        NamedImage::new("gray", gray_image),
+        NamedImage::new("binary", binary_image),
+        NamedImage::new("threshold", surface),
     ]
 }
 
-fn create_histogram_canvas() -> RgbaImage {
-    let background = Rgba([255u8, 255u8, 255u8, 255u8]);
-    let gray = Rgba([128u8, 128u8, 128u8, 255u8]);
-    let mut image = RgbaImage::from_pixel(512, 512, background);
-    draw_line_segment_mut(&mut image, (0.0, 127.0), (511.0, 127.0), gray);
-    draw_line_segment_mut(&mut image, (0.0, 255.0), (511.0, 255.0), gray);
-    draw_line_segment_mut(&mut image, (0.0, 383.0), (511.0, 383.0), gray);
-    draw_line_segment_mut(&mut image, (0.0, 511.0), (511.0, 511.0), gray);
-    image
+/// Color of `label` out of `count` labels in a false-color rendering: hues spread over the circle.
+fn label_color(label: usize, count: usize) -> Rgb<u8> {
+    Hsv {
+        h: 360.0 * label as f32 / count as f32,
+        s: 0.8,
+        v: 1.0,
+    }
+    .to_rgb8()
 }
 
-fn draw_histogram_part(
-    canvas: &mut RgbaImage,
-    values: &[u64; 256],
-    scale: u64,
-    vertical_range: (i32, i32),
-    color: Rgba<u8>,
-) {
-    let height = (vertical_range.1 - vertical_range.0) as u64;
-    for (i, v) in values.iter().enumerate() {
-        let x = (i * 2) as i32;
-        let h = ((v * height + (scale / 2)) / scale) as i32;
-        if h > 0 {
-            let rect = Rect::at(x, vertical_range.1 - h).of_size(2, h as u32);
-            draw_filled_rect_mut(canvas, rect, color);
-        }
+/// Stretches labels `0..count` to 0~255 so that the label image can be viewed.
+fn stretch_labels(labels: &GrayImage, count: usize) -> GrayImage {
+    let mut out = labels.clone();
+    for p in out.iter_mut() {
+        *p = (*p as usize * 255 / (count - 1).max(1)) as u8;
     }
+    out
 }
 
-fn draw_histogram_scale_gray(image: &GrayImage, scale: Option<u64>) -> (DynamicImage, u64) {
-    let black = Rgba([0u8, 0u8, 0u8, 255u8]);
-    let mut gray_count = [0u64; 256];
-    for luma in image.pixels() {
-        gray_count[luma.0[0] as usize] += 1;
+/// Splits `image` into classes at `cuts`, or into `levels` classes at the multi-level Otsu cuts
+/// when no cuts are given, either by gray level or on every RGB channel separately. The output
+/// holds the labels stretched to 0~255, a false-color rendering of them and the histogram with the
+/// cuts marked in red, together with the cuts used: one list, or one per RGB channel.
+pub fn quantize(
+    image: DynamicImage,
+    levels: usize,
+    cuts: Option<&[u8]>,
+    per_channel: bool,
+) -> (Vec<NamedImage>, Vec<Vec<u8>>) {
+    let find_cuts = |plane: &GrayImage| match cuts {
+        Some(cuts) => cuts.to_vec(),
+        None => multi_otsu_thresholds(&gray_histogram(plane), levels),
+    };
+
+    if !per_channel {
+        let gray_image = image.to_luma8();
+        let cuts = find_cuts(&gray_image);
+        let count = cuts.len() + 1;
+        let labels = alg::gray::quantize(&gray_image, &cuts);
+        let false_color = RgbImage::from_fn(labels.width(), labels.height(), |x, y| {
+            label_color(labels.get_pixel(x, y).0[0] as usize, count)
+        });
+        let mut histogram = render_gray_histogram(&gray_image, None);
+        histogram.mark_levels(GRAY_BAND, &cuts, RED);
+        let images = vec![
+            NamedImage::new("original", image),
+            NamedImage::new("gray", gray_image),
+            NamedImage::new("labels", stretch_labels(&labels, count)),
+            NamedImage::new("false-color", false_color),
+            NamedImage::new("histogram", histogram.into_image()),
+        ];
+        return (images, vec![cuts]);
     }
-    let scale = if let Some(v) = scale {
-        v
-    } else {
-        gray_count.iter().map(|v| *v).max().unwrap()
+
+    let style = HistogramStyle::default();
+    let mut histogram = render_image_histogram(&ImageHistogram::from_image(&image), &style, None);
+    let mut labels = Vec::new();
+    let mut counts = Vec::new();
+    let mut channel_cuts = Vec::new();
+    for (plane, band) in split_planes(&image).iter().zip(0..) {
+        let cuts = find_cuts(plane);
+        histogram.mark_levels(band, &cuts, RED);
+        counts.push(cuts.len() + 1);
+        labels.push(alg::gray::quantize(plane, &cuts));
+        channel_cuts.push(cuts);
+    }
+
+    let (width, height) = image.dimensions();
+    let stretched: Vec<GrayImage> = labels
+        .iter()
+        .zip(&counts)
+        .map(|(l, &count)| stretch_labels(l, count))
+        .collect();
+    let posterized = RgbImage::from_fn(width, height, |x, y| {
+        Rgb(std::array::from_fn(|c| stretched[c].get_pixel(x, y).0[0]))
+    });
+    // Every combination of the channel labels is a class of its own.
+    let false_color = RgbImage::from_fn(width, height, |x, y| {
+        let label = (0..3).rev().fold(0, |acc, c| {
+            acc * counts[c] + labels[c].get_pixel(x, y).0[0] as usize
+        });
+        label_color(label, counts.iter().product())
+    });
+    let images = vec![
+        NamedImage::new("original", image),
+        NamedImage::new("labels", posterized),
+        NamedImage::new("false-color", false_color),
+        NamedImage::new("histogram", histogram.into_image()),
+    ];
+    (images, channel_cuts)
+}
+
+/// Renders the four channels of `hist` in `style`.
+fn render_image_histogram(
+    hist: &ImageHistogram,
+    style: &HistogramStyle,
+    scale: Option<u64>,
+) -> HistogramChart {
+    let series = [
+        ("red", &hist.red, RED),
+        ("green", &hist.green, GREEN),
+        ("blue", &hist.blue, BLUE),
+        ("gray", &hist.gray, BLACK),
+    ]
+    .map(|(name, h, color)| Series::new(name, h.counts(), color));
+    HistogramChart::render(&series, style, scale)
+}
+
+/// Renders the gray level histogram in the gray band of the default chart, so that it lines up
+/// with the charts of color images.
+fn render_gray_histogram(image: &GrayImage, scale: Option<u64>) -> HistogramChart {
+    let hist = ImageHistogram {
+        red: Histogram::from_counts([0; 256]),
+        green: Histogram::from_counts([0; 256]),
+        blue: Histogram::from_counts([0; 256]),
+        gray: Histogram::from_gray(image),
     };
+    render_image_histogram(&hist, &HistogramStyle::default(), scale)
+}
 
-    let mut canvas = create_histogram_canvas();
-    draw_histogram_part(&mut canvas, &gray_count, scale, (384, 511), black);
-    (DynamicImage::from(canvas), scale)
+fn draw_histogram_scale_gray(image: &GrayImage, scale: Option<u64>) -> (DynamicImage, u64) {
+    let chart = render_gray_histogram(image, scale);
+    let scale = chart.scale;
+    (chart.into_image(), scale)
+}
+
+fn draw_image_histogram(hist: &ImageHistogram, scale: Option<u64>) -> (DynamicImage, u64) {
+    let chart = render_image_histogram(hist, &HistogramStyle::default(), scale);
+    let scale = chart.scale;
+    (chart.into_image(), scale)
 }
 
 fn draw_histogram_scale(image: &DynamicImage, scale: Option<u64>) -> (DynamicImage, u64) {
-    let black = Rgba([0u8, 0u8, 0u8, 255u8]);
-    let red = Rgba([255u8, 0u8, 0u8, 255u8]);
-    let green = Rgba([0u8, 255u8, 0u8, 255u8]);
-    let blue = Rgba([0u8, 0u8, 255u8, 255u8]);
-
-    let mut gray_count = [0u64; 256];
-    let mut red_count = [0u64; 256];
-    let mut green_count = [0u64; 256];
-    let mut blue_count = [0u64; 256];
-    for (_, _, pixel) in image.pixels() {
-        let luma = pixel.to_luma();
-        gray_count[luma.0[0] as usize] += 1;
-        red_count[pixel.0[0] as usize] += 1;
-        green_count[pixel.0[1] as usize] += 1;
-        blue_count[pixel.0[2] as usize] += 1;
-    }
-    let scale = if let Some(v) = scale {
-        v
-    } else {
-        let max = [
-            gray_count.iter().map(|v| *v).max().unwrap(),
-            red_count.iter().map(|v| *v).max().unwrap(),
-            green_count.iter().map(|v| *v).max().unwrap(),
-            blue_count.iter().map(|v| *v).max().unwrap(),
-        ]
-        .into_iter()
-        .max()
-        .unwrap();
-        max
+    draw_image_histogram(&ImageHistogram::from_image(image), scale)
+}
+
+/// Colors of the bars of the planes that are neither hue nor intensity, in plane order.
+const PLANE_COLORS: [Rgba<u8>; 2] = [Rgba([0, 150, 150, 255]), Rgba([150, 0, 150, 255])];
+
+/// Shows the histogram drawn in `style` of the red, green, blue and gray channels of `image`, or of
+/// the planes of `color_space`, each labeled with its range. The bars of a hue plane take the hue
+/// they count and the hue is also drawn around a wheel; gray pixels are left out of both.
+pub fn histogram(
+    image: DynamicImage,
+    color_space: Option<ColorSpace>,
+    options: ColorOptions,
+    style: &HistogramStyle,
+) -> Vec<NamedImage> {
+    let conversion = color_space.and_then(|space| space.conversion_with(&options));
+    let (Some(color_space), Some(conversion)) = (color_space, conversion) else {
+        let gray_image = image.to_luma8();
+        let chart = render_image_histogram(&ImageHistogram::from_image(&image), style, None);
+        return vec![
+            NamedImage::new("original", image),
+            NamedImage::new("gray", gray_image),
+            NamedImage::new("histogram-original", chart.into_image()),
+        ];
     };
 
-    let mut canvas = create_histogram_canvas();
-    draw_histogram_part(&mut canvas, &red_count, scale, (0, 127), red);
-    draw_histogram_part(&mut canvas, &green_count, scale, (128, 255), green);
-    draw_histogram_part(&mut canvas, &blue_count, scale, (256, 383), blue);
-    draw_histogram_part(&mut canvas, &gray_count, scale, (384, 511), black);
+    let converted = conversion.rgb_to_model(&image);
+    let ranges = conversion.ranges();
+    let channels = conversion.channels();
+    let hue = channels.iter().position(|&c| c == 'h');
+    // Saturation or chroma is the second plane of every model with a hue.
+    let hists: Vec<Histogram> = (0..3)
+        .map(|i| match hue {
+            Some(h) if h == i => {
+                Histogram::from_hue(converted.plane(i), ranges[i], converted.plane(1), ranges[1])
+            }
+            _ => Histogram::from_plane(converted.plane(i), ranges[i]),
+        })
+        .collect();
+    let names: Vec<String> = channels
+        .iter()
+        .zip(ranges)
+        .map(|(c, (lo, hi))| format!("{} {}~{}", c, lo, hi))
+        .collect();
+    let hue_colors = hue_level_colors();
+    let mut others = PLANE_COLORS.iter();
+    let series: Vec<Series> = (0..3)
+        .map(|i| {
+            let series = Series::new(&names[i], hists[i].counts(), BLACK);
+            if Some(i) == hue {
+                series.with_level_colors(&hue_colors)
+            } else if i == conversion.intensity() {
+                series
+            } else {
+                Series {
+                    color: *others.next().unwrap(),
+                    ..series
+                }
+            }
+        })
+        .collect();
+    let chart = HistogramChart::render(&series, style, None);
 
-    (DynamicImage::from(canvas), scale)
+    let name = color_space.name();
+    let mut images = vec![
+        NamedImage::new("original", image),
+        NamedImage::new(format!("histogram-{}", name), chart.into_image()),
+    ];
+    if let Some(h) = hue {
+        let size = style.width.min(style.height);
+        let wheel = render_hue_wheel(hists[h].counts(), size, style.scale);
+        images.push(NamedImage::new(format!("histogram-{}-hue", name), wheel));
+    }
+    images
 }
 
-pub fn histogram(image: DynamicImage) -> Vec<ImageDrawer> {
-    let gray_image = image.to_luma8();
-    let (hist_original, _) = draw_histogram_scale(&image, None);
+/// Size of the hue–saturation wheel drawn by `histogram2d`, about one pixel per saturation bin.
+const POLAR_DENSITY_SIZE: u32 = 516;
 
-    vec![
-        ImageDrawer::from(image),
-        ImageDrawer::from(gray_image),
-        ImageDrawer::from(hist_original),
-    ]
+/// Shows the joint distribution of every pair of `pairs` as a density image with counts mapped
+/// through `scale`. Hue–saturation is drawn as a wheel, the other pairs as heat maps with the
+/// neutral colors marked.
+pub fn histogram2d(
+    image: DynamicImage,
+    pairs: &[ChannelPair],
+    options: ColorOptions,
+    scale: AxisScale,
+) -> Vec<NamedImage> {
+    let mut images = Vec::new();
+    for &pair in pairs {
+        let hist = JointHistogram::from_image(&image, pair, options);
+        let density = match pair {
+            ChannelPair::Rg => render_density(&hist, scale, Guide::Diagonal),
+            ChannelPair::CbCr | ChannelPair::Ab => render_density(&hist, scale, Guide::Cross),
+            ChannelPair::Hs => render_polar_density(&hist, POLAR_DENSITY_SIZE, scale),
+        };
+        images.push(NamedImage::new(format!("histogram2d-{}", pair), density));
+    }
+    images.insert(0, NamedImage::new("original", image));
+    images
 }
 
-fn equalize_grayscale_luma(image: DynamicImage) -> Vec<ImageDrawer> {
-    let grayscale = image.to_luma8();
-    let equalized = histogram_equalize(&grayscale);
-    let (hist_original, scale) = draw_histogram_scale(&image, None);
-    let hist_equalized = draw_histogram_scale_gray(&equalized, Some(scale)).0;
+/// Equalizes a grayscale image globally, or with CLAHE when `adaptive` is given.
+fn equalize_gray(image: &GrayImage, adaptive: Option<ClaheParams>) -> GrayImage {
+    match adaptive {
+        Some(params) => clahe(image, params),
+        None => histogram_equalize(image),
+    }
+}
 
-    vec![
-        ImageDrawer::from(image),
-        ImageDrawer::from(grayscale),
-        ImageDrawer::from(equalized),
-        ImageDrawer::from(hist_original),
-        ImageDrawer::from(hist_equalized),
-    ]
+/// Equalizes plane `index` of `planes` globally, or with CLAHE when `adaptive` is given.
+fn equalize_model_plane(
+    planes: &mut Planes<f32, 3>,
+    index: usize,
+    range: (f32, f32),
+    adaptive: Option<ClaheParams>,
+) {
+    let (width, height) = (planes.width(), planes.height());
+    let plane = planes.plane_mut(index);
+    match adaptive {
+        Some(params) => clahe_plane(plane, width, height, range, params),
+        None => equalize_plane(plane, range),
+    }
 }
 
-fn equalize_grayscale_value(image: DynamicImage) -> Vec<ImageDrawer> {
-    let hsv = rgb_to_hsv(&image);
-    let planes = split_planes(&hsv);
-    let grayscale = planes[2].clone();
-    let equalized = histogram_equalize(&grayscale);
+fn equalize_grayscale_luma(image: DynamicImage, adaptive: Option<ClaheParams>) -> Vec<NamedImage> {
+    let grayscale = image.to_luma8();
+    let equalized = equalize_gray(&grayscale, adaptive);
     let (hist_original, scale) = draw_histogram_scale(&image, None);
-    let hist_grayscale = draw_histogram_scale_gray(&grayscale, Some(scale)).0;
     let hist_equalized = draw_histogram_scale_gray(&equalized, Some(scale)).0;
 
     vec![
-        ImageDrawer::from(image),
-        ImageDrawer::from(grayscale),
-        ImageDrawer::from(equalized),
-        ImageDrawer::from(hist_original),
-        ImageDrawer::from(hist_grayscale),
-        ImageDrawer::from(hist_equalized),
+        NamedImage::new("original", image),
+        NamedImage::new("gray", grayscale),
+        NamedImage::new("equalized", equalized),
+        NamedImage::new("histogram-original", hist_original),
+        NamedImage::new("histogram-equalized", hist_equalized),
     ]
 }
 
-fn equalize_grayscale_lightness(image: DynamicImage) -> Vec<ImageDrawer> {
-    let hsl = rgb_to_hsl(&image);
-    let planes = split_planes(&hsl);
-    let grayscale = planes[2].clone();
-    let equalized = histogram_equalize(&grayscale);
+fn equalize_grayscale_plane(
+    image: DynamicImage,
+    color_space: ColorSpace,
+    options: ColorOptions,
+    adaptive: Option<ClaheParams>,
+) -> Vec<NamedImage> {
+    let conversion = color_space.conversion_with(&options).unwrap();
+    let index = conversion.intensity();
+    let range = conversion.ranges()[index];
+    let name = format!("{}-plane-{}", color_space, conversion.channels()[index]);
+    let mut converted = conversion.rgb_to_model(&image);
+    let grayscale = converted.encode_plane(index, range);
+    equalize_model_plane(&mut converted, index, range, adaptive);
+    let equalized = converted.encode_plane(index, range);
     let (hist_original, scale) = draw_histogram_scale(&image, None);
     let hist_grayscale = draw_histogram_scale_gray(&grayscale, Some(scale)).0;
     let hist_equalized = draw_histogram_scale_gray(&equalized, Some(scale)).0;
 
     vec![
-        ImageDrawer::from(image),
-        ImageDrawer::from(grayscale),
-        ImageDrawer::from(equalized),
-        ImageDrawer::from(hist_original),
-        ImageDrawer::from(hist_grayscale),
-        ImageDrawer::from(hist_equalized),
+        NamedImage::new("original", image),
+        NamedImage::new(name.as_str(), grayscale),
+        NamedImage::new("equalized", equalized),
+        NamedImage::new("histogram-original", hist_original),
+        NamedImage::new(format!("histogram-{}", name), hist_grayscale),
+        NamedImage::new("histogram-equalized", hist_equalized),
     ]
 }
 
-fn equalize_grayscale_intensity(image: DynamicImage) -> Vec<ImageDrawer> {
-    let hsi = rgb_to_hsi(&image);
-    let planes = split_planes(&hsi);
-    let grayscale = planes[2].clone();
-    let equalized = histogram_equalize(&grayscale);
+fn equalize_color_plane(
+    image: DynamicImage,
+    color_space: ColorSpace,
+    options: ColorOptions,
+    adaptive: Option<ClaheParams>,
+) -> Vec<NamedImage> {
+    let conversion = color_space.conversion_with(&options).unwrap();
+    let index = conversion.intensity();
+    let mut converted = conversion.rgb_to_model(&image);
+    let range = conversion.ranges()[index];
+    equalize_model_plane(&mut converted, index, range, adaptive);
+    let equalized = conversion.model_to_rgb(&converted);
     let (hist_original, scale) = draw_histogram_scale(&image, None);
-    let hist_grayscale = draw_histogram_scale_gray(&grayscale, Some(scale)).0;
-    let hist_equalized = draw_histogram_scale_gray(&equalized, Some(scale)).0;
+    let hist_equalized = draw_histogram_scale(&equalized, Some(scale)).0;
 
     vec![
-        ImageDrawer::from(image),
-        ImageDrawer::from(grayscale),
-        ImageDrawer::from(equalized),
-        ImageDrawer::from(hist_original),
-        ImageDrawer::from(hist_grayscale),
-        ImageDrawer::from(hist_equalized),
+        NamedImage::new("original", image),
+        NamedImage::new("equalized", equalized),
+        NamedImage::new("histogram-original", hist_original),
+        NamedImage::new("histogram-equalized", hist_equalized),
     ]
 }
 
-fn equalize_color_hsv(image: DynamicImage) -> Vec<ImageDrawer> {
-    let hsv = rgb_to_hsv(&image);
-    let planes = split_planes(&hsv);
-    let grayscale = histogram_equalize(&planes[2]);
-    let mut equalized = RgbaImage::new(hsv.width(), hsv.height());
-    for (x, y, mut pixel) in hsv.pixels() {
-        pixel.0[2] = grayscale.get_pixel(x, y).0[0];
+fn equalize_color_rgb(image: DynamicImage, adaptive: Option<ClaheParams>) -> Vec<NamedImage> {
+    let planes = split_planes(&image);
+    let red_plane = equalize_gray(&planes[0], adaptive);
+    let green_plane = equalize_gray(&planes[1], adaptive);
+    let blue_plane = equalize_gray(&planes[2], adaptive);
+    let mut equalized = RgbaImage::new(image.width(), image.height());
+    for (x, y, mut pixel) in image.pixels() {
+        pixel.0[0] = red_plane.get_pixel(x, y).0[0];
+        pixel.0[1] = green_plane.get_pixel(x, y).0[0];
+        pixel.0[2] = blue_plane.get_pixel(x, y).0[0];
         equalized.put_pixel(x, y, pixel);
     }
-    let equalized = hsv_to_rgb(&equalized.into());
+    let equalized = DynamicImage::from(equalized);
     let (hist_original, scale) = draw_histogram_scale(&image, None);
     let hist_equalized = draw_histogram_scale(&equalized, Some(scale)).0;
 
     vec![
-        ImageDrawer::from(image),
-        ImageDrawer::from(equalized),
-        ImageDrawer::from(hist_original),
-        ImageDrawer::from(hist_equalized),
+        NamedImage::new("original", image),
+        NamedImage::new("equalized", equalized),
+        NamedImage::new("histogram-original", hist_original),
+        NamedImage::new("histogram-equalized", hist_equalized),
     ]
 }
 
-fn equalize_color_hsi(image: DynamicImage) -> Vec<ImageDrawer> {
-    let hsv = rgb_to_hsi(&image);
-    let planes = split_planes(&hsv);
-    let value_plane = histogram_equalize(&planes[2]);
-    let mut equalized = RgbaImage::new(hsv.width(), hsv.height());
-    for (x, y, mut pixel) in hsv.pixels() {
-        pixel.0[2] = value_plane.get_pixel(x, y).0[0];
-        equalized.put_pixel(x, y, pixel);
+/// Equalizes the histogram of the image, globally or with CLAHE when `adaptive` is given. Without
+/// a color space, `grayscale_only` works on the luma of the image and color images are equalized
+/// on the intensity plane of HSI.
+pub fn equalize(
+    image: DynamicImage,
+    grayscale_only: bool,
+    color_space: Option<ColorSpace>,
+    options: ColorOptions,
+    adaptive: Option<ClaheParams>,
+) -> Vec<NamedImage> {
+    if grayscale_only {
+        match color_space {
+            Some(ColorSpace::Rgb) | None => equalize_grayscale_luma(image, adaptive),
+            Some(color_space) => equalize_grayscale_plane(image, color_space, options, adaptive),
+        }
+    } else {
+        match color_space.unwrap_or(ColorSpace::Hsi) {
+            ColorSpace::Rgb => equalize_color_rgb(image, adaptive),
+            color_space => equalize_color_plane(image, color_space, options, adaptive),
+        }
+    }
+}
+
+/// What `match_histogram` shapes the histogram of an image after.
+pub enum MatchTarget {
+    /// The histogram of a reference image.
+    Image(DynamicImage),
+    /// A target curve.
+    Curve(TargetCurve),
+}
+
+/// Matches the histogram of `image` to `target`, on every RGB channel or, given a color space, on
+/// its intensity plane. The three histograms of the source, the target and the result share one
+/// scale.
+pub fn match_histogram(
+    image: DynamicImage,
+    target: MatchTarget,
+    color_space: Option<ColorSpace>,
+    options: ColorOptions,
+) -> Vec<NamedImage> {
+    let as_weights = |hist: [u64; 256]| hist.map(|n| n as f64);
+    let curve = match &target {
+        MatchTarget::Image(_) => None,
+        MatchTarget::Curve(curve) => Some(curve.histogram()),
+    };
+
+    let matched = match color_space.and_then(|c| c.conversion_with(&options)) {
+        None => {
+            let references = match &target {
+                MatchTarget::Image(reference) => split_planes(reference)
+                    .iter()
+                    .map(|p| as_weights(gray_histogram(p)))
+                    .collect(),
+                MatchTarget::Curve(_) => vec![curve.unwrap(); 3],
+            };
+            let planes: Vec<GrayImage> = split_planes(&image)
+                .iter()
+                .zip(&references)
+                .map(|(p, r)| alg::gray::match_histogram(p, r))
+                .collect();
+            let mut matched = image.to_rgba8();
+            for (x, y, pixel) in matched.enumerate_pixels_mut() {
+                for (sample, plane) in pixel.0.iter_mut().zip(&planes) {
+                    *sample = plane.get_pixel(x, y).0[0];
+                }
+            }
+            DynamicImage::from(matched)
+        }
+        Some(conversion) => {
+            let index = conversion.intensity();
+            let range = conversion.ranges()[index];
+            let reference = match &target {
+                MatchTarget::Image(reference) => {
+                    let converted = conversion.rgb_to_model(reference);
+                    as_weights(plane_histogram(converted.plane(index), range))
+                }
+                MatchTarget::Curve(_) => curve.unwrap(),
+            };
+            let mut converted = conversion.rgb_to_model(&image);
+            match_plane(converted.plane_mut(index), range, &reference);
+            conversion.model_to_rgb(&converted)
+        }
+    };
+
+    let source_hist = ImageHistogram::from_image(&image);
+    let matched_hist = ImageHistogram::from_image(&matched);
+    let target_hist = match &target {
+        MatchTarget::Image(reference) => ImageHistogram::from_image(reference),
+        MatchTarget::Curve(_) => {
+            // Spread the pixels of the source over the curve, the same on every channel.
+            let weights = curve.unwrap();
+            let total: f64 = weights.iter().sum();
+            let len = image.width() as f64 * image.height() as f64;
+            let counts = Histogram::from_counts(weights.map(|w| (w / total * len).round() as u64));
+            ImageHistogram {
+                red: counts.clone(),
+                green: counts.clone(),
+                blue: counts.clone(),
+                gray: counts,
+            }
+        }
+    };
+    let scale = [&source_hist, &target_hist, &matched_hist]
+        .iter()
+        .map(|h| h.max_count())
+        .max()
+        .unwrap()
+        .max(1);
+
+    let mut images = vec![NamedImage::new("source", image)];
+    if let MatchTarget::Image(reference) = target {
+        images.push(NamedImage::new("reference", reference));
     }
-    let equalized = hsi_to_rgb(&equalized.into());
+    images.push(NamedImage::new("matched", matched));
+    images.push(NamedImage::new(
+        "histogram-source",
+        draw_image_histogram(&source_hist, Some(scale)).0,
+    ));
+    images.push(NamedImage::new(
+        "histogram-reference",
+        draw_image_histogram(&target_hist, Some(scale)).0,
+    ));
+    images.push(NamedImage::new(
+        "histogram-matched",
+        draw_image_histogram(&matched_hist, Some(scale)).0,
+    ));
+    images
+}
+
+pub fn invert(image: DynamicImage) -> Vec<NamedImage> {
+    let inverse = Lut::invert().apply(&image);
     let (hist_original, scale) = draw_histogram_scale(&image, None);
-    let hist_equalized = draw_histogram_scale(&equalized, Some(scale)).0;
+    let hist_inverse = draw_histogram_scale(&inverse, Some(scale)).0;
 
     vec![
-        ImageDrawer::from(image),
-        ImageDrawer::from(equalized),
-        ImageDrawer::from(hist_original),
-        ImageDrawer::from(hist_equalized),
+        NamedImage::new("original", image),
+        NamedImage::new("inverted", inverse),
+        NamedImage::new("histogram-original", hist_original),
+        NamedImage::new("histogram-inverted", hist_inverse),
     ]
 }
 
-fn equalize_color_hsl(image: DynamicImage) -> Vec<ImageDrawer> {
-    let hsv = rgb_to_hsl(&image);
-    let planes = split_planes(&hsv);
-    let value_plane = histogram_equalize(&planes[2]);
-    let mut equalized = RgbaImage::new(hsv.width(), hsv.height());
-    for (x, y, mut pixel) in hsv.pixels() {
-        pixel.0[2] = value_plane.get_pixel(x, y).0[0];
-        equalized.put_pixel(x, y, pixel);
-    }
-    let equalized = hsl_to_rgb(&equalized.into());
+/// Size of the transfer curve plots.
+const CURVE_SIZE: u32 = 512;
+
+/// Maps `image` through `lut`. The output holds the result called `name`, the histograms before and
+/// after on a common scale and the plot of the transfer curve.
+fn apply_lut(image: DynamicImage, lut: &Lut, name: &str) -> Vec<NamedImage> {
+    let mapped = lut.apply(&image);
     let (hist_original, scale) = draw_histogram_scale(&image, None);
-    let hist_equalized = draw_histogram_scale(&equalized, Some(scale)).0;
+    let hist_mapped = draw_histogram_scale(&mapped, Some(scale)).0;
 
     vec![
-        ImageDrawer::from(image),
-        ImageDrawer::from(equalized),
-        ImageDrawer::from(hist_original),
-        ImageDrawer::from(hist_equalized),
+        NamedImage::new("original", image),
+        NamedImage::new(name, mapped),
+        NamedImage::new("histogram-original", hist_original),
+        NamedImage::new(format!("histogram-{}", name), hist_mapped),
+        NamedImage::new("curve", render_transfer_curve(lut, CURVE_SIZE)),
     ]
 }
 
-fn equalize_color_yuv(image: DynamicImage) -> Vec<ImageDrawer> {
-    let hsv = rgb_to_yuv(&image);
-    let planes = split_planes(&hsv);
-    let value_plane = histogram_equalize(&planes[0]);
-    let mut equalized = RgbaImage::new(hsv.width(), hsv.height());
-    for (x, y, mut pixel) in hsv.pixels() {
-        pixel.0[0] = value_plane.get_pixel(x, y).0[0];
-        equalized.put_pixel(x, y, pixel);
-    }
-    let equalized = yuv_to_rgb(&equalized.into());
+/// Applies gamma correction with exponent `1 / value` to every channel.
+pub fn gamma(image: DynamicImage, value: f32) -> Vec<NamedImage> {
+    apply_lut(image, &Lut::gamma(value), "gamma")
+}
+
+/// Remaps every channel from the input range of `levels` to its output range, with its midtone
+/// gamma.
+pub fn levels(image: DynamicImage, levels: &Levels) -> Vec<NamedImage> {
+    apply_lut(image, &Lut::levels(levels), "levels")
+}
+
+/// Maps every channel through the monotone spline through `points` of input and output levels.
+pub fn curves(image: DynamicImage, points: &[(u8, u8)]) -> Vec<NamedImage> {
+    apply_lut(image, &Lut::curve(points), "curves")
+}
+
+/// Maps the colors of `image` through the 3D `lut`, with the histograms before and after on a
+/// common scale.
+pub fn lut3d(image: DynamicImage, lut: &Lut3d, interpolation: Interpolation) -> Vec<NamedImage> {
+    let mapped = lut.apply(&image, interpolation);
     let (hist_original, scale) = draw_histogram_scale(&image, None);
-    let hist_equalized = draw_histogram_scale(&equalized, Some(scale)).0;
+    let hist_mapped = draw_histogram_scale(&mapped, Some(scale)).0;
 
     vec![
-        ImageDrawer::from(image),
-        ImageDrawer::from(equalized),
-        ImageDrawer::from(hist_original),
-        ImageDrawer::from(hist_equalized),
+        NamedImage::new("original", image),
+        NamedImage::new("lut3d", mapped),
+        NamedImage::new("histogram-original", hist_original),
+        NamedImage::new("histogram-lut3d", hist_mapped),
     ]
 }
 
-fn equalize_color_rgb(image: DynamicImage) -> Vec<ImageDrawer> {
-    let planes = split_planes(&image);
-    let red_plane = histogram_equalize(&planes[0]);
-    let green_plane = histogram_equalize(&planes[1]);
-    let blue_plane = histogram_equalize(&planes[2]);
-    let mut equalized = RgbaImage::new(image.width(), image.height());
-    for (x, y, mut pixel) in image.pixels() {
-        pixel.0[0] = red_plane.get_pixel(x, y).0[0];
-        pixel.0[1] = green_plane.get_pixel(x, y).0[0];
-        pixel.0[2] = blue_plane.get_pixel(x, y).0[0];
-        equalized.put_pixel(x, y, pixel);
-    }
-    let equalized = DynamicImage::from(equalized);
-    let (hist_original, scale) = draw_histogram_scale(&image, None);
-    let hist_equalized = draw_histogram_scale(&equalized, Some(scale)).0;
+/// Applies the neighborhood `filter` to the color channels of `image`. The output holds the
+/// filtered image and a heat map of the largest channel difference of every pixel, white at the
+/// largest difference.
+pub fn filter(image: DynamicImage, filter: &Filter, border: Border) -> Vec<NamedImage> {
+    let filtered = filter_image(&image, filter, border);
+    let full_scale = channel_error(&image, &filtered)
+        .max
+        .into_iter()
+        .max()
+        .unwrap_or(0);
+    let difference = error_heat_map(&image, &filtered, full_scale);
 
     vec![
-        ImageDrawer::from(image),
-        ImageDrawer::from(equalized),
-        ImageDrawer::from(hist_original),
-        ImageDrawer::from(hist_equalized),
+        NamedImage::new("original", image),
+        NamedImage::new("filtered", filtered),
+        NamedImage::new("difference", difference),
     ]
 }
 
-pub fn equalize(
+/// Draws a signed `plane` on a blue-white-red scale, white at 0 and full color at the largest
+/// magnitude.
+fn signed_map(plane: &[f32], width: u32, height: u32) -> RgbImage {
+    let peak = plane.iter().fold(0.0f32, |m, v| m.max(v.abs()));
+    let scale = if peak > 0.0 { 1.0 / peak } else { 0.0 };
+    RgbImage::from_fn(width, height, |x, y| {
+        diverging_color(plane[y as usize * width as usize + x as usize] * scale)
+    })
+}
+
+/// Convolves the gray levels of `image`, or with `per_channel` every color channel, with `kernel`,
+/// then divides by `divisor` and adds `offset`. The output holds the result clipped to 0~255 and
+/// the signed response before the offset, on a diverging scale for every convolved channel.
+pub fn convolve(
     image: DynamicImage,
-    grayscale_only: bool,
-    color_space: Option<&String>,
-) -> Vec<ImageDrawer> {
-    match color_space {
-        Some(str) => match str.as_str() {
-            "hsv" => {
-                if grayscale_only {
-                    equalize_grayscale_value(image)
-                } else {
-                    equalize_color_hsv(image)
-                }
-            }
-            "hsi" => {
-                if grayscale_only {
-                    equalize_grayscale_intensity(image)
-                } else {
-                    equalize_color_hsi(image)
-                }
-            }
-            "hsl" => {
-                if grayscale_only {
-                    equalize_grayscale_lightness(image)
-                } else {
-                    equalize_color_hsl(image)
-                }
-            }
-            "yuv" => {
-                if grayscale_only {
-                    equalize_grayscale_luma(image)
-                } else {
-                    equalize_color_yuv(image)
-                }
+    kernel: &Kernel,
+    divisor: f32,
+    offset: f32,
+    border: Border,
+    per_channel: bool,
+) -> Vec<NamedImage> {
+    let (width, height) = image.dimensions();
+    if per_channel {
+        let mut response = filters::convolve(&rgb_planes(&image), kernel, border);
+        for c in 0..3 {
+            response.plane_mut(c).iter_mut().for_each(|v| *v /= divisor);
+        }
+        let mut shifted = response.clone();
+        for c in 0..3 {
+            shifted.plane_mut(c).iter_mut().for_each(|v| *v += offset);
+        }
+        let convolved = rgb_image(&shifted, &image);
+        let mut images = vec![
+            NamedImage::new("original", image),
+            NamedImage::new("convolved", convolved),
+        ];
+        for (c, channel) in ['r', 'g', 'b'].iter().enumerate() {
+            let signed = signed_map(response.plane(c), width, height);
+            images.push(NamedImage::new(format!("signed-{}", channel), signed));
+        }
+        images
+    } else {
+        let gray = image.to_luma8();
+        let mut planes: Planes<f32, 1> = Planes::new(width, height);
+        for (sample, &p) in planes.plane_mut(0).iter_mut().zip(gray.iter()) {
+            *sample = p as f32;
+        }
+        let mut response = filters::convolve(&planes, kernel, border);
+        response.plane_mut(0).iter_mut().for_each(|v| *v /= divisor);
+        let levels = response
+            .plane(0)
+            .iter()
+            .map(|&v| (v + offset).round().clamp(0.0, 255.0) as u8)
+            .collect();
+        let convolved = GrayImage::from_raw(width, height, levels).unwrap();
+        let signed = signed_map(response.plane(0), width, height);
+        vec![
+            NamedImage::new("original", image),
+            NamedImage::new("gray", gray),
+            NamedImage::new("convolved", convolved),
+            NamedImage::new("signed", signed),
+        ]
+    }
+}
+
+/// Gray image of `values` scaled so that the 99th percentile, or the largest value if it is 0,
+/// shows as white.
+fn scaled_gray(values: &[f32], width: u32, height: u32) -> (GrayImage, f32) {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable_by(f32::total_cmp);
+    let percentile = sorted.get(sorted.len() * 99 / 100).copied().unwrap_or(0.0);
+    let full = if percentile > 0.0 {
+        percentile
+    } else {
+        sorted.last().copied().unwrap_or(0.0)
+    };
+    let scale = if full > 0.0 { 255.0 / full } else { 0.0 };
+    let levels = values
+        .iter()
+        .map(|&v| (v * scale).round().clamp(0.0, 255.0) as u8)
+        .collect();
+    (GrayImage::from_raw(width, height, levels).unwrap(), full)
+}
+
+/// Gradient magnitude as gray levels and gradient direction as hue, as bright as the magnitude.
+fn gradient_images(gradient: &Planes<f32, 2>) -> (GrayImage, RgbImage) {
+    let (width, height) = (gradient.width(), gradient.height());
+    let magnitude = edge::magnitude(gradient);
+    let direction = edge::direction(gradient);
+    let (magnitude_image, full) = scaled_gray(&magnitude, width, height);
+    let scale = if full > 0.0 { 1.0 / full } else { 0.0 };
+    let direction_image = RgbImage::from_fn(width, height, |x, y| {
+        let i = y as usize * width as usize + x as usize;
+        let hsv = Hsv {
+            h: direction[i],
+            s: 1.0,
+            v: (magnitude[i] * scale).min(1.0),
+        };
+        hsv.to_rgb8()
+    });
+    (magnitude_image, direction_image)
+}
+
+/// Thresholds that `edges` applied to the Canny gradient or the LoG response.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EdgeThresholds {
+    /// Hysteresis thresholds on the gradient magnitude.
+    Canny { low: f32, high: f32 },
+    /// Smallest step of the LoG response across a zero crossing.
+    ZeroCrossing(f32),
+}
+
+/// Finds the edges of the gray levels of `image` with `operator`, after smoothing by a gaussian of
+/// deviation `sigma` (1.4 for Canny and 2 for LoG by default, none for the gradient operators).
+/// `low` and `high` are the hysteresis thresholds of Canny, and `high` the smallest step of the
+/// zero crossings of LoG, derived by Otsu's method when missing. The output holds the gradient
+/// magnitude and its direction as hue, or the signed LoG response, and the edge map, together with
+/// the thresholds used by Canny and LoG.
+pub fn edges(
+    image: DynamicImage,
+    operator: EdgeOperator,
+    sigma: Option<f32>,
+    low: Option<f32>,
+    high: Option<f32>,
+    border: Border,
+) -> (Vec<NamedImage>, Option<EdgeThresholds>) {
+    let gray = image.to_luma8();
+    let (width, height) = gray.dimensions();
+    let mut plane: Planes<f32, 1> = Planes::new(width, height);
+    for (sample, &p) in plane.plane_mut(0).iter_mut().zip(gray.iter()) {
+        *sample = p as f32;
+    }
+    let mut images = vec![NamedImage::new("original", image)];
+    match operator {
+        EdgeOperator::Canny => {
+            let result = edge::canny(&gray, sigma.unwrap_or(1.4), low, high, border);
+            let (magnitude, direction) = gradient_images(&result.gradient);
+            images.push(NamedImage::new("gray", gray));
+            images.push(NamedImage::new("magnitude", magnitude));
+            images.push(NamedImage::new("direction", direction));
+            images.push(NamedImage::new("edges", result.edges));
+            let thresholds = EdgeThresholds::Canny {
+                low: result.low,
+                high: result.high,
+            };
+            (images, Some(thresholds))
+        }
+        EdgeOperator::Log => {
+            let response = edge::log_response(&plane, sigma.unwrap_or(2.0), border);
+            let (edges, threshold) = edge::zero_crossings(&response, width, height, high);
+            images.push(NamedImage::new("gray", gray));
+            images.push(NamedImage::new("log", signed_map(&response, width, height)));
+            images.push(NamedImage::new("edges", edges));
+            (images, Some(EdgeThresholds::ZeroCrossing(threshold)))
+        }
+        _ => {
+            if let Some(sigma) = sigma {
+                plane = filters::gaussian_blur(&plane, (3.0 * sigma).ceil() as u32, sigma, border);
             }
-            "rgb" => {
-                if grayscale_only {
-                    equalize_grayscale_luma(image)
-                } else {
-                    equalize_color_rgb(image)
+            let gradient = edge::gradient(&plane, operator.gradient().unwrap(), border);
+            let (magnitude, direction) = gradient_images(&gradient);
+            images.push(NamedImage::new("gray", gray));
+            images.push(NamedImage::new("magnitude", magnitude));
+            images.push(NamedImage::new("direction", direction));
+            (images, None)
+        }
+    }
+}
+
+/// Sharpens every RGB channel of `image` or, given a color space, only its intensity plane, with
+/// the threshold of `sharpen` in levels of 0~255. The output holds the detail added to the luma or
+/// the intensity plane on a diverging scale.
+pub fn sharpen(
+    image: DynamicImage,
+    sharpen: &Sharpen,
+    color_space: Option<ColorSpace>,
+    options: ColorOptions,
+    border: Border,
+) -> Vec<NamedImage> {
+    let (width, height) = image.dimensions();
+    let conversion = color_space.and_then(|space| space.conversion_with(&options));
+    let (sharpened, detail) = match conversion {
+        None => {
+            let planes = rgb_planes(&image);
+            let detail = sharpen.detail(&planes, border);
+            let mut sharpened = planes.clone();
+            let mut luma = vec![0.0; (width * height) as usize];
+            for (c, weight) in [0.299, 0.587, 0.114].into_iter().enumerate() {
+                let added = detail.plane(c);
+                for ((s, l), d) in sharpened.plane_mut(c).iter_mut().zip(&mut luma).zip(added) {
+                    *s += d;
+                    *l += weight * d;
                 }
             }
-            _ => {
-                panic!("Unknown color space: {}", str);
-            }
-        },
-        None => {
-            if grayscale_only {
-                equalize_grayscale_luma(image)
-            } else {
-                equalize_color_hsi(image)
+            (rgb_image(&sharpened, &image), luma)
+        }
+        Some(conversion) => {
+            let index = conversion.intensity();
+            let (low, high) = conversion.ranges()[index];
+            let mut converted = conversion.rgb_to_model(&image);
+            let mut plane: Planes<f32, 1> = Planes::new(width, height);
+            plane.plane_mut(0).copy_from_slice(converted.plane(index));
+            let scaled = Sharpen {
+                threshold: sharpen.threshold * (high - low) / 255.0,
+                ..*sharpen
+            };
+            let detail = scaled.detail(&plane, border);
+            for (v, d) in converted.plane_mut(index).iter_mut().zip(detail.plane(0)) {
+                *v = (*v + d).clamp(low, high);
             }
+            (
+                conversion.model_to_rgb(&converted),
+                detail.plane(0).to_vec(),
+            )
         }
-    }
+    };
+
+    vec![
+        NamedImage::new("original", image),
+        NamedImage::new("sharpened", sharpened),
+        NamedImage::new("detail", signed_map(&detail, width, height)),
+    ]
 }
 
-pub fn invert(image: DynamicImage) -> Vec<ImageDrawer> {
-    let mut inverse = RgbaImage::new(image.width(), image.height());
-    for (x, y, mut pixel) in image.pixels() {
-        pixel.0[0] = 255 - pixel.0[0];
-        pixel.0[1] = 255 - pixel.0[1];
-        pixel.0[2] = 255 - pixel.0[2];
-        inverse.put_pixel(x, y, pixel);
+/// Quality of the images of `denoise` against the clean image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DenoiseReport {
+    /// Noise deviation estimated from the noisy image, which the methods are tuned for.
+    pub sigma: f32,
+    /// Quality of the noisy image, named `noisy`, then of the result of every method, named after
+    /// the method.
+    pub quality: Vec<(String, Quality)>,
+}
+
+/// Adds `noise` drawn from a generator seeded with `seed` to the color channels of `image`.
+/// Returns the original, the noisy image and a heat map of the largest channel change of every
+/// pixel, scaled to the largest change, together with the quality of the noisy image.
+pub fn noise(image: DynamicImage, noise: Noise, seed: u64) -> (Vec<NamedImage>, Quality) {
+    let noisy = rgb_image(&add_noise(&rgb_planes(&image), noise, seed), &image);
+    let quality = Quality::measure(&image, &noisy);
+    let full_scale = channel_error(&image, &noisy)
+        .max
+        .into_iter()
+        .max()
+        .unwrap_or(0);
+    let difference = error_heat_map(&image, &noisy, full_scale);
+    let images = vec![
+        NamedImage::new("original", image),
+        NamedImage::new("noisy", noisy),
+        NamedImage::new("difference", difference),
+    ];
+    (images, quality)
+}
+
+/// Adds `noise` to `image` as `noise` does, then denoises the result with each of `methods` tuned
+/// for the noise deviation estimated from it. Returns the original, the noisy and the denoised
+/// images, together with the estimated deviation and the quality of the noisy and every denoised
+/// image against `image`.
+pub fn denoise(
+    image: DynamicImage,
+    noise: Noise,
+    seed: u64,
+    methods: &[DenoiseMethod],
+    border: Border,
+) -> (Vec<NamedImage>, DenoiseReport) {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let noisy = rgb_image(&add_noise(&rgb_planes(&image), noise, seed), &image);
+    let planes = rgb_planes(&noisy);
+    let sigma = (0..3)
+        .map(|c| estimate_sigma(planes.plane(c), width, height))
+        .sum::<f32>()
+        / 3.0;
+    let mut quality = vec![("noisy".to_string(), Quality::measure(&image, &noisy))];
+    let mut denoised = Vec::with_capacity(methods.len());
+    for &method in methods {
+        let result = rgb_image(&method.apply(&planes, sigma, border), &image);
+        quality.push((method.to_string(), Quality::measure(&image, &result)));
+        denoised.push(NamedImage::new(format!("denoised-{}", method), result));
     }
-    let inverse = DynamicImage::from(inverse);
-    let (hist_original, scale) = draw_histogram_scale(&image, None);
-    let hist_inverse = draw_histogram_scale(&inverse, Some(scale)).0;
 
-    vec![
-        ImageDrawer::from(image),
-        ImageDrawer::from(inverse),
-        ImageDrawer::from(hist_original),
-        ImageDrawer::from(hist_inverse),
-    ]
+    let mut images = vec![
+        NamedImage::new("original", image),
+        NamedImage::new("noisy", noisy),
+    ];
+    images.extend(denoised);
+    (images, DenoiseReport { sigma, quality })
 }
 
-pub fn complement(image: DynamicImage) -> Vec<ImageDrawer> {
+pub fn complement(image: DynamicImage) -> Vec<NamedImage> {
     let mut target = RgbaImage::new(image.width(), image.height());
     for (x, y, mut pixel) in image.pixels() {
         let max = pixel.0[1].max(pixel.0[2]).max(pixel.0[0]);
@@ -449,9 +1019,9 @@ pub fn complement(image: DynamicImage) -> Vec<ImageDrawer> {
     let hist_target = draw_histogram_scale(&target, Some(scale)).0;
 
     vec![
-        ImageDrawer::from(image),
-        ImageDrawer::from(target),
-        ImageDrawer::from(hist_original),
-        ImageDrawer::from(hist_target),
+        NamedImage::new("original", image),
+        NamedImage::new("complement", target),
+        NamedImage::new("histogram-original", hist_original),
+        NamedImage::new("histogram-complement", hist_target),
     ]
 }
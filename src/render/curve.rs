@@ -0,0 +1,70 @@
+use image::{Rgba, RgbaImage};
+
+use super::{draw_line, fill_rect};
+use crate::alg::lut::Lut;
+
+const BACKGROUND: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const GRID: Rgba<u8> = Rgba([224, 224, 224, 255]);
+const IDENTITY: Rgba<u8> = Rgba([176, 176, 176, 255]);
+const CHANNEL_COLORS: [Rgba<u8>; 3] = [
+    Rgba([255, 0, 0, 255]),
+    Rgba([0, 160, 0, 255]),
+    Rgba([0, 0, 255, 255]),
+];
+
+/// Plots the transfer curve of `lut` on a `size`×`size` canvas, input levels across and output
+/// levels up, over a quarter grid and the identity diagonal. A LUT sharing one table between the
+/// channels is drawn in black, otherwise every channel in its own color.
+pub fn render_transfer_curve(lut: &Lut, size: u32) -> RgbaImage {
+    let mut canvas = RgbaImage::from_pixel(size, size, BACKGROUND);
+    let last = size.saturating_sub(1) as i64;
+    let position = |level: u8| level as i64 * last / 255;
+    for quarter in 1..4 {
+        let at = (quarter * last / 4) as u32;
+        fill_rect(&mut canvas, at, 0, 1, size, GRID);
+        fill_rect(&mut canvas, 0, at, size, 1, GRID);
+    }
+    draw_line(&mut canvas, (0, last), (last, 0), IDENTITY);
+
+    let curves: Vec<(usize, Rgba<u8>)> = if lut.is_uniform() {
+        vec![(0, Rgba([0, 0, 0, 255]))]
+    } else {
+        CHANNEL_COLORS.into_iter().enumerate().collect()
+    };
+    for (channel, color) in curves {
+        let table = lut.table(channel);
+        let point = |level: usize| (position(level as u8), last - position(table[level]));
+        for level in 1..256 {
+            draw_line(&mut canvas, point(level - 1), point(level), color);
+        }
+    }
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inverted_curve_runs_down() {
+        let black = Rgba([0, 0, 0, 255]);
+        let plot = render_transfer_curve(&Lut::invert(), 256);
+        assert_eq!(*plot.get_pixel(0, 0), black);
+        assert_eq!(*plot.get_pixel(255, 255), black);
+        assert_eq!(*plot.get_pixel(0, 255), IDENTITY);
+        assert_eq!(*plot.get_pixel(63, 10), GRID);
+    }
+
+    #[test]
+    fn channels_get_own_colors() {
+        let lut = Lut::per_channel([
+            [255; 256],
+            *Lut::identity().table(0),
+            [0; 256],
+        ]);
+        let plot = render_transfer_curve(&lut, 128);
+        assert_eq!(*plot.get_pixel(40, 0), CHANNEL_COLORS[0]);
+        assert_eq!(*plot.get_pixel(40, 127), CHANNEL_COLORS[2]);
+        assert_eq!(*plot.get_pixel(64, 63), CHANNEL_COLORS[1]);
+    }
+}
@@ -0,0 +1,136 @@
+use image::{Pixel, Rgba, RgbaImage};
+
+use super::histogram::AxisScale;
+use crate::alg::color::{ColorModel, Hsv};
+use crate::alg::hist::JointHistogram;
+use crate::alg::metrics::heat_color;
+
+const BACKGROUND: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const GUIDE: Rgba<u8> = Rgba([72, 72, 72, 255]);
+/// Brightness of the least populated cell drawn, so that single pixels stand out of the background.
+const FLOOR: f64 = 0.12;
+
+/// Lines marking where neutral colors fall on a density plot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Guide {
+    None,
+    /// The diagonal where both channels are equal, as on an R–G plot.
+    Diagonal,
+    /// The center lines, where both chroma channels are zero.
+    Cross,
+}
+
+/// Brightness of a cell of `count` pixels out of a largest cell of `peak` pixels, 0 for an empty
+/// cell and between `FLOOR` and 1 otherwise.
+fn density(count: u64, peak: u64, scale: AxisScale) -> f64 {
+    if count == 0 {
+        return 0.0;
+    }
+    let ratio = scale.apply(count as f64) / scale.apply(peak.max(1) as f64);
+    FLOOR + (1.0 - FLOOR) * ratio
+}
+
+/// Draws `hist` as a 256×256 heat map, the first channel growing to the right and the second
+/// upwards. Empty cells stay black, so that `guide` shows through them.
+pub fn render_density(hist: &JointHistogram, scale: AxisScale, guide: Guide) -> RgbaImage {
+    let peak = hist.max_count();
+    RgbaImage::from_fn(256, 256, |x, y| {
+        let level = 255 - y;
+        let count = hist.count(x as u8, level as u8);
+        if count > 0 {
+            return heat_color(density(count, peak, scale) as f32).to_rgba();
+        }
+        let on_guide = match guide {
+            Guide::None => false,
+            Guide::Diagonal => x == level,
+            Guide::Cross => x == 128 || level == 128,
+        };
+        if on_guide {
+            GUIDE
+        } else {
+            BACKGROUND
+        }
+    })
+}
+
+/// Draws a hue–saturation `hist` as a wheel of `size` pixels: the hue turns counterclockwise from
+/// red on the right and the saturation grows from the center. Every pixel shows its own color, as
+/// bright as the most populated cell it covers.
+pub fn render_polar_density(hist: &JointHistogram, size: u32, scale: AxisScale) -> RgbaImage {
+    let peak = hist.max_count();
+    let center = size as f64 / 2.0;
+    let radius = center - 2.0;
+    RgbaImage::from_fn(size, size, |x, y| {
+        let dx = x as f64 + 0.5 - center;
+        let dy = center - (y as f64 + 0.5);
+        let distance = dx.hypot(dy);
+        if distance > radius {
+            return if distance < radius + 1.0 {
+                GUIDE
+            } else {
+                BACKGROUND
+            };
+        }
+        let angle = dy.atan2(dx).to_degrees().rem_euclid(360.0);
+        // The pixel covers half a pixel around its center, in saturation and in hue.
+        let bin = |v: f64| (v * 256.0).floor() as i64;
+        let saturations = bin((distance - 0.5).max(0.0) / radius)..=bin((distance + 0.5) / radius);
+        let half_arc = (0.5 / distance.max(0.5)).to_degrees().min(180.0);
+        let hues = bin((angle - half_arc) / 360.0)..=bin((angle + half_arc) / 360.0);
+        let count = hues
+            .flat_map(|h| saturations.clone().map(move |s| (h, s)))
+            .filter(|&(_, s)| s < 256)
+            .map(|(h, s)| hist.count(h.rem_euclid(256) as u8, s as u8))
+            .max()
+            .unwrap_or(0);
+        let hsv = Hsv {
+            h: angle as f32,
+            s: (distance / radius) as f32,
+            v: density(count, peak, scale) as f32,
+        };
+        hsv.to_rgb8().to_rgba()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn joint(cells: &[(f32, f32, usize)]) -> JointHistogram {
+        let mut x = Vec::new();
+        let mut y = Vec::new();
+        for &(u, v, n) in cells {
+            x.extend(std::iter::repeat_n(u, n));
+            y.extend(std::iter::repeat_n(v, n));
+        }
+        JointHistogram::from_planes(&x, (0.0, 256.0), &y, (0.0, 256.0))
+    }
+
+    #[test]
+    fn density_grows_upwards() {
+        let hist = joint(&[(10.0, 20.0, 100), (200.0, 50.0, 1)]);
+        let image = render_density(&hist, AxisScale::Log, Guide::Diagonal);
+        assert_eq!(*image.get_pixel(10, 235), Rgba([255, 255, 255, 255]));
+        let faint = image.get_pixel(200, 205).0;
+        assert!(faint[0] > 0 && faint[0] < 255 && faint[1] == 0);
+        assert_eq!(*image.get_pixel(100, 155), GUIDE);
+        assert_eq!(*image.get_pixel(100, 154), BACKGROUND);
+
+        let cross = render_density(&hist, AxisScale::Linear, Guide::Cross);
+        assert_eq!(*cross.get_pixel(128, 0), GUIDE);
+        assert_eq!(*cross.get_pixel(0, 127), GUIDE);
+    }
+
+    #[test]
+    fn polar_density_shows_cell_color() {
+        // Fully saturated red and half saturated cyan.
+        let hist = joint(&[(0.0, 255.0, 4), (128.0, 128.0, 4)]);
+        let wheel = render_polar_density(&hist, 100, AxisScale::Linear);
+        let red = wheel.get_pixel(97, 49).0;
+        assert!(red[0] == 255 && red[1] < 8 && red[2] < 8);
+        let cyan = wheel.get_pixel(26, 50).0;
+        assert!(cyan[0] < cyan[1] && cyan[1].abs_diff(cyan[2]) < 8 && cyan[2] == 255);
+        assert_eq!(*wheel.get_pixel(50, 20), BACKGROUND);
+        assert_eq!(*wheel.get_pixel(0, 0), BACKGROUND);
+    }
+}
@@ -0,0 +1,577 @@
+use image::{DynamicImage, Pixel, Rgba, RgbaImage};
+
+use crate::alg::color::{ColorModel, Hsv};
+
+use super::font::{draw_text, text_width, GLYPH_HEIGHT};
+use super::{blend_pixel, draw_line, fill_rect};
+
+/// Smallest canvas side accepted by `HistogramStyle`.
+pub const MIN_SIZE: u32 = 64;
+/// Largest canvas side accepted by `HistogramStyle`.
+pub const MAX_SIZE: u32 = 4096;
+/// Smallest number of bins.
+pub const MIN_BINS: usize = 16;
+/// Largest number of bins, one per level.
+pub const MAX_BINS: usize = 256;
+
+/// Room left around the plot for tick labels.
+const LEFT_MARGIN: u32 = 36;
+const RIGHT_MARGIN: u32 = 6;
+const TOP_MARGIN: u32 = 5;
+const BOTTOM_MARGIN: u32 = 14;
+/// Opacity of the bars when the channels are overlaid.
+const OVERLAY_ALPHA: f32 = 0.4;
+
+const BACKGROUND: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const AXIS: Rgba<u8> = Rgba([128, 128, 128, 255]);
+const TEXT: Rgba<u8> = Rgba([64, 64, 64, 255]);
+
+/// Mapping of the counts to the height of the bars.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum AxisScale {
+    #[default]
+    Linear,
+    /// Square root of the count, to lift small counts.
+    Sqrt,
+    /// Logarithm of one plus the count, to see the smallest counts next to a dominant peak.
+    Log,
+}
+
+impl AxisScale {
+    pub(crate) fn apply(self, count: f64) -> f64 {
+        match self {
+            AxisScale::Linear => count,
+            AxisScale::Sqrt => count.sqrt(),
+            AxisScale::Log => count.ln_1p(),
+        }
+    }
+
+    fn invert(self, value: f64) -> f64 {
+        match self {
+            AxisScale::Linear => value,
+            AxisScale::Sqrt => value * value,
+            AxisScale::Log => value.exp_m1(),
+        }
+    }
+}
+
+/// Arrangement of several channels on the canvas.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Layout {
+    /// One band per channel, from top to bottom.
+    #[default]
+    Stacked,
+    /// All channels in one band, drawn translucent over each other.
+    Overlaid,
+}
+
+/// How `HistogramChart::render` draws a histogram. The default is the 512×512 stacked chart of
+/// linear bars without labels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistogramStyle {
+    pub width: u32,
+    pub height: u32,
+    /// Number of bins the 256 levels are grouped into, from `MIN_BINS` to `MAX_BINS`.
+    pub bins: usize,
+    pub scale: AxisScale,
+    pub layout: Layout,
+    /// Draws the cumulative distribution of every channel as a line over its bars.
+    pub cdf: bool,
+    /// Draws tick labels with the counts and levels, and the channel names.
+    pub labels: bool,
+}
+
+impl Default for HistogramStyle {
+    fn default() -> Self {
+        HistogramStyle {
+            width: 512,
+            height: 512,
+            bins: MAX_BINS,
+            scale: AxisScale::Linear,
+            layout: Layout::Stacked,
+            cdf: false,
+            labels: false,
+        }
+    }
+}
+
+/// One channel of a chart.
+#[derive(Clone, Copy, Debug)]
+pub struct Series<'a> {
+    pub name: &'a str,
+    pub counts: &'a [u64; 256],
+    /// Color of the bars, and a darker shade of it for the CDF and the name.
+    pub color: Rgba<u8>,
+    /// Color of the bar of every level, such as the hue it stands for, instead of `color`.
+    pub level_colors: Option<&'a [Rgba<u8>; 256]>,
+}
+
+impl<'a> Series<'a> {
+    pub fn new(name: &'a str, counts: &'a [u64; 256], color: Rgba<u8>) -> Self {
+        Series {
+            name,
+            counts,
+            color,
+            level_colors: None,
+        }
+    }
+
+    pub fn with_level_colors(self, colors: &'a [Rgba<u8>; 256]) -> Self {
+        Series {
+            level_colors: Some(colors),
+            ..self
+        }
+    }
+}
+
+/// Color of every level of a hue plane spanning the whole circle, at full saturation and value.
+pub fn hue_level_colors() -> [Rgba<u8>; 256] {
+    std::array::from_fn(|level| {
+        let hsv = Hsv {
+            h: (level as f32 + 0.5) * 360.0 / 256.0,
+            s: 1.0,
+            v: 1.0,
+        };
+        hsv.to_rgb8().to_rgba()
+    })
+}
+
+/// Sums the counts of the levels falling into each of `bins` bins.
+pub fn bin_counts(counts: &[u64; 256], bins: usize) -> Vec<u64> {
+    let mut binned = vec![0; bins];
+    for (level, &count) in counts.iter().enumerate() {
+        binned[level * bins / 256] += count;
+    }
+    binned
+}
+
+/// Short form of a count for tick labels, such as `950`, `12.5K` or `3M`.
+pub fn format_count(count: u64) -> String {
+    let short = |value: f64, suffix: &str| {
+        if value < 100.0 {
+            format!("{:.1}{}", value, suffix).replace(".0", "")
+        } else {
+            format!("{:.0}{}", value, suffix)
+        }
+    };
+    match count {
+        0..=9_999 => count.to_string(),
+        10_000..=999_999 => short(count as f64 / 1e3, "K"),
+        _ => short(count as f64 / 1e6, "M"),
+    }
+}
+
+/// Darker shade of `color` for lines drawn over bars of that color.
+fn darken(color: Rgba<u8>) -> Rgba<u8> {
+    Rgba([color.0[0] / 2, color.0[1] / 2, color.0[2] / 2, 255])
+}
+
+/// A rendered histogram, which remembers its geometry so that levels can be marked on it.
+#[derive(Clone, Debug)]
+pub struct HistogramChart {
+    pub image: RgbaImage,
+    /// Count drawn at the full height of a band.
+    pub scale: u64,
+    style: HistogramStyle,
+    /// Left, top, width and height of the plot area.
+    plot: (u32, u32, u32, u32),
+    bands: u32,
+}
+
+impl HistogramChart {
+    /// Draws `series` in `style`, the full height of a band standing for `scale` or, when no scale
+    /// is given, for the largest binned count. Channels without any count are left blank.
+    pub fn render(series: &[Series], style: &HistogramStyle, scale: Option<u64>) -> Self {
+        let binned: Vec<Vec<u64>> = series
+            .iter()
+            .map(|s| bin_counts(s.counts, style.bins))
+            .collect();
+        let scale = scale
+            .unwrap_or_else(|| binned.iter().flatten().copied().max().unwrap_or(0))
+            .max(1);
+        let plot = if style.labels {
+            (
+                LEFT_MARGIN,
+                TOP_MARGIN,
+                style.width.saturating_sub(LEFT_MARGIN + RIGHT_MARGIN),
+                style.height.saturating_sub(TOP_MARGIN + BOTTOM_MARGIN),
+            )
+        } else {
+            (0, 0, style.width, style.height)
+        };
+        let bands = match style.layout {
+            Layout::Stacked => series.len().max(1) as u32,
+            Layout::Overlaid => 1,
+        };
+        let mut chart = HistogramChart {
+            image: RgbaImage::from_pixel(style.width, style.height, BACKGROUND),
+            scale,
+            style: *style,
+            plot,
+            bands,
+        };
+
+        for band in 0..bands {
+            let (_, bottom) = chart.band_rows(band);
+            fill_rect(&mut chart.image, plot.0, bottom, plot.2, 1, AXIS);
+        }
+        let drawn: Vec<(u32, &Series, &Vec<u64>)> = series
+            .iter()
+            .zip(&binned)
+            .enumerate()
+            .filter(|(_, (_, counts))| counts.iter().any(|&n| n > 0))
+            .map(|(i, (s, counts))| (chart.band_of(i), s, counts))
+            .collect();
+        for &(band, s, counts) in &drawn {
+            chart.draw_bars(band, counts, s);
+        }
+        if style.cdf {
+            for &(band, s, counts) in &drawn {
+                chart.draw_cdf(band, counts, darken(s.color));
+            }
+        }
+        if style.labels {
+            chart.draw_labels(&drawn);
+        }
+        chart
+    }
+
+    fn band_of(&self, series: usize) -> u32 {
+        match self.style.layout {
+            Layout::Stacked => series as u32,
+            Layout::Overlaid => 0,
+        }
+    }
+
+    /// First row of `band` and the row of its axis line, below the bars.
+    fn band_rows(&self, band: u32) -> (u32, u32) {
+        let (_, top, _, height) = self.plot;
+        let first = top + band * height / self.bands;
+        let next = top + (band + 1) * height / self.bands;
+        (first, next.max(first + 1) - 1)
+    }
+
+    /// First and past-the-end columns of the bin `index` out of `bins`.
+    fn columns(&self, index: usize, bins: usize) -> (u32, u32) {
+        let (left, _, width, _) = self.plot;
+        let x0 = left + (index * width as usize / bins) as u32;
+        let x1 = left + ((index + 1) * width as usize / bins) as u32;
+        (x0, x1.max(x0 + 1))
+    }
+
+    /// Height in pixels of a bar standing for `count` in a band of `band_height` rows.
+    fn bar_height(&self, count: u64, band_height: u32) -> u32 {
+        let scale = self.style.scale;
+        let ratio = scale.apply(count as f64) / scale.apply(self.scale as f64);
+        (ratio * band_height as f64).round().min(band_height as f64) as u32
+    }
+
+    fn draw_bars(&mut self, band: u32, counts: &[u64], series: &Series) {
+        let (top, bottom) = self.band_rows(band);
+        for (i, &count) in counts.iter().enumerate() {
+            let h = self.bar_height(count, bottom - top);
+            if h == 0 {
+                continue;
+            }
+            let (x0, x1) = self.columns(i, counts.len());
+            // A bin takes the color of its middle level.
+            let color = match series.level_colors {
+                Some(colors) => colors[(2 * i + 1) * 128 / counts.len()],
+                None => series.color,
+            };
+            match self.style.layout {
+                Layout::Stacked => fill_rect(&mut self.image, x0, bottom - h, x1 - x0, h, color),
+                Layout::Overlaid => {
+                    for y in bottom - h..bottom {
+                        for x in x0..x1 {
+                            blend_pixel(&mut self.image, x, y, color, OVERLAY_ALPHA);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_cdf(&mut self, band: u32, counts: &[u64], color: Rgba<u8>) {
+        let (top, bottom) = self.band_rows(band);
+        let total = counts.iter().sum::<u64>() as f64;
+        let height = (bottom - top) as f64;
+        let mut last = (self.columns(0, counts.len()).0 as i64, bottom as i64 - 1);
+        let mut sum = 0;
+        for (i, &count) in counts.iter().enumerate() {
+            sum += count;
+            let y = bottom as f64 - 1.0 - (sum as f64 / total * (height - 1.0)).round();
+            let point = (self.columns(i, counts.len()).1 as i64 - 1, y as i64);
+            draw_line(&mut self.image, last, point, color);
+            last = point;
+        }
+    }
+
+    fn draw_labels(&mut self, drawn: &[(u32, &Series, &Vec<u64>)]) {
+        let (left, _, width, _) = self.plot;
+        let scale = self.style.scale;
+        for band in 0..self.bands {
+            let (top, bottom) = self.band_rows(band);
+            let height = bottom - top;
+            let mut ticks = Vec::new();
+            // The zero of a band would collide with the top label of the band below it.
+            if band == self.bands - 1 {
+                ticks.push((bottom, 0));
+            }
+            if height >= 2 * GLYPH_HEIGHT + 2 {
+                ticks.push((top, self.scale));
+            }
+            if height >= 4 * GLYPH_HEIGHT {
+                let half = scale.invert(scale.apply(self.scale as f64) / 2.0).round() as u64;
+                ticks.push((bottom - self.bar_height(half, height), half));
+            }
+            for (y, count) in ticks {
+                fill_rect(&mut self.image, left - 3, y, 3, 1, AXIS);
+                let text = format_count(count);
+                let x = left as i64 - 5 - text_width(&text) as i64;
+                draw_text(&mut self.image, x, y as i64 - 3, &text, TEXT);
+            }
+        }
+
+        let (_, bottom) = self.band_rows(self.bands - 1);
+        for level in [0u8, 64, 128, 192, 255] {
+            let (x0, x1) = self.columns(level as usize, 256);
+            let x = (x0 + x1) / 2;
+            fill_rect(&mut self.image, x, bottom + 1, 1, 3, AXIS);
+            let text = level.to_string();
+            let half = text_width(&text) as i64 / 2;
+            let x = (x as i64 - half).clamp(0, self.image.width() as i64 - 2 * half - 1);
+            draw_text(&mut self.image, x, bottom as i64 + 5, &text, TEXT);
+        }
+
+        let scale_name = match scale {
+            AxisScale::Linear => None,
+            AxisScale::Sqrt => Some("sqrt"),
+            AxisScale::Log => Some("log"),
+        };
+        let mut right = vec![left + width; self.bands as usize];
+        for &(band, s, _) in drawn.iter().rev() {
+            let (top, _) = self.band_rows(band);
+            let x = right[band as usize] as i64 - 2 - text_width(s.name) as i64;
+            draw_text(&mut self.image, x, top as i64 + 2, s.name, darken(s.color));
+            right[band as usize] = (x - 6).max(0) as u32;
+        }
+        if let Some(name) = scale_name {
+            let (top, _) = self.band_rows(0);
+            draw_text(&mut self.image, left as i64 + 2, top as i64 + 2, name, TEXT);
+        }
+    }
+
+    /// Draws a vertical line across `band` at each of `levels`, over its axis too.
+    pub fn mark_levels(&mut self, band: u32, levels: &[u8], color: Rgba<u8>) {
+        let (top, bottom) = self.band_rows(band);
+        for &level in levels {
+            let (x0, x1) = self.columns(level as usize, 256);
+            fill_rect(&mut self.image, x0, top, x1 - x0, bottom - top + 1, color);
+        }
+    }
+
+    pub fn into_image(self) -> DynamicImage {
+        DynamicImage::from(self.image)
+    }
+}
+
+/// Draws the histogram of a hue plane as bars around a circle, each bar pointing at the hue it
+/// counts (red to the right, hues growing counterclockwise). The longest bar stands for the largest
+/// count and a ring of all hues marks the base of the bars.
+pub fn render_hue_wheel(counts: &[u64; 256], size: u32, scale: AxisScale) -> RgbaImage {
+    let colors = hue_level_colors();
+    let peak = scale.apply(counts.iter().copied().max().unwrap_or(0).max(1) as f64);
+    let center = size as f64 / 2.0;
+    let outer = center - 4.0;
+    let inner = outer * 0.3;
+    RgbaImage::from_fn(size, size, |x, y| {
+        let dx = x as f64 + 0.5 - center;
+        let dy = center - (y as f64 + 0.5);
+        let r = dx.hypot(dy);
+        let angle = dy.atan2(dx).to_degrees().rem_euclid(360.0);
+        let level = ((angle / 360.0 * 256.0) as usize).min(255);
+        if (inner - 6.0..inner - 2.0).contains(&r) {
+            return colors[level];
+        }
+        let length = scale.apply(counts[level] as f64) / peak * (outer - inner);
+        if counts[level] > 0 && (inner..=inner + length.max(1.0)).contains(&r) {
+            colors[level]
+        } else if (r - outer).abs() < 0.5 {
+            AXIS
+        } else {
+            BACKGROUND
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+    fn spike(level: usize, count: u64) -> [u64; 256] {
+        let mut counts = [0; 256];
+        counts[level] = count;
+        counts
+    }
+
+    #[test]
+    fn hue_bars_take_hue_colors() {
+        let counts = [1; 256];
+        let colors = hue_level_colors();
+        assert_eq!(colors[0], Rgba([255, 3, 0, 255]));
+        assert_eq!(colors[85].0[1], 255);
+        let series = [Series::new("h", &counts, BLACK).with_level_colors(&colors)];
+        let style = HistogramStyle {
+            bins: 16,
+            ..HistogramStyle::default()
+        };
+        let chart = HistogramChart::render(&series, &style, None);
+        assert_eq!(*chart.image.get_pixel(0, 500), colors[8]);
+        assert_eq!(*chart.image.get_pixel(511, 500), colors[248]);
+    }
+
+    #[test]
+    fn hue_wheel_points_at_hue() {
+        let mut counts = spike(0, 10);
+        counts[128] = 5;
+        let wheel = render_hue_wheel(&counts, 100, AxisScale::Linear);
+        let colors = hue_level_colors();
+        // Red bar to the right reaching the outline, cyan bar to the left reaching half way.
+        assert_eq!(*wheel.get_pixel(94, 49), colors[0]);
+        assert_eq!(*wheel.get_pixel(2, 50), BACKGROUND);
+        assert_eq!(*wheel.get_pixel(25, 50), colors[128]);
+        // Nothing points up, except the ring of hues at the base.
+        assert_eq!(*wheel.get_pixel(50, 20), BACKGROUND);
+        assert_eq!(wheel.get_pixel(50, 40).0[1], 255);
+    }
+
+    #[test]
+    fn bins_sum_levels() {
+        let counts = [1; 256];
+        assert_eq!(bin_counts(&counts, 16), vec![16; 16]);
+        let binned = bin_counts(&counts, 100);
+        assert_eq!(binned.len(), 100);
+        assert_eq!(binned.iter().sum::<u64>(), 256);
+        assert_eq!(bin_counts(&spike(255, 7), 16)[15], 7);
+    }
+
+    #[test]
+    fn counts_are_shortened() {
+        assert_eq!(format_count(950), "950");
+        assert_eq!(format_count(12_500), "12.5K");
+        assert_eq!(format_count(20_000), "20K");
+        assert_eq!(format_count(250_000), "250K");
+        assert_eq!(format_count(3_000_000_000), "3000M");
+        assert_eq!(format_count(1_500_000), "1.5M");
+        assert_eq!(format_count(3_000_000), "3M");
+        assert_eq!(format_count(30_000_000), "30M");
+    }
+
+    #[test]
+    fn default_style_draws_full_bars() {
+        let counts = spike(10, 100);
+        let series = [Series::new("gray", &counts, BLACK)];
+        let chart = HistogramChart::render(&series, &HistogramStyle::default(), None);
+        assert_eq!(chart.scale, 100);
+        assert_eq!(chart.image.dimensions(), (512, 512));
+        // The bar fills the band down to the axis line on the last row.
+        assert_eq!(*chart.image.get_pixel(20, 0), BLACK);
+        assert_eq!(*chart.image.get_pixel(21, 510), BLACK);
+        assert_eq!(*chart.image.get_pixel(22, 0), BACKGROUND);
+        assert_eq!(*chart.image.get_pixel(0, 511), AXIS);
+    }
+
+    #[test]
+    fn log_scale_lifts_small_counts() {
+        let mut counts = spike(0, 100_000);
+        counts[100] = 1;
+        let series = [Series::new("gray", &counts, BLACK)];
+        let bar_top = |scale| {
+            let style = HistogramStyle {
+                scale,
+                ..HistogramStyle::default()
+            };
+            let chart = HistogramChart::render(&series, &style, None);
+            (0..512)
+                .find(|&y| *chart.image.get_pixel(200, y) == BLACK)
+                .unwrap_or(512)
+        };
+        assert_eq!(bar_top(AxisScale::Linear), 512);
+        assert!(bar_top(AxisScale::Sqrt) < 511);
+        assert!(bar_top(AxisScale::Log) < bar_top(AxisScale::Sqrt));
+    }
+
+    #[test]
+    fn cdf_reaches_top_of_band() {
+        let counts = [1; 256];
+        let series = [Series::new("gray", &counts, Rgba([255, 0, 0, 255]))];
+        let style = HistogramStyle {
+            cdf: true,
+            bins: 16,
+            ..HistogramStyle::default()
+        };
+        let chart = HistogramChart::render(&series, &style, Some(32));
+        let line = Rgba([127, 0, 0, 255]);
+        assert_eq!(*chart.image.get_pixel(511, 0), line);
+        assert_eq!(*chart.image.get_pixel(0, 510), line);
+    }
+
+    #[test]
+    fn overlaid_channels_share_one_band() {
+        let red = spike(50, 10);
+        let blue = spike(50, 10);
+        let series = [
+            Series::new("red", &red, Rgba([255, 0, 0, 255])),
+            Series::new("blue", &blue, Rgba([0, 0, 255, 255])),
+        ];
+        let style = HistogramStyle {
+            layout: Layout::Overlaid,
+            width: 256,
+            height: 128,
+            ..HistogramStyle::default()
+        };
+        let chart = HistogramChart::render(&series, &style, None);
+        let pixel = chart.image.get_pixel(50, 64).0;
+        // Blue drawn over red over white gives a purple.
+        assert_eq!(pixel, [153, 92, 194, 255]);
+        assert_eq!(*chart.image.get_pixel(50, 127), AXIS);
+    }
+
+    #[test]
+    fn labels_leave_room_for_ticks() {
+        let counts = [3; 256];
+        let series = [Series::new("gray", &counts, BLACK)];
+        let style = HistogramStyle {
+            labels: true,
+            width: MIN_SIZE,
+            height: MIN_SIZE,
+            ..HistogramStyle::default()
+        };
+        let mut chart = HistogramChart::render(&series, &style, None);
+        chart.mark_levels(0, &[0, 255], Rgba([255, 0, 0, 255]));
+        // Nothing but text is drawn left of the plot.
+        assert!((0..MIN_SIZE).all(|y| *chart.image.get_pixel(0, y) != BLACK));
+        assert!(chart.image.pixels().any(|p| *p == TEXT));
+    }
+
+    #[test]
+    fn marks_span_band() {
+        let counts = [0; 256];
+        let series = [
+            Series::new("red", &counts, BLACK),
+            Series::new("gray", &counts, BLACK),
+        ];
+        let red = Rgba([255, 0, 0, 255]);
+        let mut chart = HistogramChart::render(&series, &HistogramStyle::default(), None);
+        chart.mark_levels(1, &[128], red);
+        assert_eq!(*chart.image.get_pixel(256, 256), red);
+        assert_eq!(*chart.image.get_pixel(257, 511), red);
+        assert_eq!(*chart.image.get_pixel(255, 511), AXIS);
+        assert_eq!(*chart.image.get_pixel(256, 255), AXIS);
+    }
+}
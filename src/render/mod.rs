@@ -0,0 +1,83 @@
+//! Rendering of charts, such as histograms, into images.
+
+pub mod curve;
+pub mod density;
+pub mod font;
+pub mod histogram;
+
+use image::{Rgba, RgbaImage};
+
+/// Fills the rectangle at `(x, y)` of `width` by `height` pixels, clipped to the canvas.
+pub fn fill_rect(canvas: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgba<u8>) {
+    let x_end = (x + width).min(canvas.width());
+    let y_end = (y + height).min(canvas.height());
+    for y in y..y_end {
+        for x in x..x_end {
+            canvas.put_pixel(x, y, color);
+        }
+    }
+}
+
+/// Mixes `color` into the pixel at `(x, y)` with opacity `alpha` in 0~1.
+pub fn blend_pixel(canvas: &mut RgbaImage, x: u32, y: u32, color: Rgba<u8>, alpha: f32) {
+    if x >= canvas.width() || y >= canvas.height() {
+        return;
+    }
+    let pixel = canvas.get_pixel_mut(x, y);
+    for c in 0..3 {
+        let mixed = pixel.0[c] as f32 * (1.0 - alpha) + color.0[c] as f32 * alpha;
+        pixel.0[c] = mixed.round() as u8;
+    }
+}
+
+/// Draws a one pixel wide line from `from` to `to` (Bresenham), clipped to the canvas.
+pub fn draw_line(canvas: &mut RgbaImage, from: (i64, i64), to: (i64, i64), color: Rgba<u8>) {
+    let (mut x, mut y) = from;
+    let dx = (to.0 - x).abs();
+    let dy = -(to.1 - y).abs();
+    let sx = if x < to.0 { 1 } else { -1 };
+    let sy = if y < to.1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    loop {
+        if x >= 0 && y >= 0 && x < canvas.width() as i64 && y < canvas.height() as i64 {
+            canvas.put_pixel(x as u32, y as u32, color);
+        }
+        if (x, y) == to {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_covers_both_ends() {
+        let red = Rgba([255, 0, 0, 255]);
+        let mut canvas = RgbaImage::new(10, 10);
+        draw_line(&mut canvas, (1, 8), (8, 2), red);
+        assert_eq!(*canvas.get_pixel(1, 8), red);
+        assert_eq!(*canvas.get_pixel(8, 2), red);
+        assert_eq!(canvas.pixels().filter(|p| **p == red).count(), 8);
+        draw_line(&mut canvas, (-5, -5), (20, 20), red);
+        assert_eq!(*canvas.get_pixel(9, 9), red);
+    }
+
+    #[test]
+    fn blend_mixes_colors() {
+        let mut canvas = RgbaImage::from_pixel(1, 1, Rgba([255, 255, 255, 255]));
+        blend_pixel(&mut canvas, 0, 0, Rgba([0, 0, 255, 255]), 0.5);
+        assert_eq!(*canvas.get_pixel(0, 0), Rgba([128, 128, 255, 255]));
+        blend_pixel(&mut canvas, 3, 0, Rgba([0, 0, 0, 255]), 1.0);
+    }
+}
@@ -1,6 +1,13 @@
 use crate::draw::ImageDrawer;
+use imgproc::proc::NamedImage;
+use imgproc::{Error, Result};
 use softbuffer::{Context, Surface};
-use winit::window::{Window, WindowId};
+use std::cmp::max;
+use std::collections::HashMap;
+use std::panic;
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::{Window, WindowBuilder, WindowButtons, WindowId};
 
 pub struct ImageView {
     window: Window,
@@ -9,14 +16,16 @@ pub struct ImageView {
 }
 
 impl ImageView {
-    pub fn new(window: Window, drawer: ImageDrawer) -> Self {
-        let context = unsafe { Context::new(&window) }.unwrap();
-        let surface = unsafe { Surface::new(&context, &window) }.unwrap();
-        ImageView {
+    pub fn new(window: Window, drawer: ImageDrawer) -> Result<Self> {
+        let context = unsafe { Context::new(&window) }
+            .map_err(|e| Error::DisplayUnavailable(e.to_string()))?;
+        let surface = unsafe { Surface::new(&context, &window) }
+            .map_err(|e| Error::DisplayUnavailable(e.to_string()))?;
+        Ok(ImageView {
             window,
             surface,
             drawer,
-        }
+        })
     }
 
     pub fn window_id(&self) -> WindowId {
@@ -27,3 +36,66 @@ impl ImageView {
         self.drawer.draw(&mut self.surface);
     }
 }
+
+fn create_event_loop() -> Result<EventLoop<()>> {
+    // winit panics instead of returning an error when no display server can be reached
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let event_loop = panic::catch_unwind(EventLoop::new);
+    panic::set_hook(hook);
+    event_loop
+        .map_err(|_| Error::DisplayUnavailable("cannot connect to a display server".to_string()))
+}
+
+pub fn show_images(images: Vec<NamedImage>) -> Result<()> {
+    let event_loop = create_event_loop()?;
+    let mut views = HashMap::new();
+
+    let mut pos = winit::dpi::PhysicalPosition::new(0, 0);
+    let mut y_offset = 0;
+    for drawer in images.into_iter().map(ImageDrawer::from) {
+        let window = WindowBuilder::new()
+            .with_title(drawer.name())
+            .with_inner_size(winit::dpi::PhysicalSize::new(
+                drawer.width(),
+                drawer.height(),
+            ))
+            .with_resizable(false)
+            .with_enabled_buttons(WindowButtons::CLOSE)
+            .build(&event_loop)
+            .map_err(|e| Error::DisplayUnavailable(e.to_string()))?;
+        if let Some(mon) = window.current_monitor() {
+            // 如果不是第一个窗口并且按当前位置排列会超出显示器边界，则将该窗口排到下一行
+            if pos.x > 0 && (pos.x + window.outer_size().width) > mon.size().width {
+                pos = winit::dpi::PhysicalPosition::new(0, y_offset);
+            }
+        }
+        window.set_outer_position(pos);
+        pos.x += window.outer_size().width;
+        y_offset = max(y_offset, pos.y + window.outer_size().height);
+
+        let view = ImageView::new(window, drawer)?;
+        views.insert(view.window_id(), view);
+    }
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Wait;
+
+        match event {
+            Event::RedrawRequested(window_id) => {
+                let view = views.get_mut(&window_id).unwrap();
+                view.draw();
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                window_id,
+            } => {
+                views.remove(&window_id);
+                if views.is_empty() {
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+            _ => {}
+        }
+    });
+}
@@ -0,0 +1,45 @@
+//! Command line tests: the `imgproc` binary is run on the bundled default image.
+
+#![cfg(feature = "cli")]
+
+use std::env;
+use std::process::{Command, Output};
+
+fn imgproc(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_imgproc"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn unknown_subcommand_is_rejected() {
+    let dir = env::temp_dir().join("imgproc-cli-unknown");
+    let output = imgproc(&["foo", &format!("--output={}", dir.display())]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unrecognized subcommand 'foo'"), "{}", stderr);
+    assert!(!dir.exists());
+}
+
+#[test]
+fn unknown_chain_step_is_rejected() {
+    let output = imgproc(&["lut3d", "--chain=foo --value=2"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("'foo'"), "{}", stderr);
+}
+
+#[test]
+fn per_channel_takes_a_hyphen() {
+    let dir = env::temp_dir().join("imgproc-cli-per-channel");
+    let output = imgproc(&[
+        "quantize",
+        "--per-channel",
+        &format!("--output={}", dir.display()),
+    ]);
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("Cuts of r: "), "{}", stdout);
+}
@@ -0,0 +1,363 @@
+//! Golden-image tests: every `proc` function is run on a downscaled `res/lena.jpg` and each result
+//! image is compared against `tests/golden/<case>-<index>-<name>.png`.
+//!
+//! Run with `IMGPROC_UPDATE_GOLDEN=1` to (re)write the golden images after an intended change.
+
+use std::env;
+use std::path::PathBuf;
+
+use image::{imageops::FilterType, DynamicImage};
+use imgproc::alg::color::{ColorOptions, ColorSpace};
+use imgproc::alg::edge::EdgeOperator;
+use imgproc::alg::filter::{Border, BorderMode, Filter, Kernel};
+use imgproc::alg::gray::{AdaptiveMethod, ClaheParams, TargetCurve, ThresholdMethod};
+use imgproc::alg::hist::ChannelPair;
+use imgproc::alg::lut::Levels;
+use imgproc::alg::lut3d::{Interpolation, Lut3d};
+use imgproc::alg::noise::{DenoiseMethod, Noise, NoiseKind};
+use imgproc::alg::sharpen::{Sharpen, SharpenMethod};
+use imgproc::proc::{self, EdgeThresholds, MatchTarget, NamedImage};
+use imgproc::render::histogram::{AxisScale, HistogramStyle, Layout};
+
+/// Largest difference allowed between a sample and its golden value, to absorb floating point
+/// differences between platforms.
+const TOLERANCE: u8 = 2;
+
+fn lena() -> DynamicImage {
+    image::load_from_memory(include_bytes!("../res/lena.jpg"))
+        .unwrap()
+        .resize_exact(64, 64, FilterType::Triangle)
+}
+
+fn golden_path(case: &str, index: usize, image: &NamedImage) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}-{:02}-{}.png", case, index + 1, image.name))
+}
+
+fn check_golden(case: &str, images: Vec<NamedImage>) {
+    let update = env::var_os("IMGPROC_UPDATE_GOLDEN").is_some();
+    for (index, image) in images.iter().enumerate() {
+        let path = golden_path(case, index, image);
+        let actual = image.image.to_rgba8();
+        if update {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            actual.save(&path).unwrap();
+            continue;
+        }
+
+        let expected = image::open(&path)
+            .unwrap_or_else(|e| panic!("cannot open golden image {}: {}", path.display(), e))
+            .to_rgba8();
+        assert_eq!(
+            expected.dimensions(),
+            actual.dimensions(),
+            "size of {} differs",
+            path.display()
+        );
+        let max_diff = expected
+            .iter()
+            .zip(actual.iter())
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap_or(0);
+        assert!(
+            max_diff <= TOLERANCE,
+            "{} differs by up to {}",
+            path.display(),
+            max_diff
+        );
+    }
+}
+
+#[test]
+fn grayscale() {
+    let options = ColorOptions::default();
+    check_golden("grayscale", proc::grayscale(lena(), None, options));
+    for space in [ColorSpace::Hsv, ColorSpace::YCbCr, ColorSpace::Lab] {
+        let case = format!("grayscale-{}", space);
+        check_golden(&case, proc::grayscale(lena(), Some(space), options));
+    }
+}
+
+#[test]
+fn binarize() {
+    let method = ThresholdMethod::default();
+    check_golden("binarize", proc::binarize(lena(), None, method).0);
+    let (images, level) = proc::binarize(lena(), Some(100), method);
+    assert_eq!(level, 100);
+    check_golden("binarize-100", images);
+    let (images, level) = proc::binarize(lena(), None, ThresholdMethod::Otsu);
+    assert!((64..192).contains(&level), "{}", level);
+    check_golden("binarize-otsu", images);
+    check_golden(
+        "binarize-sauvola",
+        proc::binarize_adaptive(lena(), AdaptiveMethod::Sauvola, 15, None),
+    );
+}
+
+#[test]
+fn quantize() {
+    let (images, cuts) = proc::quantize(lena(), 3, None, false);
+    assert!(cuts.len() == 1 && cuts[0].len() == 2 && cuts[0][0] < cuts[0][1]);
+    check_golden("quantize", images);
+    let (images, cuts) = proc::quantize(lena(), 2, Some(&[64, 128, 192]), true);
+    assert_eq!(cuts, vec![vec![64, 128, 192]; 3]);
+    check_golden("quantize-channels", images);
+}
+
+#[test]
+fn histogram() {
+    let options = ColorOptions::default();
+    let default_style = HistogramStyle::default();
+    check_golden(
+        "histogram",
+        proc::histogram(lena(), None, options, &default_style),
+    );
+    let style = HistogramStyle {
+        width: 320,
+        height: 240,
+        bins: 64,
+        scale: AxisScale::Log,
+        layout: Layout::Overlaid,
+        cdf: true,
+        labels: true,
+    };
+    check_golden(
+        "histogram-log",
+        proc::histogram(lena(), None, options, &style),
+    );
+    for space in [ColorSpace::Hsv, ColorSpace::YCbCr] {
+        let case = format!("histogram-{}", space);
+        check_golden(
+            &case,
+            proc::histogram(lena(), Some(space), options, &default_style),
+        );
+    }
+}
+
+#[test]
+fn histogram2d() {
+    check_golden(
+        "histogram2d",
+        proc::histogram2d(
+            lena(),
+            &ChannelPair::ALL,
+            ColorOptions::default(),
+            AxisScale::Log,
+        ),
+    );
+}
+
+#[test]
+fn equalize() {
+    let options = ColorOptions::default();
+    check_golden(
+        "equalize",
+        proc::equalize(lena(), false, None, options, None),
+    );
+    check_golden(
+        "equalize-gray",
+        proc::equalize(lena(), true, None, options, None),
+    );
+    check_golden(
+        "equalize-rgb",
+        proc::equalize(lena(), false, Some(ColorSpace::Rgb), options, None),
+    );
+    let clahe = Some(ClaheParams::default());
+    check_golden(
+        "equalize-clahe-gray",
+        proc::equalize(lena(), true, None, options, clahe),
+    );
+    check_golden(
+        "equalize-clahe-lab",
+        proc::equalize(lena(), false, Some(ColorSpace::Lab), options, clahe),
+    );
+}
+
+#[test]
+fn match_histogram() {
+    let options = ColorOptions::default();
+    let reference = || {
+        let image = image::load_from_memory(include_bytes!("../res/lena.jpg")).unwrap();
+        image
+            .crop_imm(256, 0, 256, 256)
+            .resize_exact(32, 32, FilterType::Triangle)
+    };
+    check_golden(
+        "match",
+        proc::match_histogram(lena(), MatchTarget::Image(reference()), None, options),
+    );
+    check_golden(
+        "match-gaussian-hsv",
+        proc::match_histogram(
+            lena(),
+            MatchTarget::Curve(TargetCurve::Gaussian),
+            Some(ColorSpace::Hsv),
+            options,
+        ),
+    );
+}
+
+#[test]
+fn invert() {
+    check_golden("invert", proc::invert(lena()));
+}
+
+#[test]
+fn complement() {
+    check_golden("complement", proc::complement(lena()));
+}
+
+#[test]
+fn lut() {
+    check_golden("gamma", proc::gamma(lena(), 2.2));
+    let levels = Levels {
+        input: (10, 240),
+        output: (0, 255),
+        gamma: 1.1,
+    };
+    check_golden("levels", proc::levels(lena(), &levels));
+    let points = [(0, 0), (64, 40), (192, 220), (255, 255)];
+    check_golden("curves", proc::curves(lena(), &points));
+}
+
+#[test]
+fn lut3d() {
+    // Warm look: red lifted, blue pulled towards the luminance.
+    let look = Lut3d::from_fn(9, |[r, g, b]| {
+        let y = 0.299 * r + 0.587 * g + 0.114 * b;
+        [r.powf(0.8), g, 0.5 * b + 0.5 * y]
+    });
+    check_golden(
+        "lut3d",
+        proc::lut3d(lena(), &look, Interpolation::Tetrahedral),
+    );
+    check_golden(
+        "lut3d-trilinear",
+        proc::lut3d(lena(), &look, Interpolation::Trilinear),
+    );
+}
+
+#[test]
+fn filter() {
+    let reflect = Border::new(BorderMode::Reflect);
+    let gaussian = Filter::Gaussian {
+        radius: 2,
+        sigma: 1.1,
+    };
+    check_golden("filter-gaussian", proc::filter(lena(), &gaussian, reflect));
+    let median = Filter::Median { radius: 2 };
+    check_golden(
+        "filter-median",
+        proc::filter(lena(), &median, Border::default()),
+    );
+    let bilateral = Filter::Bilateral {
+        radius: 3,
+        sigma_space: 2.0,
+        sigma_range: 25.0,
+    };
+    check_golden(
+        "filter-bilateral",
+        proc::filter(lena(), &bilateral, reflect),
+    );
+}
+
+#[test]
+fn convolve() {
+    let sobel = Kernel::parse("-1 0 1\n-2 0 2\n-1 0 1").unwrap();
+    let reflect = Border::new(BorderMode::Reflect);
+    check_golden(
+        "convolve",
+        proc::convolve(lena(), &sobel, 1.0, 128.0, reflect, false),
+    );
+    let sharpen = Kernel::parse("[[0, -1, 0], [-1, 5, -1], [0, -1, 0]]").unwrap();
+    check_golden(
+        "convolve-per-channel",
+        proc::convolve(lena(), &sharpen, 1.0, 0.0, Border::default(), true),
+    );
+}
+
+#[test]
+fn edges() {
+    let reflect = Border::new(BorderMode::Reflect);
+    let (images, thresholds) = proc::edges(lena(), EdgeOperator::Canny, None, None, None, reflect);
+    let Some(EdgeThresholds::Canny { low, high }) = thresholds else {
+        panic!("no Canny thresholds: {:?}", thresholds);
+    };
+    assert!(0.0 < low && low < high);
+    check_golden("edges-canny", images);
+    let (images, thresholds) = proc::edges(lena(), EdgeOperator::Sobel, None, None, None, reflect);
+    assert_eq!(thresholds, None);
+    check_golden("edges-sobel", images);
+    let (images, thresholds) =
+        proc::edges(lena(), EdgeOperator::Log, Some(2.0), None, None, reflect);
+    assert!(matches!(thresholds, Some(EdgeThresholds::ZeroCrossing(t)) if t > 0.0));
+    check_golden("edges-log", images);
+}
+
+#[test]
+fn sharpen() {
+    let unsharp = Sharpen {
+        amount: 1.5,
+        ..Sharpen::default()
+    };
+    check_golden(
+        "sharpen-unsharp",
+        proc::sharpen(
+            lena(),
+            &unsharp,
+            None,
+            ColorOptions::default(),
+            Border::default(),
+        ),
+    );
+    let laplacian = Sharpen {
+        method: SharpenMethod::Laplacian,
+        threshold: 4.0,
+        ..Sharpen::default()
+    };
+    check_golden(
+        "sharpen-laplacian-lab",
+        proc::sharpen(
+            lena(),
+            &laplacian,
+            Some(ColorSpace::Lab),
+            ColorOptions::default(),
+            Border::new(BorderMode::Reflect),
+        ),
+    );
+}
+
+#[test]
+fn noise() {
+    let (images, quality) = proc::noise(lena(), Noise::default(), 1);
+    assert!(quality.psnr < 25.0 && quality.ssim < 0.9);
+    check_golden("noise-gaussian", images);
+    let salt_and_pepper = Noise::new(NoiseKind::SaltAndPepper);
+    let (images, _) = proc::noise(lena(), salt_and_pepper, 1);
+    check_golden("noise-salt-and-pepper", images);
+}
+
+#[test]
+fn denoise() {
+    let (images, report) = proc::denoise(
+        lena(),
+        Noise::default(),
+        1,
+        &DenoiseMethod::ALL,
+        Border::new(BorderMode::Reflect),
+    );
+    let names: Vec<&str> = report.quality.iter().map(|(n, _)| n.as_str()).collect();
+    assert_eq!(names, ["noisy", "median", "bilateral", "nlm", "wiener"]);
+    let noisy = report.quality[0].1;
+    assert!(report.quality[1..].iter().all(|(_, q)| q.psnr > noisy.psnr));
+    check_golden("denoise", images);
+}
+
+#[test]
+fn roundtrip() {
+    let (images, error) = proc::roundtrip(lena(), ColorSpace::Hsi, ColorOptions::default());
+    assert_eq!(error.max, [0, 0, 0]);
+    check_golden("roundtrip-hsi", images);
+}